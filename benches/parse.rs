@@ -0,0 +1,125 @@
+//! Throughput of the per-row hot path: parse → validate → serialize, with no network.
+//!
+//! Run with `cargo bench`; compare runs before and after a reader change with
+//! `cargo bench -- --save-baseline before` and `cargo bench -- --baseline before`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use halo_action_importer::{
+    domain::models::action_object::{ActionDate, ActionObject},
+    inbound::file::{ColumnMapping, Reader, csv::Csv, excel::Excel},
+};
+use rust_xlsxwriter::Workbook;
+
+/// Rows in each synthetic file.
+const ROWS: usize = 10_000;
+
+const HEADERS: [&str; 5] = ["requestId", "actionDate", "note", "actionWho", "CFactionId"];
+
+/// `ACTION_ID_CUSTOM_FIELD_ID` the rows are serialized with.
+const ACTION_ID_FIELD_ID: u32 = 123;
+
+/// One synthetic row, with a note long enough to look like real action text.
+fn row(idx: usize) -> [String; 5] {
+    [
+        (2_000 + idx % 500).to_string(),
+        format!("2024-01-{:02}T{:02}:30:00", idx % 28 + 1, idx % 24),
+        format!(
+            "Called the customer back about ticket {} and confirmed the fix is working",
+            idx
+        ),
+        "Sample Agent".to_string(),
+        (100_000 + idx).to_string(),
+    ]
+}
+
+fn csv_buffer(rows: usize) -> Vec<u8> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADERS).unwrap();
+    for idx in 0..rows {
+        writer.write_record(row(idx)).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
+
+fn xlsx_buffer(rows: usize) -> Vec<u8> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    for (col, header) in HEADERS.iter().enumerate() {
+        sheet.write_string(0, col as u16, *header).unwrap();
+    }
+    for idx in 0..rows {
+        let [ticket_id, date, note, who, action_id] = row(idx);
+        let excel_row = idx as u32 + 1;
+        // Numbers as numeric cells, the way Halo exports them
+        sheet
+            .write_number(excel_row, 0, ticket_id.parse::<f64>().unwrap())
+            .unwrap();
+        sheet.write_string(excel_row, 1, date).unwrap();
+        sheet.write_string(excel_row, 2, note).unwrap();
+        sheet.write_string(excel_row, 3, who).unwrap();
+        sheet
+            .write_number(excel_row, 4, action_id.parse::<f64>().unwrap())
+            .unwrap();
+    }
+    workbook.save_to_buffer().unwrap()
+}
+
+/// Validates and serializes every parsed row like a POST body, returning the bytes written.
+fn serialize_rows(rows: impl Iterator<Item = anyhow::Result<ActionObject>>) -> usize {
+    rows.map(|row| {
+        let action = row.unwrap();
+        action.action_id.cfactionid().unwrap();
+        serde_json::to_vec(&action.payload(ACTION_ID_FIELD_ID, &[]))
+            .unwrap()
+            .len()
+    })
+    .sum()
+}
+
+fn bench_readers(c: &mut Criterion) {
+    let mapping = ColumnMapping::default();
+    let csv = csv_buffer(ROWS);
+    let xlsx = xlsx_buffer(ROWS);
+
+    let mut group = c.benchmark_group("parse_validate_serialize");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(20);
+    group.bench_with_input(BenchmarkId::new("csv", ROWS), &csv, |b, csv| {
+        b.iter(|| {
+            let iter =
+                <Reader as Csv>::csv_action_iter_from_reader(csv.as_slice(), "bench.csv", &mapping);
+            black_box(serialize_rows(iter))
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("xlsx", ROWS), &xlsx, |b, xlsx| {
+        b.iter(|| {
+            let iter = <Reader as Excel>::excel_action_iter_from_bytes(
+                xlsx.clone(),
+                "bench.xlsx",
+                &mapping,
+            )
+            .unwrap();
+            black_box(serialize_rows(iter))
+        })
+    });
+    group.finish();
+}
+
+fn bench_date_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("action_date_parse");
+    for (name, value) in [
+        ("naive", "2024-01-15T09:30:00"),
+        ("fractional", "2024-01-15T09:30:00.123"),
+        ("space_separated", "2024-01-15 09:30:00"),
+        ("utc_suffix", "2024-01-15T09:30:00Z"),
+        ("rfc3339_offset", "2024-01-15T09:30:00+02:00"),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), value, |b, value| {
+            b.iter(|| ActionDate::parse(black_box(value)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_readers, bench_date_parse);
+criterion_main!(benches);
@@ -1,58 +1,810 @@
 use anyhow::Context;
+use futures::{FutureExt, StreamExt};
 use halo_action_importer::{
-    config::Config,
+    config::{BadDatePolicy, Config, FileOrder, LeadingZeros, NOTE_DATE_PLACEHOLDER},
     domain::importer::{
-        ImportSummary, SetupResult, log_summary, process_csv_file, process_excel_file, setup,
+        FailedActions, FailureThreshold, FileResult, ImportManifest, ImportSummary, LatencySamples,
+        PastRun, ProcessOptions, ProcessingStats, RowSampler, RunEstimator, RunGuard, SetupResult,
+        Shutdown, SummaryFormat, check_headers, combined_summary, confirm_import, find_manifests,
+        log_summary, prescan_files, process_input_file,
+        processor::{format_number, prepare_action},
+        replay, run_history, run_self_test, run_staging_phase, setup,
+        setup::RunContext,
+        shutdown::SHUTDOWN_REASON,
+        verify_file,
+    },
+    domain::models::action_object::{ActionDate, ActionObject, strip_leading_zeros},
+    inbound::{
+        client::ReportClient,
+        file::{
+            ColumnMapping, InputKind, InputWatcher, WatchEvent,
+            archive::{extract_archive, remove_extracted_archives},
+            watch::{is_input_file, wait_until_written},
+        },
+        report_cache::{CachedReport, ReportDiff},
+    },
+    outbound::client::{
+        action::ActionClient,
+        auth::AuthClient,
+        http::{RetryStats, build_http_client},
+        webhook::WebhookClient,
     },
 };
-use std::ffi::OsStr;
-use std::time::Instant;
-use tracing::{error, info};
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, error, info, info_span, warn};
 
-fn format_number(n: usize) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    let chars: Vec<char> = s.chars().collect();
-    for (i, &ch) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i).is_multiple_of(3) {
-            result.push(',');
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn validate_headers(input_path: &str) -> anyhow::Result<()> {
+    let result = check_all_headers(input_path);
+    remove_extracted_archives();
+    result
+}
+
+fn check_all_headers(input_path: &str) -> anyhow::Result<()> {
+    let discovered = setup::discover_files(input_path, FileOrder::Name)?;
+    let files = discovered.files;
+    let mapping = ColumnMapping::default();
+    let mut out = std::io::stdout();
+    for skipped in &discovered.skipped {
+        println!("Unreadable: {}", skipped);
+    }
+    let mut failed = discovered.skipped.len();
+    for (path, file_name) in &files {
+        match check_headers(path, &mapping) {
+            Ok(check) => {
+                check.write_to(&mut out)?;
+                if !check.is_ok() {
+                    failed += 1;
+                }
+            }
+            Err(e) => {
+                println!("File: {}\nUnreadable: {:#}", file_name, e);
+                failed += 1;
+            }
         }
-        result.push(ch);
     }
-    result
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} file(s) are missing required columns or could not be read",
+            format_number(failed),
+            format_number(files.len() + discovered.skipped.len())
+        );
+    }
+    println!(
+        "All {} file(s) have every required column",
+        format_number(files.len())
+    );
+    Ok(())
+}
+
+fn summarize_past_runs(path: &Path) -> anyhow::Result<()> {
+    tracing_subscriber::fmt().with_target(false).init();
+    let manifests = find_manifests(path)?;
+    if manifests.is_empty() {
+        anyhow::bail!(
+            "No import manifests (*{}) found in {}",
+            run_history::MANIFEST_SUFFIX,
+            path.display()
+        );
+    }
+    let runs = manifests
+        .iter()
+        .map(|manifest| PastRun::read(manifest))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for run in &runs {
+        if run.has_rejects {
+            info!(
+                "Run {}: {} imported, {} failed",
+                run.run,
+                format_number(run.imported),
+                format_number(run.failed())
+            );
+        } else {
+            info!(
+                "Run {}: {} imported (no rejects file, so its failures aren't counted)",
+                run.run,
+                format_number(run.imported)
+            );
+        }
+    }
+    info!("Skipped rows, unreadable files, and retries aren't recorded in run artifacts");
+    log_summary(combined_summary(&runs), false);
+    Ok(())
+}
+
+fn replay_log(log_path: &Path) -> anyhow::Result<()> {
+    tracing_subscriber::fmt().with_target(false).init();
+    let run = replay::ReplayedRun::read(log_path)?;
+    if run.actions.is_empty() && run.imported() == 0 && run.skipped() == 0 {
+        anyhow::bail!(
+            "No imported, skipped, or failed actions found in log file: {}",
+            log_path.display()
+        );
+    }
+    let replay_path = replay::replay_path_for(log_path);
+    run.write_actions(&replay_path)?;
+    info!(
+        "Run {}: {} action(s) named in the log written to {}",
+        run.run_id.as_deref().unwrap_or("(unknown run ID)"),
+        format_number(run.actions.len()),
+        replay_path.display()
+    );
+    info!("Retries, latencies, and unreadable files aren't rebuilt from the log");
+    log_summary(run.summary(), false);
+    Ok(())
+}
+
+/// The argument following `name`, if `name` was given. A flag given without a value is an
+/// error naming what it `expects`.
+fn flag_value(args: &[String], name: &str, expects: &str) -> anyhow::Result<Option<String>> {
+    let Some(i) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    match args.get(i + 1) {
+        Some(value) => Ok(Some(value.clone())),
+        None => anyhow::bail!("{} requires {}", name, expects),
+    }
+}
+
+async fn compare_reports(args: &[String], saved_path: &Path) -> anyhow::Result<()> {
+    let flag = |name: &str, expects: &str| flag_value(args, name, expects);
+    let mut config = Config::from_profile(flag("--profile", "a profile name")?.as_deref())
+        .context("Failed to load configuration from environment variables")?;
+    if let Some(output_dir) = flag("--output-dir", "a directory")? {
+        config.output_dir = output_dir.into();
+    }
+    // The reports are always fetched fresh; a cached copy would compare the cache with itself
+    config.report_cache_secs = None;
+    let run = RunContext::new(config.output_dir.clone());
+    setup::setup_logging(false, config.log_level, config.compress_old_logs_days, &run)?;
+    warn_if_insecure(&config);
+
+    let saved_reports = CachedReport::read_all(saved_path)?;
+    if saved_reports.is_empty() {
+        anyhow::bail!("No cached reports found in {}", saved_path.display());
+    }
+    let oldest_fetch = saved_reports.iter().map(|report| report.fetched_at).min();
+    // Saved as the reports listed them, compared in the current LEADING_ZEROS mode like a cache hit
+    let saved: HashSet<String> = saved_reports
+        .into_iter()
+        .flat_map(|report| report.ids)
+        .map(|id| match config.leading_zeros {
+            LeadingZeros::Strip => strip_leading_zeros(&id).to_string(),
+            LeadingZeros::Keep => id,
+        })
+        .collect();
+
+    let http_client = build_http_client(&config)?;
+    let retry_stats = Arc::new(RetryStats::default());
+    let auth_client = Arc::new(AuthClient::new(config.clone(), http_client.clone()));
+    let report_client = ReportClient::new(config.clone(), http_client, auth_client, retry_stats);
+    let (current, _) = report_client
+        .get_existing_action_ids()
+        .await
+        .context("Failed to fetch existing action IDs from report")?;
+
+    let diff = ReportDiff::new(&saved, &current);
+    info!(
+        "Saved reports fetched {}: {} action IDs. Now: {} action IDs",
+        oldest_fetch
+            .map(|fetched_at| fetched_at.to_rfc3339())
+            .unwrap_or_default(),
+        format_number(saved.len()),
+        format_number(current.len())
+    );
+    info!(
+        "{} added, {} removed, {} unchanged",
+        format_number(diff.added.len()),
+        format_number(diff.removed.len()),
+        format_number(diff.unchanged)
+    );
+    if let Some(output) = flag("--compare-output", "a file path")? {
+        diff.write(Path::new(&output))?;
+        info!("Added and removed action IDs written to {}", output);
+    }
+    Ok(())
+}
+
+fn time_budget_reason(config: &Config) -> String {
+    format!(
+        "MAX_RUN_SECS time budget of {}s reached",
+        config.max_run_secs.unwrap_or_default()
+    )
+}
+
+fn warn_if_insecure(config: &Config) {
+    for url in config.insecure_http_urls() {
+        warn!(
+            "INSECURE: {} uses plain http (ALLOW_INSECURE_HTTP=true), so the client secret, token, and action data are sent unencrypted",
+            url
+        );
+    }
+}
+
+/// `NOTE_PREFIX`/`NOTE_SUFFIX` with `{date}` replaced by today's local date.
+fn expand_note_date(template: &str) -> String {
+    template.replace(
+        NOTE_DATE_PLACEHOLDER,
+        &chrono::Local::now().format("%Y-%m-%d").to_string(),
+    )
+}
+
+async fn post_single_action(args: &[String]) -> anyhow::Result<()> {
+    let flag = |name: &str, expects: &str| flag_value(args, name, expects);
+    let required = |name: &str| {
+        flag(name, "a value")?.with_context(|| {
+            format!(
+                "post requires {}: post --ticket-id <id> --action-id <id> --note <text> --who <name> [--date <date>] [--outcome <outcome>]",
+                name
+            )
+        })
+    };
+    let mut record = HashMap::from([
+        ("ticket_id".to_string(), required("--ticket-id")?),
+        ("action_id".to_string(), required("--action-id")?),
+        ("note".to_string(), required("--note")?),
+        ("actionwho".to_string(), required("--who")?),
+    ]);
+    if let Some(date) = flag("--date", "a date")? {
+        record.insert("actiondate".to_string(), date);
+    }
+    let outcome = flag("--outcome", "an outcome")?;
+    if let Some(outcome) = &outcome {
+        record.insert("outcome".to_string(), outcome.clone());
+    }
+
+    let mut config = Config::from_profile(flag("--profile", "a profile name")?.as_deref())
+        .context("Failed to load configuration from environment variables")?;
+    if let Some(output_dir) = flag("--output-dir", "a directory")? {
+        config.output_dir = output_dir.into();
+    }
+    // The payload and response are only logged at debug
+    config.log_level = tracing::Level::DEBUG;
+    let run = RunContext::new(config.output_dir.clone());
+    setup::setup_logging(false, config.log_level, config.compress_old_logs_days, &run)?;
+    warn_if_insecure(&config);
+
+    let (ticket_id_map, agent_map) = setup::load_maps(&config)?;
+    let options = ProcessOptions {
+        max_note_len: config.max_note_len,
+        note_length_mode: config.max_note_len_mode,
+        column_mapping: ColumnMapping::from_config(&config),
+        ticket_id_map,
+        agent_map,
+        source_timezone: config.source_timezone,
+        empty_note: config.empty_note,
+        required_fields: config.required_fields.clone(),
+        bad_date_policy: config.bad_date_policy,
+        note_prefix: expand_note_date(&config.note_prefix),
+        note_suffix: expand_note_date(&config.note_suffix),
+        ..ProcessOptions::default()
+    };
+
+    // Built and readied the same way as a file row, so the payload matches an import;
+    // `--outcome` stands in for a file's outcome column
+    let mut action = ActionObject::from_record(record)?;
+    options
+        .column_mapping
+        .apply_defaults(&mut action, outcome.is_some());
+    if let Some(date_error) = action.date_error() {
+        match config.bad_date_policy {
+            BadDatePolicy::Fail => anyhow::bail!("--date: {}", date_error),
+            BadDatePolicy::Null => warn!("--date: {}, posting without a date", date_error),
+            BadDatePolicy::Now => {
+                warn!("--date: {}, dating the action now", date_error);
+                action.actiondate = Some(ActionDate::utc(chrono::Utc::now().naive_utc()));
+            }
+        }
+    }
+    prepare_action(&mut action, &options).map_err(anyhow::Error::msg)?;
+
+    let http_client = build_http_client(&config)?;
+    let auth_client = Arc::new(AuthClient::new(config.clone(), http_client.clone()));
+    let action_client = ActionClient::new(
+        config,
+        http_client,
+        auth_client,
+        Arc::new(RetryStats::default()),
+    );
+    let response = action_client.post_single(&action).await?;
+    println!("POST {}", response.endpoint);
+    println!("Status: {}", response.status);
+    println!("Request ID: {}", response.request_id);
+    println!("Response body:\n{}", response.body);
+    if !response.is_success() {
+        anyhow::bail!(
+            "POST for action ID {} failed with status {}",
+            action.action_id(),
+            response.status
+        );
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args: Vec<String> = std::env::args().collect();
-    let only_parse = args
-        .iter()
-        .any(|arg| arg == "--only-parse" || arg == "--op");
-    let input_path = args
-        .iter()
-        .position(|arg| arg == "--input")
-        .and_then(|i| args.get(i + 1))
-        .map(|s| s.as_str())
-        .unwrap_or("input");
-    let batch_size = args
-        .iter()
-        .position(|arg| arg == "--batch")
-        .and_then(|i| args.get(i + 1))
+    // Runs before config is loaded so it works without credentials or a .env file
+    if args.iter().any(|arg| arg == "--self-test") {
+        return run_self_test();
+    }
+    // `verify-file <path>` also needs no credentials, token, or report fetch
+    if args.get(1).is_some_and(|arg| arg == "verify-file") {
+        let path = args
+            .get(2)
+            .context("verify-file requires a path: verify-file <path>")?;
+        let report = verify_file(Path::new(path), &ColumnMapping::default())?;
+        report.write_to(&mut std::io::stdout())?;
+        if !report.is_ok() {
+            anyhow::bail!(
+                "{} of {} row(s) failed to parse",
+                format_number(report.failures.len()),
+                format_number(report.total_rows)
+            );
+        }
+        return Ok(());
+    }
+    // Fetches the reports now and diffs them against a saved fetch, without importing
+    let flag = |name: &str, expects: &str| flag_value(&args, name, expects);
+    if let Some(path) = flag("--compare-reports", "a report cache file or directory")? {
+        return compare_reports(&args, Path::new(&path)).await;
+    }
+    // `post ...` sends one hand-built action, for reproducing a single failing row
+    if args.get(1).is_some_and(|arg| arg == "post") {
+        return post_single_action(&args).await;
+    }
+    // Offline implies parse-only: there is no token to post with
+    let offline = args.iter().any(|arg| arg == "--offline");
+    let only_parse = offline
+        || args
+            .iter()
+            .any(|arg| arg == "--only-parse" || arg == "--op");
+    let input_path = flag("--input", "an input directory")?.unwrap_or_else(|| "input".to_string());
+    let input_path = input_path.as_str();
+    // Header row only, so a broken export is caught before any credentials or rows are read
+    if args.iter().any(|arg| arg == "--validate-only-headers") {
+        return validate_headers(input_path);
+    }
+    // Rebuilt from past runs' artifacts, without reading any input or contacting Halo
+    if let Some(path) = flag("--summary-only", "an import manifest or output directory")? {
+        return summarize_past_runs(Path::new(&path));
+    }
+    // Rebuilt from a past run's log, for post-mortems without the input files
+    if let Some(path) = flag("--replay-log", "a log file")? {
+        return replay_log(Path::new(&path));
+    }
+    let batch_size = flag("--batch", "a batch size")?
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(1);
-    let config =
-        Config::from_env().context("Failed to load configuration from environment variables")?;
+    let fail_fast = args.iter().any(|arg| arg == "--fail-fast");
+    let max_rows = flag("--max-rows", "a number of rows")?
+        .map(|s| {
+            s.parse::<usize>()
+                .with_context(|| format!("invalid value '{}' for --max-rows", s))
+        })
+        .transpose()?;
+    let estimate_rows = flag("--estimate", "a number of rows to time")?
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|rows| *rows > 0)
+                .with_context(|| format!("invalid value '{}' for --estimate", value))
+        })
+        .transpose()?;
+    let assume_yes = args.iter().any(|arg| arg == "--yes");
+    let prescan_csv = args.iter().any(|arg| arg == "--prescan-csv");
+    let prescan_only = prescan_csv || args.iter().any(|arg| arg == "--prescan");
+    let quiet = args.iter().any(|arg| arg == "--quiet");
+    let no_manifest = args.iter().any(|arg| arg == "--no-manifest");
+    let watch = args.iter().any(|arg| arg == "--watch");
+    let summary_format = flag("--summary-format", "text, json, or csv")?
+        .map(|value| SummaryFormat::parse(&value))
+        .transpose()?
+        .unwrap_or_default();
+    if watch && prescan_only {
+        anyhow::bail!("--watch can't be combined with --prescan");
+    }
+    // The estimate times real POSTs against a pre-scan of every row
+    if estimate_rows.is_some() && (only_parse || prescan_only || watch) {
+        anyhow::bail!("--estimate can't be combined with --only-parse, --prescan, or --watch");
+    }
+    if watch && input_path.starts_with(setup::SQLITE_URL_PREFIX) {
+        anyhow::bail!("--watch needs an input directory, not a sqlite:// database");
+    }
+    // Files that arrive while watching are imported without a prompt of their own
+    if watch && !only_parse && !assume_yes {
+        anyhow::bail!("--watch posts files as they arrive, so it requires --yes");
+    }
+    let continue_from_file = flag("--continue-from-file", "a file name")?;
+    let profile = flag("--profile", "a profile name")?;
+    let mut config = if offline {
+        Config::offline_from_profile(profile.as_deref())
+    } else {
+        Config::from_profile(profile.as_deref())
+    }
+    .context("Failed to load configuration from environment variables")?;
+    if let Some(output_dir) = flag("--output-dir", "a directory")? {
+        config.output_dir = output_dir.into();
+    }
+    if args.iter().any(|arg| arg == "--allow-empty-report") {
+        config.allow_empty_report = true;
+    }
 
-    setup::setup_logging(only_parse, config.log_level)?;
+    let run = RunContext::new(config.output_dir.clone());
+    setup::setup_logging(
+        only_parse,
+        config.log_level,
+        config.compress_old_logs_days,
+        &run,
+    )?;
+    warn_if_insecure(&config);
 
-    let SetupResult {
+    let cli = Cli {
+        only_parse,
+        input_path: input_path.to_string(),
+        continue_from_file,
+        batch_size,
+        fail_fast,
+        max_rows,
+        estimate_rows,
+        assume_yes,
+        prescan_only,
+        prescan_csv,
+        quiet,
+        watch,
+        // Parse-only runs create nothing, so there is nothing to record
+        manifest_path: (!only_parse && !no_manifest)
+            .then(|| run.artifact_path(run_history::MANIFEST_SUFFIX)),
+        summary_format,
+    };
+    let noop_exit_code = config.noop_exit_code;
+    // Every log line of the run carries the run ID via this span
+    let span = info_span!("run", run_id = %run.run_id);
+    let outcome = run_import(config, cli, run).instrument(span).await;
+    remove_extracted_archives();
+    let outcome = outcome?;
+    if let (RunOutcome::NothingToImport, Some(code)) = (outcome, noop_exit_code) {
+        info!("Exiting with NOOP_EXIT_CODE {}", code);
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// How a run that didn't fail ended.
+enum RunOutcome {
+    Completed,
+    /// No files, or every row already existed (`NOOP_EXIT_CODE`).
+    NothingToImport,
+}
+
+/// Command-line flags that shape a run, parsed in `main`.
+struct Cli {
+    only_parse: bool,
+    input_path: String,
+    /// File name to start at, skipping the files ordered before it.
+    continue_from_file: Option<String>,
+    batch_size: usize,
+    fail_fast: bool,
+    max_rows: Option<usize>,
+    /// Rows to time before estimating the run's duration and asking to continue.
+    estimate_rows: Option<usize>,
+    assume_yes: bool,
+    prescan_only: bool,
+    /// Also write the pre-scan's per-file breakdown as CSV.
+    prescan_csv: bool,
+    quiet: bool,
+    /// Keep running after the initial files and import new ones as they land.
+    watch: bool,
+    /// Where to write the imported-actions manifest, unless `--no-manifest`.
+    manifest_path: Option<PathBuf>,
+    /// Machine-readable copy of the end-of-run summary, besides the log lines.
+    summary_format: SummaryFormat,
+}
+
+/// Totals across the files a run has finished.
+#[derive(Default)]
+struct RunTotals {
+    processed: usize,
+    imported: usize,
+    skipped: usize,
+    sampled_out: usize,
+    skipped_empty_note: usize,
+    failed: FailedActions,
+    /// Files that couldn't be read or panicked, with why.
+    skipped_files: Vec<String>,
+    row_latencies: LatencySamples,
+}
+
+impl RunTotals {
+    fn add(&mut self, stats: ProcessingStats) {
+        self.processed += stats.processed;
+        self.imported += stats.imported;
+        self.skipped += stats.skipped;
+        self.sampled_out += stats.sampled_out;
+        self.skipped_empty_note += stats.skipped_empty_note;
+        self.failed.append(stats.failed);
+        self.row_latencies.extend(&stats.row_times);
+    }
+
+    /// Rows imported, skipped, or failed so far.
+    fn finished(&self) -> usize {
+        self.imported + self.skipped + self.skipped_empty_note + self.failed.len()
+    }
+}
+
+/// Why a run stops after a file.
+enum RunEnd {
+    Aborted(String),
+    StoppedEarly(String),
+}
+
+fn run_end(stats: &ProcessingStats, max_rows: Option<usize>, config: &Config) -> Option<RunEnd> {
+    if let Some(reason) = &stats.aborted {
+        return Some(RunEnd::Aborted(reason.clone()));
+    }
+    if stats.row_cap_reached
+        && let Some(cap) = max_rows
+    {
+        return Some(RunEnd::StoppedEarly(row_cap_reason(cap)));
+    }
+    if stats.deadline_reached {
+        return Some(RunEnd::StoppedEarly(time_budget_reason(config)));
+    }
+    if stats.estimate_declined {
+        return Some(RunEnd::StoppedEarly(
+            "stopped by the operator after the --estimate sample".to_string(),
+        ));
+    }
+    if stats.shutdown_requested {
+        return Some(RunEnd::StoppedEarly(SHUTDOWN_REASON.to_string()));
+    }
+    None
+}
+
+fn row_cap_reason(cap: usize) -> String {
+    format!("--max-rows cap of {} reached", format_number(cap))
+}
+
+/// The failure-ratio check for a file, given the run's rows before it.
+fn failure_threshold(
+    config: &Config,
+    finished_before: usize,
+    failed_before: usize,
+) -> Option<FailureThreshold> {
+    config.abort_failure_ratio.map(|ratio| FailureThreshold {
+        ratio,
+        min_sample: config.abort_failure_min_sample,
+        finished_before,
+        failed_before,
+    })
+}
+
+/// Processes one input file, catching a panic so one corrupt file can't abort the whole
+/// batch. `None` for a file type the importer doesn't read; `Err` is the file's entry in
+/// the summary's skipped-file list.
+#[allow(clippy::too_many_arguments)]
+async fn process_file(
+    file_path: &Path,
+    file_name: &str,
+    existing_ids: &HashSet<String>,
+    action_client: Option<&ActionClient>,
+    sheet_times: &mut Vec<f64>,
+    sheet_number: usize,
+    total_sheets: usize,
+    options: &ProcessOptions,
+) -> Option<Result<FileResult, String>> {
+    let result = AssertUnwindSafe(process_input_file(
+        file_path,
+        file_name,
         existing_ids,
         action_client,
+        sheet_times,
+        sheet_number,
+        total_sheets,
+        options,
+    ))
+    .catch_unwind()
+    .await;
+    Some(match result {
+        Ok(None) => return None,
+        Ok(Some(Ok(result))) => Ok(result),
+        Ok(Some(Err(e))) => {
+            error!("Failed to read file {:?}: {}", file_name, e);
+            Err(format!("{:?}: {}", file_name, e))
+        }
+        Err(panic) => {
+            let cause = panic_message(panic.as_ref());
+            error!(
+                "Processing file {:?} panicked, skipping it: {}",
+                file_name, cause
+            );
+            Err(format!("{:?}: panicked: {}", file_name, cause))
+        }
+    })
+}
+
+async fn run_import(config: Config, cli: Cli, run: RunContext) -> anyhow::Result<RunOutcome> {
+    let Cli {
+        only_parse,
+        input_path,
+        continue_from_file,
+        batch_size,
+        fail_fast,
+        max_rows,
+        estimate_rows,
+        assume_yes,
+        prescan_only,
+        prescan_csv,
+        quiet,
+        watch,
+        manifest_path,
+        summary_format,
+    } = cli;
+    // The budget covers the whole run, including the report fetch before processing
+    let deadline = config
+        .max_run_secs
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+    // From here on a SIGTERM ends the run like MAX_RUN_SECS does, so an orchestrator
+    // stopping the container still gets the summary, rejects file, and notification
+    let shutdown = Arc::new(Shutdown::default());
+    shutdown.listen_for_sigterm()?;
+    let input_path = input_path.as_str();
+
+    let SetupResult {
+        mut existing_ids,
+        action_client,
         files_to_process,
+        skipped_files,
         auth_client: _,
-    } = setup::setup(&config, only_parse, input_path).await?;
+        http_client,
+        retry_stats,
+        ticket_id_map,
+        agent_map,
+        report_stats,
+    } = setup::setup(
+        &config,
+        only_parse,
+        input_path,
+        continue_from_file.as_deref(),
+    )
+    .await?;
+
+    // An empty input directory is a no-op, not a failure, so scheduled runs exit cleanly
+    if files_to_process.is_empty() && !watch {
+        if !skipped_files.is_empty() {
+            anyhow::bail!(
+                "None of the input files could be read: {}",
+                skipped_files.join("; ")
+            );
+        }
+        return Ok(RunOutcome::NothingToImport);
+    }
+
+    let mut process_options = ProcessOptions {
+        only_parse,
+        batch_size,
+        ordered_concurrency: config.ordered_concurrency,
+        fail_fast,
+        max_note_len: config.max_note_len,
+        note_length_mode: config.max_note_len_mode,
+        max_batch_bytes: config.max_batch_bytes,
+        quiet,
+        csv_count_rows: config.csv_count_rows,
+        max_rows,
+        column_mapping: ColumnMapping::from_config(&config),
+        ticket_id_map,
+        agent_map,
+        sampler: config
+            .sample_rate
+            .map(|rate| RowSampler::new(rate, config.sample_seed)),
+        source_timezone: config.source_timezone,
+        // Created once the run is confirmed so pre-scans and declined runs leave no file
+        manifest: None,
+        max_failures_kept: Some(config.max_failures_kept),
+        rejects: None,
+        within_file_duplicate: config.within_file_duplicate,
+        empty_note: config.empty_note,
+        required_fields: config.required_fields.clone(),
+        bad_date_policy: config.bad_date_policy,
+        hooks: Default::default(),
+        failure_threshold: None,
+        sort_by_date: config.sort_by_date,
+        deadline,
+        estimator: None,
+        shutdown: Some(shutdown.clone()),
+        // Shared by the files processed at the same time under FILE_CONCURRENCY
+        run_guard: None,
+        note_prefix: expand_note_date(&config.note_prefix),
+        note_suffix: expand_note_date(&config.note_suffix),
+        source_location: config
+            .source_file_custom_field_id
+            .map(|_| config.source_file_detail),
+    };
+
+    if prescan_only {
+        info!(
+            "Pre-scan mode: parsing {} file(s) without posting",
+            format_number(files_to_process.len())
+        );
+        let scan = prescan_files(
+            &files_to_process,
+            &existing_ids,
+            &process_options.column_mapping,
+            process_options.bad_date_policy,
+        );
+        scan.log();
+        if prescan_csv {
+            let path = run.artifact_path("_prescan.csv");
+            scan.write_csv(&path)?;
+            info!("Wrote per-file pre-scan report to {}", path.display());
+        }
+        return Ok(RunOutcome::Completed);
+    }
+
+    if !only_parse {
+        confirm_import(&config, &files_to_process, &existing_ids, assume_yes)?;
+    }
+    if !only_parse && !files_to_process.is_empty() {
+        run_staging_phase(
+            &config,
+            &files_to_process,
+            &existing_ids,
+            &http_client,
+            retry_stats.clone(),
+            &process_options,
+            assume_yes,
+        )
+        .await?;
+    }
+    let rejects = manifest_path.as_deref().map(|path| {
+        Arc::new(run_history::RejectsWriter::new(
+            run_history::rejects_path_for(path),
+        ))
+    });
+    process_options.rejects = rejects.clone();
+    if let Some(path) = manifest_path {
+        let manifest = ImportManifest::create(&path)?;
+        info!("Recording imported actions to {}", path.display());
+        process_options.manifest = Some(Arc::new(manifest));
+    }
+
+    // Actions imported this run, added to the existing IDs between watched files so a
+    // later file repeating them skips them
+    let imported_ids: Arc<Mutex<Vec<String>>> = Arc::default();
+    let mut watcher = None;
+    if watch {
+        let ids = imported_ids.clone();
+        process_options.hooks = process_options.hooks.on_imported(move |action_id, _| {
+            ids.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(action_id.to_string());
+        });
+        // Started before the initial files are processed so none that land meanwhile are missed
+        watcher = Some(InputWatcher::start(Path::new(input_path))?);
+    }
+    // A file is imported at most once per run, however often it changes afterwards. Files
+    // from an archive are named `<archive>/<entry>`, so the archive counts as seen
+    let mut seen_files: HashSet<String> = files_to_process
+        .iter()
+        .filter_map(|(_, file_name)| file_name.split('/').next())
+        .map(str::to_string)
+        .collect();
 
-    let total_sheets = files_to_process.len();
+    let mut total_sheets = files_to_process.len();
     info!("Processing files from directory: {}", input_path);
     if batch_size > 1 {
         info!(
@@ -60,6 +812,48 @@ async fn main() -> anyhow::Result<()> {
             format_number(batch_size)
         );
     }
+    if let Some(cap) = max_rows {
+        info!(
+            "Row cap enabled: the run will stop after {} action(s) across all files",
+            format_number(cap)
+        );
+    }
+    if let Some(sampler) = &process_options.sampler {
+        info!(
+            "Sampling enabled: processing {:.1}% of rows (seed {})",
+            sampler.rate() * 100.0,
+            sampler.seed()
+        );
+    }
+    if let Some(rate) = config.trace_sample_rate
+        && !only_parse
+    {
+        if config.log_level == tracing::Level::TRACE {
+            info!(
+                "Trace sampling enabled: logging full payloads and responses for {:.2}% of POSTs",
+                rate * 100.0
+            );
+        } else {
+            warn!(
+                "TRACE_SAMPLE_RATE is set but LOG_LEVEL is not trace, so no POSTs will be traced"
+            );
+        }
+    }
+    if config.sort_by_date.is_some() {
+        warn!(
+            "SORT_BY_DATE is on: each sheet is read fully into memory and sorted before posting, so very large files need a lot of memory and nothing is posted until a sheet is read"
+        );
+    }
+    if fail_fast {
+        info!("Fail-fast enabled: the run will stop at the first failed import");
+    }
+    if let Some(ratio) = config.abort_failure_ratio {
+        info!(
+            "The run will abort if more than {:.1}% of rows fail, checked after {} rows",
+            ratio * 100.0,
+            format_number(config.abort_failure_min_sample)
+        );
+    }
     if only_parse {
         info!(
             "Starting parse-only import of {} file(s) (will test report fetching + file parsing)",
@@ -68,75 +862,342 @@ async fn main() -> anyhow::Result<()> {
     } else {
         info!("Starting import of {} file(s)", format_number(total_sheets));
     }
-    let mut total_actions_processed = 0;
-    let mut total_actions_imported = 0;
-    let mut total_actions_skipped = 0;
-    let mut skipped_files: Vec<String> = Vec::new();
-    let mut failed_imports: Vec<(String, String)> = Vec::new();
+    if let Some(sample_rows) = estimate_rows {
+        let scan = prescan_files(
+            &files_to_process,
+            &existing_ids,
+            &process_options.column_mapping,
+            process_options.bad_date_policy,
+        );
+        let expected_rows = max_rows.map_or(scan.to_post, |cap| scan.to_post.min(cap));
+        info!(
+            "Estimate enabled: timing the first {} of {} action(s) to post before asking to continue",
+            format_number(sample_rows.min(expected_rows)),
+            format_number(expected_rows)
+        );
+        process_options.estimator = Some(Arc::new(RunEstimator::new(
+            sample_rows,
+            expected_rows,
+            assume_yes,
+        )));
+    }
+    let mut totals = RunTotals {
+        failed: FailedActions::new(Some(config.max_failures_kept), rejects.clone()),
+        skipped_files,
+        ..RunTotals::default()
+    };
     let mut sheet_times: Vec<f64> = Vec::new();
+    let mut aborted: Option<String> = None;
+    let mut stopped_early: Option<String> = None;
+    let mut rows_left = max_rows;
     let start_time = Instant::now();
 
-    for (sheet_num, (file_path, file_name)) in files_to_process.iter().enumerate() {
-        let sheet_number = sheet_num + 1;
-        if let Some(ext) = file_path.extension().and_then(OsStr::to_str) {
-            let ext_lower = ext.to_lowercase();
-            let result = if ext_lower == "csv" {
-                process_csv_file(
-                    file_path,
-                    &existing_ids,
-                    action_client.as_ref(),
-                    &mut sheet_times,
-                    file_name,
-                    sheet_number,
-                    total_sheets,
-                    only_parse,
-                    batch_size,
-                )
-                .await
-            } else if ext_lower == "xlsx" || ext_lower == "xls" {
-                process_excel_file(
-                    file_path,
-                    &existing_ids,
-                    action_client.as_ref(),
-                    &mut sheet_times,
-                    sheet_number,
-                    total_sheets,
-                    only_parse,
-                    batch_size,
-                )
-                .await
-            } else {
-                continue;
-            };
-
+    // The initial files, then each watched file or the files extracted from a watched archive
+    let mut pending_files: VecDeque<(PathBuf, String)> = files_to_process.iter().cloned().collect();
+    let mut sheet_number = 0;
+    // The row cap needs each file to know what the files before it posted
+    if config.file_concurrency > 1 && max_rows.is_some() {
+        warn!("--max-rows processes files one at a time, so FILE_CONCURRENCY is ignored");
+    } else if config.file_concurrency > 1 && files_to_process.len() > 1 {
+        info!(
+            "File concurrency enabled: processing up to {} files at a time",
+            format_number(config.file_concurrency)
+        );
+        // Set once the run should end: files not started yet are left alone. Files already
+        // running finish after a stop, and are stopped by `run_guard` after an abort
+        let stopping = Cell::new(false);
+        // Shares the failure ratio across the files running at the same time. An abort in
+        // one stops the others before their next post; their queued rows count as not posted
+        let run_guard = Arc::new(RunGuard::default());
+        let file_options = ProcessOptions {
+            failure_threshold: failure_threshold(&config, 0, 0),
+            run_guard: Some(run_guard.clone()),
+            ..process_options.clone()
+        };
+        let mut results = futures::stream::iter(files_to_process.iter().enumerate())
+            .map(|(idx, (file_path, file_name))| {
+                let (stopping, run_guard, shutdown) = (&stopping, &run_guard, &shutdown);
+                let (existing_ids, action_client) = (&existing_ids, action_client.as_ref());
+                let file_options = &file_options;
+                async move {
+                    if stopping.get() || run_guard.aborted().is_some() || shutdown.requested() {
+                        return None;
+                    }
+                    // Every line logged while processing the file names it
+                    process_file(
+                        file_path,
+                        file_name,
+                        existing_ids,
+                        action_client,
+                        &mut Vec::new(),
+                        idx + 1,
+                        total_sheets,
+                        file_options,
+                    )
+                    .instrument(info_span!("file", name = %file_name))
+                    .await
+                }
+            })
+            .buffer_unordered(config.file_concurrency);
+        while let Some(result) = results.next().await {
             match result {
-                Ok(stats) => {
-                    total_actions_processed += stats.processed;
-                    total_actions_imported += stats.imported;
-                    total_actions_skipped += stats.skipped;
-                    failed_imports.extend(stats.failed);
+                Some(Ok(file)) => {
+                    // Recorded as files finish, which may not be the order they started
+                    sheet_times.push(file.duration.as_secs_f64());
+                    let end = run_end(&file.stats, max_rows, &config);
+                    totals.add(file.stats);
+                    match end {
+                        Some(RunEnd::Aborted(reason)) => {
+                            stopping.set(true);
+                            aborted.get_or_insert(reason);
+                        }
+                        Some(RunEnd::StoppedEarly(reason)) => {
+                            stopping.set(true);
+                            stopped_early.get_or_insert(reason);
+                        }
+                        None => {}
+                    }
+                }
+                Some(Err(skipped)) => totals.skipped_files.push(skipped),
+                None => {}
+            }
+        }
+        drop(results);
+        if shutdown.requested() {
+            stopped_early.get_or_insert_with(|| SHUTDOWN_REASON.to_string());
+        }
+        pending_files.clear();
+        sheet_number = files_to_process.len();
+        if aborted.is_some() || stopped_early.is_some() {
+            watcher = None;
+        }
+    }
+    // Logged again after each watched file, so the prompt stays near the bottom of the log
+    let mut announce_watch = true;
+    loop {
+        let (file_path, file_name) = match pending_files.pop_front() {
+            Some(file) => file,
+            None => {
+                let Some(watcher) = watcher.as_mut() else {
+                    break;
+                };
+                if let (Some(cap), Some(0)) = (max_rows, rows_left) {
+                    stopped_early = Some(row_cap_reason(cap));
+                    break;
+                }
+                if announce_watch {
+                    announce_watch = false;
+                    info!(
+                        "Watching {} for new files. Press Enter for the totals so far, or type q and Enter to stop",
+                        input_path
+                    );
+                }
+                existing_ids.extend(
+                    imported_ids
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .drain(..),
+                );
+                let next_event = async {
+                    match deadline {
+                        Some(deadline) => tokio::time::timeout(
+                            deadline.saturating_duration_since(Instant::now()),
+                            watcher.next(),
+                        )
+                        .await
+                        .ok(),
+                        None => Some(watcher.next().await),
+                    }
+                };
+                let event = tokio::select! {
+                    event = next_event => match event {
+                        Some(event) => event,
+                        None => {
+                            stopped_early = Some(time_budget_reason(&config));
+                            break;
+                        }
+                    },
+                    () = shutdown.wait() => {
+                        stopped_early = Some(SHUTDOWN_REASON.to_string());
+                        break;
+                    }
+                };
+                match event {
+                    Some(WatchEvent::File(path)) => {
+                        let file_name = path
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or("unknown")
+                            .to_string();
+                        if seen_files.contains(&file_name)
+                            || !is_input_file(&path)
+                            || !wait_until_written(&path).await
+                        {
+                            continue;
+                        }
+                        seen_files.insert(file_name.clone());
+                        announce_watch = true;
+                        info!("New file in {}: {}", input_path, file_name);
+                        if InputKind::of(&path) == Some(InputKind::Archive) {
+                            match extract_archive(&path) {
+                                Ok(extracted) => {
+                                    total_sheets += extracted.files.len();
+                                    pending_files.extend(extracted.files);
+                                }
+                                Err(e) => {
+                                    error!("Failed to read file {:?}: {:#}", file_name, e);
+                                    totals
+                                        .skipped_files
+                                        .push(format!("{:?}: {:#}", file_name, e));
+                                }
+                            }
+                            continue;
+                        }
+                        total_sheets += 1;
+                        (path, file_name)
+                    }
+                    Some(WatchEvent::Summary) => {
+                        info!(
+                            "Totals so far: {} file(s), {} processed, {} imported, {} skipped, {} failed, {} unreadable file(s)",
+                            format_number(sheet_number),
+                            format_number(totals.processed),
+                            format_number(totals.imported),
+                            format_number(totals.skipped),
+                            format_number(totals.failed.len()),
+                            format_number(totals.skipped_files.len())
+                        );
+                        continue;
+                    }
+                    Some(WatchEvent::Stop) | None => {
+                        info!("Stopped watching {}", input_path);
+                        break;
+                    }
                 }
-                Err(e) => {
-                    error!("Failed to read file {:?}: {}", file_name, e);
-                    skipped_files.push(format!("{:?}: {}", file_name, e));
+            }
+        };
+        sheet_number += 1;
+        if let (Some(cap), Some(0)) = (max_rows, rows_left) {
+            stopped_early = Some(row_cap_reason(cap));
+            break;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            stopped_early = Some(time_budget_reason(&config));
+            break;
+        }
+        if shutdown.requested() {
+            stopped_early = Some(SHUTDOWN_REASON.to_string());
+            break;
+        }
+        // Each file gets whatever is left of the run-wide row budget, and the run's totals
+        // so far so the failure ratio covers the whole run
+        let file_options = ProcessOptions {
+            max_rows: rows_left,
+            failure_threshold: failure_threshold(&config, totals.finished(), totals.failed.len()),
+            ..process_options.clone()
+        };
+        let Some(result) = process_file(
+            &file_path,
+            &file_name,
+            &existing_ids,
+            action_client.as_ref(),
+            &mut sheet_times,
+            sheet_number,
+            total_sheets,
+            &file_options,
+        )
+        .await
+        else {
+            continue;
+        };
+        match result {
+            Ok(FileResult { stats, .. }) => {
+                rows_left = rows_left.map(|left| left.saturating_sub(stats.posted));
+                let end = run_end(&stats, max_rows, &config);
+                totals.add(stats);
+                match end {
+                    Some(RunEnd::Aborted(reason)) => {
+                        aborted = Some(reason);
+                        break;
+                    }
+                    Some(RunEnd::StoppedEarly(reason)) => {
+                        stopped_early = Some(reason);
+                        break;
+                    }
+                    None => {}
                 }
             }
+            Err(skipped) => totals.skipped_files.push(skipped),
+        }
+    }
+
+    // Alongside the manifest, so `--summary-only` can count this run's failures later
+    if let Some(rejects) = &rejects {
+        match rejects.write(totals.failed.kept()) {
+            Ok(()) if !totals.failed.is_empty() => info!(
+                "Wrote {} failed action(s) to {}",
+                format_number(totals.failed.len()),
+                rejects.path().display()
+            ),
+            Ok(()) => {}
+            Err(e) => warn!("Failed to write rejects file: {:#}", e),
         }
     }
 
     let total_runtime = start_time.elapsed().as_secs_f64();
-    log_summary(
-        ImportSummary {
-            total_processed: total_actions_processed,
-            total_imported: total_actions_imported,
-            total_skipped: total_actions_skipped,
-            total_failed: failed_imports.len(),
-            skipped_files,
-            total_runtime_secs: total_runtime,
-            sheet_times,
+    let summary = ImportSummary {
+        total_processed: totals.processed,
+        total_imported: totals.imported,
+        total_skipped: totals.skipped,
+        total_sampled_out: totals.sampled_out,
+        total_skipped_empty_note: totals.skipped_empty_note,
+        total_failed: totals.failed.len(),
+        failures_by_category: totals.failed.by_category().clone(),
+        failures_not_kept: totals.failed.not_kept(),
+        skipped_files: totals.skipped_files,
+        total_runtime_secs: total_runtime,
+        sheet_times,
+        row_latencies: totals.row_latencies,
+        retries: retry_stats.snapshot(),
+        stopped_early,
+        report_stats,
+    };
+    let notification = summary.notification(&run.run_id, only_parse, aborted.as_deref());
+    let nothing_to_import = summary.nothing_to_import();
+    log_summary(summary, only_parse);
+    match summary_format {
+        SummaryFormat::Text => {}
+        // Printed rather than logged, so the last line of stdout parses as JSON
+        SummaryFormat::Json => match serde_json::to_string(&notification) {
+            Ok(json) => println!("{}", json),
+            Err(e) => warn!("Failed to render summary as JSON: {}", e),
         },
-        only_parse,
-    );
+        SummaryFormat::Csv => {
+            let path = run.output_dir.join(run_history::HISTORY_FILE);
+            match run_history::append_history(&path, run.started_at, &notification) {
+                Ok(()) => info!("Appended run totals to {}", path.display()),
+                Err(e) => warn!("Failed to append to run history: {:#}", e),
+            }
+        }
+    }
 
-    Ok(())
+    // A failed notification is logged but never changes the run's outcome
+    if let Some(webhook) = WebhookClient::from_config(&config, http_client) {
+        match webhook.send(&notification).await {
+            Ok(()) => info!("Sent run notification to {}", webhook.url()),
+            Err(e) => warn!(
+                "Failed to send run notification to {}: {:#}",
+                webhook.url(),
+                e
+            ),
+        }
+    }
+
+    if let Some(reason) = aborted {
+        anyhow::bail!("Import aborted: {}", reason);
+    }
+    Ok(if nothing_to_import {
+        RunOutcome::NothingToImport
+    } else {
+        RunOutcome::Completed
+    })
 }
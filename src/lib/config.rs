@@ -1,30 +1,706 @@
+use crate::domain::models::timezone::SourceTimezone;
 use anyhow::Context;
+use chrono::NaiveDate;
 use reqwest::Url;
+use std::path::PathBuf;
 use tracing::Level;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub base_resource_url: Url,
+    /// Permit `http://` URLs, which send the client secret and token in plaintext.
+    pub allow_insecure_http: bool,
     pub token_url: Url,
     pub client_id: String,
     pub client_secret: String,
+    /// OAuth grant the token request uses, with the fields it sends.
+    pub grant: Grant,
+    /// Extra form fields sent with every token request (`TOKEN_EXTRA_FIELDS`).
+    pub token_extra_fields: Vec<(String, String)>,
     pub action_ids_resources: Vec<Url>,
     pub action_id_custom_field_id: u32,
+    /// Ticket and Halo action ID of a previously imported action, read back before a run
+    /// to confirm `action_id_custom_field_id` holds its action ID.
+    pub field_check_action: Option<(u32, u32)>,
+    /// Unit of the token response's `expires_in`.
+    pub expires_in_unit: ExpiresInUnit,
+    /// File the token is kept in between runs; a new token every run when unset.
+    pub token_cache_path: Option<PathBuf>,
     pub log_level: Level,
+    pub max_note_len: Option<usize>,
+    pub max_note_len_mode: NoteLengthMode,
+    /// Upper bound on a batch POST body; batches are sent early to stay under it.
+    pub max_batch_bytes: Option<usize>,
+    /// Directory for logs and other run artifacts.
+    pub output_dir: PathBuf,
+    /// Earlier runs' logs older than this many days are gzipped at startup.
+    pub compress_old_logs_days: Option<u64>,
+    /// Proceed with a real import even when the reports return no existing action IDs.
+    pub allow_empty_report: bool,
+    /// Wait before retrying a report request that returned 504.
+    pub report_retry_delay_secs: u64,
+    /// Retries of a report request whose body was cut short or garbled.
+    pub report_parse_retries: usize,
+    /// Days of actions each report request covers when the report URLs use `{from}`/`{to}`.
+    pub report_chunk_days: Option<u32>,
+    /// First day of the chunked report range.
+    pub report_chunk_start: Option<NaiveDate>,
+    /// Reports fetched at the same time when several are configured.
+    pub report_concurrency: usize,
+    /// Input files processed at the same time; rows within a file stay in order.
+    pub file_concurrency: usize,
+    /// Batches of a file posted at the same time, with their results recorded in source
+    /// order and batches for the same ticket posted one after another.
+    pub ordered_concurrency: usize,
+    /// Reuse each report's cached IDs for this many seconds; no caching when unset.
+    pub report_cache_secs: Option<u64>,
+    /// Source header to read `outcome` from instead of the `outcome` column.
+    pub outcome_column: Option<String>,
+    /// Outcome for rows from files without an outcome column.
+    pub default_outcome: Option<String>,
+    /// Result for rows without a result value; `null` when unset.
+    pub default_result: Option<String>,
+    /// Source headers whose values are appended to each row's note, in order.
+    pub extra_note_columns: Vec<String>,
+    /// Put between the note and each appended extra note value.
+    pub extra_note_separator: String,
+    /// Table or view actions are read from in SQLite database inputs.
+    pub sqlite_table: Option<String>,
+    /// Put before every note; `{date}` is expanded to the run date.
+    pub note_prefix: String,
+    /// Put after every note; `{date}` is expanded to the run date.
+    pub note_suffix: String,
+    /// Timezone naive action dates are read in; Arizona time when unset.
+    pub source_timezone: Option<SourceTimezone>,
+    /// Source header holding a per-row timezone that overrides `source_timezone`.
+    pub timezone_column: Option<String>,
+    /// Count CSV rows before processing so progress has a total and ETA.
+    pub csv_count_rows: bool,
+    /// Order in which discovered input files are processed.
+    pub file_order: FileOrder,
+    /// What to do with an action ID that appears more than once in the same file.
+    pub within_file_duplicate: WithinFileDuplicate,
+    /// Whether leading zeros in all-digit action IDs are dropped or kept.
+    pub leading_zeros: LeadingZeros,
+    /// Whether Excel rows with an empty ticket ID or action ID cell fail or are skipped.
+    pub empty_numeric_cell: EmptyNumericCell,
+    /// Digit grouping of ticket IDs typed as text in Excel files.
+    pub number_locale: NumberLocale,
+    /// How boolean Excel cells read as text.
+    pub bool_format: BoolFormat,
+    /// Whether rows with an empty note are posted, skipped, or failed.
+    pub empty_note: EmptyNote,
+    /// Fields a row must have non-empty to be posted; always includes
+    /// [`RequiredField::ALWAYS`].
+    pub required_fields: Vec<RequiredField>,
+    /// What a row whose date can't be read in any accepted format gets.
+    pub bad_date_policy: BadDatePolicy,
+    /// Post each sheet's rows in `actiondate` order; undated rows go where this says.
+    /// `None` keeps file order.
+    pub sort_by_date: Option<UndatedRows>,
+    /// Default endpoint actions are posted to.
+    pub actions_url: Url,
+    /// Per-outcome endpoint overrides, matched case-insensitively on the outcome.
+    pub outcome_endpoints: Vec<(String, Url)>,
+    /// Halo instance the first `staging_sample` rows are posted to before the real import.
+    pub staging_base_url: Option<Url>,
+    pub staging_sample: usize,
+    /// Credentials for the staging instance, so production ones never leave for it.
+    pub staging_client_id: Option<String>,
+    pub staging_client_secret: Option<String>,
+    /// CSV of `source_id,halo_id` used to rewrite ticket IDs before posting.
+    pub ticket_id_map_path: Option<PathBuf>,
+    /// Keep ticket IDs missing from the map instead of failing the row.
+    pub ticket_id_map_pass_through: bool,
+    /// CSV of `name,agent_id` used to resolve `actionwho` to a Halo agent ID.
+    pub agent_map_path: Option<PathBuf>,
+    /// Post unmapped agent names without an agent ID instead of failing the row.
+    pub agent_map_pass_through: bool,
+    /// Halo-internal fields left out of the POST body.
+    pub omitted_payload_fields: Vec<PayloadField>,
+    /// Custom field that records which file (and optionally sheet and row) each action
+    /// was imported from.
+    pub source_file_custom_field_id: Option<u32>,
+    pub source_file_detail: SourceFileDetail,
+    /// Fraction of rows to process, between 0.0 and 1.0; `None` processes every row.
+    pub sample_rate: Option<f64>,
+    /// Seed for row sampling so the same rows are selected on every run.
+    pub sample_seed: u64,
+    /// Fraction of POSTs whose full payload and response are logged at TRACE.
+    pub trace_sample_rate: Option<f64>,
+    /// Abort once more than this fraction of finished rows has failed.
+    pub abort_failure_ratio: Option<f64>,
+    /// Rows that must be finished before `abort_failure_ratio` is checked.
+    pub abort_failure_min_sample: usize,
+    /// Failures held in memory; later ones are only written to the rejects file and counted.
+    pub max_failures_kept: usize,
+    /// Stop taking new rows once the run has taken this many seconds.
+    pub max_run_secs: Option<u64>,
+    /// Idle keep-alive connections kept per host; reqwest's default when unset.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle pooled connection is kept; reqwest's default when unset.
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// `Content-Type` sent on report and action requests.
+    pub request_content_type: String,
+    /// Log every outbound request's method, URL, status, and latency at debug.
+    pub http_trace: bool,
+    /// Client certificate presented to a mutual-TLS gateway: PEM (with `client_key_path`)
+    /// or PKCS#12 (`.p12`/`.pfx`, unlocked with `client_cert_password`).
+    pub client_cert_path: Option<PathBuf>,
+    pub client_key_path: Option<PathBuf>,
+    pub client_cert_password: Option<String>,
+    /// Extra PEM root certificates trusted alongside the system store.
+    pub ca_bundle_path: Option<PathBuf>,
+    /// Receives a POST with the run totals after the summary is logged.
+    pub notify_webhook_url: Option<Url>,
+    pub notify_webhook_format: WebhookFormat,
+    /// Exit code for a run with nothing new to import; such runs exit 0 when unset.
+    pub noop_exit_code: Option<i32>,
+    /// Loaded for an offline parse: no credentials, no auth, no report fetch.
+    pub offline: bool,
+}
+
+/// What to do with a note longer than `MAX_NOTE_LEN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteLengthMode {
+    /// Cut the note down and append a truncation marker.
+    #[default]
+    Truncate,
+    /// Reject the row locally without posting it.
+    Fail,
+}
+
+/// How discovered input files are ordered before processing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileOrder {
+    /// By file name, so sheet numbering is reproducible across platforms.
+    #[default]
+    Name,
+    /// Oldest modification time first, ties broken by file name.
+    Modified,
+}
+
+/// How repeated action IDs within one file are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WithinFileDuplicate {
+    /// Process every occurrence.
+    #[default]
+    Allow,
+    /// Keep the first occurrence and skip the rest.
+    First,
+    /// Keep the last occurrence and skip the earlier ones.
+    Last,
+    /// Fail every occurrence.
+    Error,
+}
+
+/// How much of a row's origin is written to `SOURCE_FILE_CUSTOM_FIELD_ID`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceFileDetail {
+    /// The file name.
+    #[default]
+    File,
+    /// The file name and, for workbooks, the sheet name.
+    Sheet,
+    /// The file name, sheet name, and row number.
+    Row,
+}
+
+/// What happens to rows whose note is empty or only whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyNote {
+    /// Post the action with its empty note.
+    #[default]
+    Post,
+    /// Leave the row out, counted as skipped for an empty note.
+    Skip,
+    /// Record the row as a validation failure.
+    Fail,
+}
+
+/// A field `REQUIRED_FIELDS` can require to be non-empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredField {
+    Note,
+    ActionWho,
+    ActionDate,
+    TicketId,
+    ActionId,
+}
+
+impl RequiredField {
+    /// The fields required when `REQUIRED_FIELDS` isn't set. An action can't be posted or
+    /// deduplicated without them, so they are required even when it is.
+    pub const ALWAYS: [RequiredField; 2] = [RequiredField::TicketId, RequiredField::ActionId];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::ActionWho => "actionwho",
+            Self::ActionDate => "actiondate",
+            Self::TicketId => "ticket_id",
+            Self::ActionId => "action_id",
+        }
+    }
+}
+
+/// What happens to rows whose `actiondate` matches none of the accepted formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BadDatePolicy {
+    /// Record the row as a deserialize failure.
+    #[default]
+    Fail,
+    /// Post the action without a date.
+    Null,
+    /// Post the action dated at the time it is processed.
+    Now,
+}
+
+/// How all-digit action IDs with leading zeros (`00456`) are matched and posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeadingZeros {
+    /// Drop the zeros: `00456` is the same action as `456`, in the rows and in the
+    /// existing-ID reports, and is posted as the number 456.
+    #[default]
+    Strip,
+    /// Keep the zeros: `00456` only matches `00456` in the reports, and is posted as the
+    /// text `"00456"`, for a text custom field.
+    Keep,
+}
+
+/// What happens to Excel rows whose ticket ID or action ID cell is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyNumericCell {
+    /// Fail the row as missing a required numeric field.
+    #[default]
+    Fail,
+    /// Leave the row out with a warning.
+    Skip,
+}
+
+/// Digit grouping of ticket IDs typed as text in Excel files, e.g. `1.234` from a
+/// German installation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberLocale {
+    /// Digits only.
+    #[default]
+    Plain,
+    /// `1,234`
+    En,
+    /// `1.234`
+    De,
+    /// `1 234`, with an ordinary, no-break, or narrow no-break space.
+    Fr,
+    /// `1'234`
+    Ch,
+}
+
+impl NumberLocale {
+    fn is_separator(self, c: char) -> bool {
+        match self {
+            Self::Plain => false,
+            Self::En => c == ',',
+            Self::De => c == '.',
+            Self::Fr => matches!(c, ' ' | '\u{a0}' | '\u{202f}'),
+            Self::Ch => matches!(c, '\'' | '\u{2019}'),
+        }
+    }
+
+    /// Reads a whole number written with this locale's grouping. Groups after the first
+    /// must be three digits, so a decimal like `1.5` is never read as `15`.
+    pub fn parse_u32(self, text: &str) -> Option<u32> {
+        let mut groups = text.split(|c| self.is_separator(c));
+        let first = groups.next()?;
+        if first.is_empty() || first.len() > 3 && text.len() != first.len() {
+            return None;
+        }
+        let mut digits = first.to_string();
+        for group in groups {
+            if group.len() != 3 {
+                return None;
+            }
+            digits.push_str(group);
+        }
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+/// How boolean Excel cells are written into text fields such as `note`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoolFormat {
+    /// `true` / `false`
+    #[default]
+    TrueFalse,
+    /// `Yes` / `No`
+    YesNo,
+    /// `1` / `0`
+    OneZero,
+}
+
+impl BoolFormat {
+    pub fn format(self, value: bool) -> &'static str {
+        match (self, value) {
+            (Self::TrueFalse, true) => "true",
+            (Self::TrueFalse, false) => "false",
+            (Self::YesNo, true) => "Yes",
+            (Self::YesNo, false) => "No",
+            (Self::OneZero, true) => "1",
+            (Self::OneZero, false) => "0",
+        }
+    }
+}
+
+/// Where rows without an action date go when sorting by date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UndatedRows {
+    First,
+    #[default]
+    Last,
+}
+
+/// OAuth grant type of the token request (`GRANT_TYPE`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Grant {
+    #[default]
+    ClientCredentials,
+    /// Resource owner password grant, signing in as `AUTH_USERNAME`.
+    Password { username: String, password: String },
+    /// Any other grant type, sent as is; its fields come from `TOKEN_EXTRA_FIELDS`.
+    Custom(String),
+}
+
+impl Grant {
+    pub fn grant_type(&self) -> &str {
+        match self {
+            Grant::ClientCredentials => "client_credentials",
+            Grant::Password { .. } => "password",
+            Grant::Custom(grant_type) => grant_type,
+        }
+    }
+}
+
+/// Token request fields the importer sets itself, which `TOKEN_EXTRA_FIELDS` can't override.
+const RESERVED_TOKEN_FIELDS: [&str; 6] = [
+    "client_id",
+    "client_secret",
+    "grant_type",
+    "scope",
+    "username",
+    "password",
+];
+
+/// Parses `TOKEN_EXTRA_FIELDS`: comma-separated `name=value` pairs.
+fn parse_token_extra_fields(fields: &str) -> anyhow::Result<Vec<(String, String)>> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let (name, value) = field
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .filter(|(name, _)| !name.is_empty())
+                .with_context(|| {
+                    format!(
+                        "invalid field '{}' in {}. expected name=value",
+                        field, TOKEN_EXTRA_FIELDS_KEY
+                    )
+                })?;
+            if RESERVED_TOKEN_FIELDS.contains(&name) {
+                anyhow::bail!(
+                    "{} can't set '{}', which the importer sets itself",
+                    TOKEN_EXTRA_FIELDS_KEY,
+                    name
+                );
+            }
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// How the token response's `expires_in` value is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpiresInUnit {
+    /// Seconds from now, as OAuth defines it.
+    #[default]
+    Seconds,
+    /// Milliseconds from now.
+    Milliseconds,
+    /// An absolute Unix timestamp in seconds.
+    Epoch,
+}
+
+/// Body format of the post-run webhook notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WebhookFormat {
+    /// The run totals as a JSON object.
+    #[default]
+    Json,
+    /// A Slack incoming-webhook message (`{"text": ...}`).
+    Slack,
+}
+
+/// Optional Halo-internal fields in the action payload.
+///
+/// Some API versions reject `__rowNum__`, and the rest duplicate other fields, so each
+/// can be dropped via `OMIT_PAYLOAD_FIELDS` or all at once with `MINIMAL_PAYLOAD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadField {
+    /// `__rowNum__`, a Halo UI artifact.
+    RowNum,
+    /// `result`, always sent as null.
+    Result,
+    /// `requestid`, a duplicate of `ticket_id`.
+    RequestId,
+    /// `who`, a duplicate of `actionwho`.
+    Who,
+}
+
+impl PayloadField {
+    pub const ALL: [PayloadField; 4] = [
+        PayloadField::RowNum,
+        PayloadField::Result,
+        PayloadField::RequestId,
+        PayloadField::Who,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PayloadField::RowNum => "__rowNum__",
+            PayloadField::Result => "result",
+            PayloadField::RequestId => "requestid",
+            PayloadField::Who => "who",
+        }
+    }
 }
 
 const BASE_RESOURCE_URL_KEY: &str = "BASE_RESOURCE_URL";
+const FIELD_CHECK_TICKET_ID_KEY: &str = "FIELD_CHECK_TICKET_ID";
+const FIELD_CHECK_ACTION_ID_KEY: &str = "FIELD_CHECK_ACTION_ID";
+const ALLOW_INSECURE_HTTP_KEY: &str = "ALLOW_INSECURE_HTTP";
 const CLIENT_ID_KEY: &str = "CLIENT_ID";
 const CLIENT_SECRET_KEY: &str = "CLIENT_SECRET";
+const GRANT_TYPE_KEY: &str = "GRANT_TYPE";
+const AUTH_USERNAME_KEY: &str = "AUTH_USERNAME";
+const AUTH_PASSWORD_KEY: &str = "AUTH_PASSWORD";
+const TOKEN_EXTRA_FIELDS_KEY: &str = "TOKEN_EXTRA_FIELDS";
 
 const TOKEN_URL_PATH: &str = "auth/token";
+const DEFAULT_ACTIONS_PATH: &str = "api/actions";
 
 const ACTION_IDS_RESOURCE_PATH_KEY: &str = "ACTION_IDS_RESOURCE_PATH";
 const ACTION_ID_CUSTOM_FIELD_ID_KEY: &str = "ACTION_ID_CUSTOM_FIELD_ID";
+const EXPIRES_IN_UNIT_KEY: &str = "EXPIRES_IN_UNIT";
+const TOKEN_CACHE_PATH_KEY: &str = "TOKEN_CACHE_PATH";
 const LOG_LEVEL_KEY: &str = "LOG_LEVEL";
+const APP_ENV_KEY: &str = "APP_ENV";
+const MAX_NOTE_LEN_KEY: &str = "MAX_NOTE_LEN";
+const MAX_NOTE_LEN_MODE_KEY: &str = "MAX_NOTE_LEN_MODE";
+const MAX_BATCH_BYTES_KEY: &str = "MAX_BATCH_BYTES";
+const OUTPUT_DIR_KEY: &str = "OUTPUT_DIR";
+const COMPRESS_OLD_LOGS_KEY: &str = "COMPRESS_OLD_LOGS";
+const ALLOW_EMPTY_REPORT_KEY: &str = "ALLOW_EMPTY_REPORT";
+const REPORT_RETRY_DELAY_SECS_KEY: &str = "REPORT_RETRY_DELAY_SECS";
+const REPORT_PARSE_RETRIES_KEY: &str = "REPORT_PARSE_RETRIES";
+const REPORT_CACHE_SECS_KEY: &str = "REPORT_CACHE_SECS";
+const REPORT_CONCURRENCY_KEY: &str = "REPORT_CONCURRENCY";
+const FILE_CONCURRENCY_KEY: &str = "FILE_CONCURRENCY";
+const ORDERED_CONCURRENCY_KEY: &str = "ORDERED_CONCURRENCY";
+const DEFAULT_REPORT_RETRY_DELAY_SECS: u64 = 60;
+const DEFAULT_REPORT_PARSE_RETRIES: usize = 3;
+const REPORT_CHUNK_DAYS_KEY: &str = "REPORT_CHUNK_DAYS";
+const REPORT_CHUNK_START_KEY: &str = "REPORT_CHUNK_START";
+/// Placeholders in a report URL replaced with each chunk's date range.
+pub const REPORT_FROM_PLACEHOLDER: &str = "{from}";
+pub const REPORT_TO_PLACEHOLDER: &str = "{to}";
+const OUTCOME_COLUMN_KEY: &str = "OUTCOME_COLUMN";
+const DEFAULT_OUTCOME_KEY: &str = "DEFAULT_OUTCOME";
+const DEFAULT_RESULT_KEY: &str = "DEFAULT_RESULT";
+const SOURCE_TZ_OFFSET_KEY: &str = "SOURCE_TZ_OFFSET";
+const TIMEZONE_COLUMN_KEY: &str = "TIMEZONE_COLUMN";
+const EXTRA_NOTE_COLUMNS_KEY: &str = "EXTRA_NOTE_COLUMNS";
+const EXTRA_NOTE_SEPARATOR_KEY: &str = "EXTRA_NOTE_SEPARATOR";
+const SQLITE_TABLE_KEY: &str = "SQLITE_TABLE";
+const DEFAULT_EXTRA_NOTE_SEPARATOR: &str = "\n";
+const NOTE_PREFIX_KEY: &str = "NOTE_PREFIX";
+const NOTE_SUFFIX_KEY: &str = "NOTE_SUFFIX";
+/// Placeholder in `NOTE_PREFIX`/`NOTE_SUFFIX` replaced with the run date.
+pub const NOTE_DATE_PLACEHOLDER: &str = "{date}";
+const CSV_COUNT_ROWS_KEY: &str = "CSV_COUNT_ROWS";
+const FILE_ORDER_KEY: &str = "FILE_ORDER";
+const WITHIN_FILE_DUPLICATE_KEY: &str = "WITHIN_FILE_DUPLICATE";
+const LEADING_ZEROS_KEY: &str = "LEADING_ZEROS";
+const EMPTY_NUMERIC_CELL_KEY: &str = "EMPTY_NUMERIC_CELL";
+const SKIP_EMPTY_NOTE_KEY: &str = "SKIP_EMPTY_NOTE";
+const NUMBER_LOCALE_KEY: &str = "NUMBER_LOCALE";
+const BOOL_FORMAT_KEY: &str = "BOOL_FORMAT";
+const REQUIRED_FIELDS_KEY: &str = "REQUIRED_FIELDS";
+const BAD_DATE_POLICY_KEY: &str = "BAD_DATE_POLICY";
+const SORT_BY_DATE_KEY: &str = "SORT_BY_DATE";
+const SORT_UNDATED_KEY: &str = "SORT_UNDATED";
+const ACTIONS_PATH_KEY: &str = "ACTIONS_PATH";
+const OUTCOME_ENDPOINTS_KEY: &str = "OUTCOME_ENDPOINTS";
+const TICKET_ID_MAP_PATH_KEY: &str = "TICKET_ID_MAP_PATH";
+const TICKET_ID_MAP_PASS_THROUGH_KEY: &str = "TICKET_ID_MAP_PASS_THROUGH";
+const AGENT_MAP_PATH_KEY: &str = "AGENT_MAP_PATH";
+const AGENT_MAP_PASS_THROUGH_KEY: &str = "AGENT_MAP_PASS_THROUGH";
+const OMIT_PAYLOAD_FIELDS_KEY: &str = "OMIT_PAYLOAD_FIELDS";
+const MINIMAL_PAYLOAD_KEY: &str = "MINIMAL_PAYLOAD";
+const SOURCE_FILE_CUSTOM_FIELD_ID_KEY: &str = "SOURCE_FILE_CUSTOM_FIELD_ID";
+const SOURCE_FILE_DETAIL_KEY: &str = "SOURCE_FILE_DETAIL";
+const SAMPLE_RATE_KEY: &str = "SAMPLE_RATE";
+const TRACE_SAMPLE_RATE_KEY: &str = "TRACE_SAMPLE_RATE";
+const ABORT_FAILURE_RATIO_KEY: &str = "ABORT_FAILURE_RATIO";
+const ABORT_FAILURE_MIN_SAMPLE_KEY: &str = "ABORT_FAILURE_MIN_SAMPLE";
+const DEFAULT_ABORT_FAILURE_MIN_SAMPLE: usize = 100;
+const MAX_FAILURES_KEPT_KEY: &str = "MAX_FAILURES_KEPT";
+const DEFAULT_MAX_FAILURES_KEPT: usize = 10_000;
+const MAX_RUN_SECS_KEY: &str = "MAX_RUN_SECS";
+const STAGING_BASE_URL_KEY: &str = "STAGING_BASE_URL";
+const STAGING_SAMPLE_KEY: &str = "STAGING_SAMPLE";
+const STAGING_CLIENT_ID_KEY: &str = "STAGING_CLIENT_ID";
+const STAGING_CLIENT_SECRET_KEY: &str = "STAGING_CLIENT_SECRET";
+const DEFAULT_STAGING_SAMPLE: usize = 10;
+const POOL_MAX_IDLE_PER_HOST_KEY: &str = "POOL_MAX_IDLE_PER_HOST";
+const POOL_IDLE_TIMEOUT_SECS_KEY: &str = "POOL_IDLE_TIMEOUT_SECS";
+const REQUEST_CONTENT_TYPE_KEY: &str = "REQUEST_CONTENT_TYPE";
+const HTTP_TRACE_KEY: &str = "HTTP_TRACE";
+const DEFAULT_REQUEST_CONTENT_TYPE: &str = "application/json; charset=utf-8";
+const CLIENT_CERT_PATH_KEY: &str = "CLIENT_CERT_PATH";
+const CLIENT_KEY_PATH_KEY: &str = "CLIENT_KEY_PATH";
+const CLIENT_CERT_PASSWORD_KEY: &str = "CLIENT_CERT_PASSWORD";
+const CA_BUNDLE_PATH_KEY: &str = "CA_BUNDLE_PATH";
+const NOTIFY_WEBHOOK_URL_KEY: &str = "NOTIFY_WEBHOOK_URL";
+const NOTIFY_WEBHOOK_FORMAT_KEY: &str = "NOTIFY_WEBHOOK_FORMAT";
+const NOOP_EXIT_CODE_KEY: &str = "NOOP_EXIT_CODE";
+const SAMPLE_SEED_KEY: &str = "SAMPLE_SEED";
+
+const DEFAULT_OUTPUT_DIR: &str = "log";
+
+/// Reads an optional variable, treating unset and blank values alike.
+fn optional_var(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Whether a report URL has the `{from}`/`{to}` placeholders of a chunked fetch.
+///
+/// `Url` percent-encodes braces in the path, so both spellings are checked.
+pub fn report_url_is_chunked(url: &Url) -> bool {
+    let url = url.as_str();
+    [REPORT_FROM_PLACEHOLDER, REPORT_TO_PLACEHOLDER]
+        .iter()
+        .any(|placeholder| url.contains(placeholder) || url.contains(&encode_braces(placeholder)))
+}
+
+/// Requires `https`, or `http` when `ALLOW_INSECURE_HTTP` is set.
+fn check_url_scheme(what: &str, url: &Url, allow_insecure_http: bool) -> anyhow::Result<()> {
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if allow_insecure_http => Ok(()),
+        "http" => anyhow::bail!(
+            "{} uses plain http ({}), which would send the client secret and token unencrypted. use https, or set {}=true to allow it",
+            what,
+            url,
+            ALLOW_INSECURE_HTTP_KEY
+        ),
+        other => anyhow::bail!(
+            "{} has unsupported scheme '{}' ({}). must be https",
+            what,
+            other,
+            url
+        ),
+    }
+}
+
+/// `url` with its scheme, host, and port taken from `base`, keeping its path and query.
+fn rebase_url(url: &Url, base: &Url) -> Url {
+    let mut rebased = base.clone();
+    rebased.set_path(url.path());
+    rebased.set_query(url.query());
+    rebased
+}
+
+/// `{from}` as it appears in a URL path: `%7Bfrom%7D`.
+pub fn encode_braces(placeholder: &str) -> String {
+    placeholder.replace('{', "%7B").replace('}', "%7D")
+}
+
+fn parse_optional_var<T>(key: &str) -> anyhow::Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    optional_var(key)
+        .map(|value| {
+            value
+                .parse::<T>()
+                .map_err(|e| anyhow::anyhow!("invalid value '{}' for {}: {}", value, key, e))
+        })
+        .transpose()
+}
 
 impl Config {
+    /// The URLs requests go to over plain `http` (only possible with `ALLOW_INSECURE_HTTP`):
+    /// any of the resource base, the token URL, and the staging base, whose token URL is
+    /// rebased onto it.
+    pub fn insecure_http_urls(&self) -> Vec<&Url> {
+        [
+            Some(&self.base_resource_url),
+            Some(&self.token_url),
+            self.staging_base_url.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|url| url.scheme() == "http")
+        .collect()
+    }
+
+    /// Loads configuration using the profile named by `APP_ENV`, if any.
     pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_profile(None)
+    }
+
+    /// Loads configuration for the given profile (falling back to `APP_ENV` when `None`).
+    ///
+    /// Load order, highest precedence first:
+    /// 1. variables already set in the process environment
+    /// 2. `.env.<profile>` when a profile is selected
+    /// 3. `.env`
+    ///
+    /// Files never override a variable that is already set, so keys missing from the
+    /// profile file fall back to `.env`. `${VAR}` references inside the files are
+    /// interpolated by dotenvy. A selected profile whose file does not exist is an error
+    /// so a typo can't silently fall through to the default target.
+    pub fn from_profile(profile: Option<&str>) -> anyhow::Result<Self> {
+        Self::load(profile, false)
+    }
+
+    /// Loads configuration for an offline parse-only run.
+    ///
+    /// `CLIENT_ID`, `CLIENT_SECRET`, and `ACTION_IDS_RESOURCE_PATH` become optional since
+    /// nothing authenticates or fetches the report; everything else loads as usual.
+    pub fn offline_from_profile(profile: Option<&str>) -> anyhow::Result<Self> {
+        Self::load(profile, true)
+    }
+
+    fn load(profile: Option<&str>, offline: bool) -> anyhow::Result<Self> {
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| std::env::var(APP_ENV_KEY).ok())
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        if let Some(profile) = &profile {
+            let profile_file = format!(".env.{}", profile);
+            dotenvy::from_filename(&profile_file).with_context(|| {
+                format!("failed to load profile '{}' from {}", profile, profile_file)
+            })?;
+        }
         dotenvy::dotenv().ok();
 
         let base_url_str = std::env::var(BASE_RESOURCE_URL_KEY).with_context(|| {
@@ -39,28 +715,56 @@ impl Config {
                 BASE_RESOURCE_URL_KEY, base_url_str
             )
         })?;
+        let allow_insecure_http =
+            parse_optional_var::<bool>(ALLOW_INSECURE_HTTP_KEY)?.unwrap_or(false);
         let mut token_url = base_resource_url.clone();
         token_url.set_path(TOKEN_URL_PATH);
-        let client_id = std::env::var(CLIENT_ID_KEY)
-            .with_context(|| format!("missing required environment variable: {}", CLIENT_ID_KEY))?;
-        let client_secret = std::env::var(CLIENT_SECRET_KEY).with_context(|| {
-            format!(
-                "missing required environment variable: {}",
-                CLIENT_SECRET_KEY
-            )
-        })?;
-        let action_ids_paths = std::env::var(ACTION_IDS_RESOURCE_PATH_KEY).with_context(|| {
-            format!(
-                "missing required environment variable: {}",
-                ACTION_IDS_RESOURCE_PATH_KEY
-            )
-        })?;
+        // Credentials and the report are only read by auth and the report fetch
+        let credential_var = |key: &str| -> anyhow::Result<String> {
+            if offline {
+                return Ok(optional_var(key).unwrap_or_default());
+            }
+            std::env::var(key)
+                .with_context(|| format!("missing required environment variable: {}", key))
+        };
+        let client_id = credential_var(CLIENT_ID_KEY)?;
+        let client_secret = credential_var(CLIENT_SECRET_KEY)?;
+        // Grant type values are case-sensitive and may be URNs, so they're kept as written
+        let grant = match optional_var(GRANT_TYPE_KEY).as_deref() {
+            None | Some("client_credentials") => Grant::ClientCredentials,
+            Some("password") => {
+                let grant_var = |key: &str| -> anyhow::Result<String> {
+                    match optional_var(key) {
+                        Some(value) => Ok(value),
+                        None if offline => Ok(String::new()),
+                        None => {
+                            anyhow::bail!("{}=password requires {} to be set", GRANT_TYPE_KEY, key)
+                        }
+                    }
+                };
+                Grant::Password {
+                    username: grant_var(AUTH_USERNAME_KEY)?,
+                    password: grant_var(AUTH_PASSWORD_KEY)?,
+                }
+            }
+            Some(other) => Grant::Custom(other.to_string()),
+        };
+        let token_extra_fields = optional_var(TOKEN_EXTRA_FIELDS_KEY)
+            .map(|fields| parse_token_extra_fields(&fields))
+            .transpose()?
+            .unwrap_or_default();
+        let action_ids_paths = credential_var(ACTION_IDS_RESOURCE_PATH_KEY)?;
         let action_ids_resources: Vec<Url> = action_ids_paths
             .split(',')
             .map(|path| {
-                let path = path.trim();
+                // A query string (e.g. for `{from}`/`{to}`) would be escaped into the path
+                let (path, query) = match path.trim().split_once('?') {
+                    Some((path, query)) => (path, Some(query)),
+                    None => (path.trim(), None),
+                };
                 let mut url = base_resource_url.clone();
                 url.set_path(path);
+                url.set_query(query);
                 url
             })
             .collect();
@@ -79,6 +783,35 @@ impl Config {
                 )
             })?;
 
+        let field_check_action = match (
+            parse_optional_var::<u32>(FIELD_CHECK_TICKET_ID_KEY)?,
+            parse_optional_var::<u32>(FIELD_CHECK_ACTION_ID_KEY)?,
+        ) {
+            (Some(ticket_id), Some(action_id)) => Some((ticket_id, action_id)),
+            (None, None) => None,
+            _ => anyhow::bail!(
+                "{} and {} must be set together",
+                FIELD_CHECK_TICKET_ID_KEY,
+                FIELD_CHECK_ACTION_ID_KEY
+            ),
+        };
+
+        let expires_in_unit = match optional_var(EXPIRES_IN_UNIT_KEY)
+            .map(|unit| unit.to_lowercase())
+            .as_deref()
+        {
+            None | Some("seconds") => ExpiresInUnit::Seconds,
+            Some("milliseconds") => ExpiresInUnit::Milliseconds,
+            Some("epoch") => ExpiresInUnit::Epoch,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid expires_in unit '{}' for {}. must be one of: seconds, milliseconds, epoch",
+                    other,
+                    EXPIRES_IN_UNIT_KEY
+                );
+            }
+        };
+
         let log_level_str = std::env::var(LOG_LEVEL_KEY).unwrap_or_else(|_| "info".to_string());
         let log_level_str_trimmed = log_level_str.trim().to_lowercase();
         let log_level = match log_level_str_trimmed.as_str() {
@@ -96,16 +829,678 @@ impl Config {
             }
         };
 
+        let max_note_len = parse_optional_var::<usize>(MAX_NOTE_LEN_KEY)?;
+        let max_note_len_mode = match optional_var(MAX_NOTE_LEN_MODE_KEY)
+            .map(|mode| mode.to_lowercase())
+            .as_deref()
+        {
+            None | Some("truncate") => NoteLengthMode::Truncate,
+            Some("fail") => NoteLengthMode::Fail,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid note length mode '{}' for {}. must be one of: truncate, fail",
+                    other,
+                    MAX_NOTE_LEN_MODE_KEY
+                );
+            }
+        };
+
+        let token_cache_path = optional_var(TOKEN_CACHE_PATH_KEY).map(PathBuf::from);
+
+        let max_batch_bytes = parse_optional_var::<usize>(MAX_BATCH_BYTES_KEY)?;
+        if max_batch_bytes == Some(0) {
+            anyhow::bail!("{} must be at least 1", MAX_BATCH_BYTES_KEY);
+        }
+
+        let output_dir = PathBuf::from(
+            optional_var(OUTPUT_DIR_KEY).unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string()),
+        );
+        let compress_old_logs_days = parse_optional_var::<u64>(COMPRESS_OLD_LOGS_KEY)?;
+
+        let allow_empty_report =
+            parse_optional_var::<bool>(ALLOW_EMPTY_REPORT_KEY)?.unwrap_or(false);
+        let report_retry_delay_secs = parse_optional_var::<u64>(REPORT_RETRY_DELAY_SECS_KEY)?
+            .unwrap_or(DEFAULT_REPORT_RETRY_DELAY_SECS);
+        let report_parse_retries = parse_optional_var::<usize>(REPORT_PARSE_RETRIES_KEY)?
+            .unwrap_or(DEFAULT_REPORT_PARSE_RETRIES);
+        let report_concurrency = parse_optional_var::<usize>(REPORT_CONCURRENCY_KEY)?.unwrap_or(1);
+        if report_concurrency == 0 {
+            anyhow::bail!("{} must be at least 1", REPORT_CONCURRENCY_KEY);
+        }
+        let file_concurrency = parse_optional_var::<usize>(FILE_CONCURRENCY_KEY)?.unwrap_or(1);
+        if file_concurrency == 0 {
+            anyhow::bail!("{} must be at least 1", FILE_CONCURRENCY_KEY);
+        }
+        let ordered_concurrency =
+            parse_optional_var::<usize>(ORDERED_CONCURRENCY_KEY)?.unwrap_or(1);
+        if ordered_concurrency == 0 {
+            anyhow::bail!("{} must be at least 1", ORDERED_CONCURRENCY_KEY);
+        }
+        let report_cache_secs =
+            parse_optional_var::<u64>(REPORT_CACHE_SECS_KEY)?.filter(|secs| *secs > 0);
+        let report_chunk_days = parse_optional_var::<u32>(REPORT_CHUNK_DAYS_KEY)?;
+        if report_chunk_days == Some(0) {
+            anyhow::bail!("{} must be at least 1", REPORT_CHUNK_DAYS_KEY);
+        }
+        let report_chunk_start = optional_var(REPORT_CHUNK_START_KEY)
+            .map(|value| {
+                NaiveDate::parse_from_str(&value, "%Y-%m-%d").with_context(|| {
+                    format!(
+                        "invalid date '{}' for {}. expected YYYY-MM-DD",
+                        value, REPORT_CHUNK_START_KEY
+                    )
+                })
+            })
+            .transpose()?;
+        let chunked_reports = action_ids_resources
+            .iter()
+            .filter(|url| report_url_is_chunked(url))
+            .count();
+        match (report_chunk_days, report_chunk_start) {
+            (Some(_), None) => anyhow::bail!(
+                "{} requires {} (the first day of the report range)",
+                REPORT_CHUNK_DAYS_KEY,
+                REPORT_CHUNK_START_KEY
+            ),
+            (Some(_), Some(_)) if chunked_reports == 0 => anyhow::bail!(
+                "{} is set but no {} path contains {} and {} placeholders",
+                REPORT_CHUNK_DAYS_KEY,
+                ACTION_IDS_RESOURCE_PATH_KEY,
+                REPORT_FROM_PLACEHOLDER,
+                REPORT_TO_PLACEHOLDER
+            ),
+            (None, _) if chunked_reports > 0 => anyhow::bail!(
+                "{} contains {}/{} placeholders but {} is not set",
+                ACTION_IDS_RESOURCE_PATH_KEY,
+                REPORT_FROM_PLACEHOLDER,
+                REPORT_TO_PLACEHOLDER,
+                REPORT_CHUNK_DAYS_KEY
+            ),
+            _ => {}
+        }
+
+        let outcome_column = optional_var(OUTCOME_COLUMN_KEY);
+        let default_outcome = optional_var(DEFAULT_OUTCOME_KEY);
+        let default_result = optional_var(DEFAULT_RESULT_KEY);
+        let extra_note_columns: Vec<String> = optional_var(EXTRA_NOTE_COLUMNS_KEY)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|column| !column.is_empty())
+            .map(str::to_string)
+            .collect();
+        // Read raw so a separator of only spaces survives; `\n` and `\t` are unescaped
+        // since real newlines are awkward to put in a .env file
+        let extra_note_separator = std::env::var(EXTRA_NOTE_SEPARATOR_KEY)
+            .ok()
+            .filter(|separator| !separator.is_empty())
+            .map(|separator| separator.replace("\\n", "\n").replace("\\t", "\t"))
+            .unwrap_or_else(|| DEFAULT_EXTRA_NOTE_SEPARATOR.to_string());
+        let sqlite_table = optional_var(SQLITE_TABLE_KEY);
+        // Also read raw, so "[Imported {date}] " keeps the space before the note
+        let note_affix = |key| {
+            std::env::var(key)
+                .unwrap_or_default()
+                .replace("\\n", "\n")
+                .replace("\\t", "\t")
+        };
+        let note_prefix = note_affix(NOTE_PREFIX_KEY);
+        let note_suffix = note_affix(NOTE_SUFFIX_KEY);
+        let source_timezone = optional_var(SOURCE_TZ_OFFSET_KEY)
+            .map(|value| {
+                SourceTimezone::parse(&value)
+                    .map_err(|e| anyhow::anyhow!("{} for {}", e, SOURCE_TZ_OFFSET_KEY))
+            })
+            .transpose()?;
+        let timezone_column = optional_var(TIMEZONE_COLUMN_KEY);
+
+        let csv_count_rows = parse_optional_var::<bool>(CSV_COUNT_ROWS_KEY)?.unwrap_or(true);
+
+        let file_order = match optional_var(FILE_ORDER_KEY)
+            .map(|order| order.to_lowercase())
+            .as_deref()
+        {
+            None | Some("name") => FileOrder::Name,
+            Some("modified") => FileOrder::Modified,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid file order '{}' for {}. must be one of: name, modified",
+                    other,
+                    FILE_ORDER_KEY
+                );
+            }
+        };
+
+        let within_file_duplicate = match optional_var(WITHIN_FILE_DUPLICATE_KEY)
+            .map(|policy| policy.to_lowercase())
+            .as_deref()
+        {
+            None | Some("allow") => WithinFileDuplicate::Allow,
+            Some("first") => WithinFileDuplicate::First,
+            Some("last") => WithinFileDuplicate::Last,
+            Some("error") => WithinFileDuplicate::Error,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid duplicate policy '{}' for {}. must be one of: allow, first, last, error",
+                    other,
+                    WITHIN_FILE_DUPLICATE_KEY
+                );
+            }
+        };
+
+        let leading_zeros = match optional_var(LEADING_ZEROS_KEY)
+            .map(|mode| mode.to_lowercase())
+            .as_deref()
+        {
+            None | Some("strip") => LeadingZeros::Strip,
+            Some("keep") => LeadingZeros::Keep,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid leading zeros mode '{}' for {}. must be one of: strip, keep",
+                    other,
+                    LEADING_ZEROS_KEY
+                );
+            }
+        };
+
+        let empty_numeric_cell = match optional_var(EMPTY_NUMERIC_CELL_KEY)
+            .map(|policy| policy.to_lowercase())
+            .as_deref()
+        {
+            None | Some("fail") => EmptyNumericCell::Fail,
+            Some("skip") => EmptyNumericCell::Skip,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid empty numeric cell policy '{}' for {}. must be one of: fail, skip",
+                    other,
+                    EMPTY_NUMERIC_CELL_KEY
+                );
+            }
+        };
+
+        let number_locale = match optional_var(NUMBER_LOCALE_KEY)
+            .map(|locale| locale.to_lowercase())
+            .as_deref()
+        {
+            None | Some("plain") => NumberLocale::Plain,
+            Some("en") => NumberLocale::En,
+            Some("de") => NumberLocale::De,
+            Some("fr") => NumberLocale::Fr,
+            Some("ch") => NumberLocale::Ch,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid number locale '{}' for {}. must be one of: plain, en, de, fr, ch",
+                    other,
+                    NUMBER_LOCALE_KEY
+                );
+            }
+        };
+
+        let bool_format = match optional_var(BOOL_FORMAT_KEY)
+            .map(|format| format.to_lowercase())
+            .as_deref()
+        {
+            None | Some("truefalse") => BoolFormat::TrueFalse,
+            Some("yesno") => BoolFormat::YesNo,
+            Some("onezero") => BoolFormat::OneZero,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid bool format '{}' for {}. must be one of: truefalse, yesno, onezero",
+                    other,
+                    BOOL_FORMAT_KEY
+                );
+            }
+        };
+
+        let empty_note = match optional_var(SKIP_EMPTY_NOTE_KEY)
+            .map(|policy| policy.to_lowercase())
+            .as_deref()
+        {
+            None | Some("post") => EmptyNote::Post,
+            Some("skip") => EmptyNote::Skip,
+            Some("fail") => EmptyNote::Fail,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid empty note policy '{}' for {}. must be one of: post, skip, fail",
+                    other,
+                    SKIP_EMPTY_NOTE_KEY
+                );
+            }
+        };
+
+        let mut required_fields = RequiredField::ALWAYS.to_vec();
+        for field in optional_var(REQUIRED_FIELDS_KEY)
+            .unwrap_or_default()
+            .split(',')
+            .map(|field| field.trim().to_lowercase())
+            .filter(|field| !field.is_empty())
+        {
+            let field = match field.as_str() {
+                "note" => RequiredField::Note,
+                "actionwho" => RequiredField::ActionWho,
+                "actiondate" => RequiredField::ActionDate,
+                "ticket_id" => RequiredField::TicketId,
+                "action_id" => RequiredField::ActionId,
+                other => anyhow::bail!(
+                    "invalid field '{}' in {}. must be a comma-separated list of: note, actionwho, actiondate, ticket_id, action_id",
+                    other,
+                    REQUIRED_FIELDS_KEY
+                ),
+            };
+            if !required_fields.contains(&field) {
+                required_fields.push(field);
+            }
+        }
+
+        let bad_date_policy = match optional_var(BAD_DATE_POLICY_KEY)
+            .map(|policy| policy.to_lowercase())
+            .as_deref()
+        {
+            None | Some("fail") => BadDatePolicy::Fail,
+            Some("null") => BadDatePolicy::Null,
+            Some("now") => BadDatePolicy::Now,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid bad date policy '{}' for {}. must be one of: fail, null, now",
+                    other,
+                    BAD_DATE_POLICY_KEY
+                );
+            }
+        };
+
+        let undated_rows = match optional_var(SORT_UNDATED_KEY)
+            .map(|position| position.to_lowercase())
+            .as_deref()
+        {
+            None | Some("last") => UndatedRows::Last,
+            Some("first") => UndatedRows::First,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid undated row position '{}' for {}. must be one of: first, last",
+                    other,
+                    SORT_UNDATED_KEY
+                );
+            }
+        };
+        let sort_by_date = parse_optional_var::<bool>(SORT_BY_DATE_KEY)?
+            .unwrap_or(false)
+            .then_some(undated_rows);
+
+        let mut actions_url = base_resource_url.clone();
+        actions_url.set_path(
+            &optional_var(ACTIONS_PATH_KEY).unwrap_or_else(|| DEFAULT_ACTIONS_PATH.to_string()),
+        );
+        let mut outcome_endpoints = Vec::new();
+        for pair in optional_var(OUTCOME_ENDPOINTS_KEY)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (outcome, path) = pair
+                .split_once('=')
+                .map(|(outcome, path)| (outcome.trim(), path.trim()))
+                .filter(|(outcome, path)| !outcome.is_empty() && !path.is_empty())
+                .with_context(|| {
+                    format!(
+                        "invalid entry '{}' for {}. expected outcome=path",
+                        pair, OUTCOME_ENDPOINTS_KEY
+                    )
+                })?;
+            let mut url = base_resource_url.clone();
+            url.set_path(path);
+            outcome_endpoints.push((outcome.to_string(), url));
+        }
+
+        // Every endpoint is derived from the base URL, but check each one the credentials
+        // or token are sent to rather than rely on that
+        check_url_scheme(
+            BASE_RESOURCE_URL_KEY,
+            &base_resource_url,
+            allow_insecure_http,
+        )?;
+        check_url_scheme("token URL", &token_url, allow_insecure_http)?;
+        for url in &action_ids_resources {
+            check_url_scheme(ACTION_IDS_RESOURCE_PATH_KEY, url, allow_insecure_http)?;
+        }
+        check_url_scheme(ACTIONS_PATH_KEY, &actions_url, allow_insecure_http)?;
+        for (_, url) in &outcome_endpoints {
+            check_url_scheme(OUTCOME_ENDPOINTS_KEY, url, allow_insecure_http)?;
+        }
+
+        let staging_base_url = optional_var(STAGING_BASE_URL_KEY)
+            .map(|url| {
+                Url::parse(&url).with_context(|| {
+                    format!("invalid URL format for {}: {}", STAGING_BASE_URL_KEY, url)
+                })
+            })
+            .transpose()?;
+        if let Some(url) = &staging_base_url {
+            check_url_scheme(STAGING_BASE_URL_KEY, url, allow_insecure_http)?;
+            if url.origin() == base_resource_url.origin() {
+                anyhow::bail!(
+                    "{} must point at a different Halo instance than {}",
+                    STAGING_BASE_URL_KEY,
+                    BASE_RESOURCE_URL_KEY
+                );
+            }
+        }
+        let staging_sample = parse_optional_var::<usize>(STAGING_SAMPLE_KEY)?;
+        if staging_sample.is_some() && staging_base_url.is_none() {
+            anyhow::bail!(
+                "{} requires {} to be set",
+                STAGING_SAMPLE_KEY,
+                STAGING_BASE_URL_KEY
+            );
+        }
+        let staging_sample = staging_sample.unwrap_or(DEFAULT_STAGING_SAMPLE);
+        if staging_sample == 0 {
+            anyhow::bail!("{} must be at least 1", STAGING_SAMPLE_KEY);
+        }
+        let staging_client_id = optional_var(STAGING_CLIENT_ID_KEY);
+        let staging_client_secret = optional_var(STAGING_CLIENT_SECRET_KEY);
+
+        let ticket_id_map_path = optional_var(TICKET_ID_MAP_PATH_KEY).map(PathBuf::from);
+        let ticket_id_map_pass_through =
+            parse_optional_var::<bool>(TICKET_ID_MAP_PASS_THROUGH_KEY)?.unwrap_or(false);
+        let agent_map_path = optional_var(AGENT_MAP_PATH_KEY).map(PathBuf::from);
+        let agent_map_pass_through =
+            parse_optional_var::<bool>(AGENT_MAP_PASS_THROUGH_KEY)?.unwrap_or(false);
+
+        let minimal_payload = parse_optional_var::<bool>(MINIMAL_PAYLOAD_KEY)?.unwrap_or(false);
+        let omitted_payload_fields = if minimal_payload {
+            PayloadField::ALL.to_vec()
+        } else {
+            let mut fields = Vec::new();
+            for name in optional_var(OMIT_PAYLOAD_FIELDS_KEY)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                let field = PayloadField::ALL
+                    .into_iter()
+                    .find(|field| field.name().eq_ignore_ascii_case(name))
+                    .with_context(|| {
+                        format!(
+                            "invalid payload field '{}' for {}. must be one of: __rowNum__, result, requestid, who",
+                            name, OMIT_PAYLOAD_FIELDS_KEY
+                        )
+                    })?;
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+            fields
+        };
+        let source_file_custom_field_id =
+            parse_optional_var::<u32>(SOURCE_FILE_CUSTOM_FIELD_ID_KEY)?;
+        if source_file_custom_field_id == Some(action_id_custom_field_id) {
+            anyhow::bail!(
+                "{} must differ from {}",
+                SOURCE_FILE_CUSTOM_FIELD_ID_KEY,
+                ACTION_ID_CUSTOM_FIELD_ID_KEY
+            );
+        }
+        let source_file_detail = match optional_var(SOURCE_FILE_DETAIL_KEY)
+            .map(|detail| detail.to_lowercase())
+            .as_deref()
+        {
+            None | Some("file") => SourceFileDetail::File,
+            Some("sheet") => SourceFileDetail::Sheet,
+            Some("row") => SourceFileDetail::Row,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid source file detail '{}' for {}. must be one of: file, sheet, row",
+                    other,
+                    SOURCE_FILE_DETAIL_KEY
+                );
+            }
+        };
+
+        let sample_rate = parse_optional_var::<f64>(SAMPLE_RATE_KEY)?;
+        if let Some(rate) = sample_rate
+            && !(0.0..=1.0).contains(&rate)
+        {
+            anyhow::bail!(
+                "invalid sample rate '{}' for {}. must be between 0.0 and 1.0",
+                rate,
+                SAMPLE_RATE_KEY
+            );
+        }
+        // A rate of 1.0 keeps every row, so it is the same as no sampling
+        let sample_rate = sample_rate.filter(|rate| *rate < 1.0);
+        let sample_seed = parse_optional_var::<u64>(SAMPLE_SEED_KEY)?.unwrap_or(0);
+        let trace_sample_rate = parse_optional_var::<f64>(TRACE_SAMPLE_RATE_KEY)?;
+        if let Some(rate) = trace_sample_rate
+            && !(0.0..=1.0).contains(&rate)
+        {
+            anyhow::bail!(
+                "invalid trace sample rate '{}' for {}. must be between 0.0 and 1.0",
+                rate,
+                TRACE_SAMPLE_RATE_KEY
+            );
+        }
+        let trace_sample_rate = trace_sample_rate.filter(|rate| *rate > 0.0);
+
+        let abort_failure_ratio = parse_optional_var::<f64>(ABORT_FAILURE_RATIO_KEY)?;
+        if let Some(ratio) = abort_failure_ratio
+            && !(0.0..1.0).contains(&ratio)
+        {
+            anyhow::bail!(
+                "invalid failure ratio '{}' for {}. must be at least 0.0 and below 1.0",
+                ratio,
+                ABORT_FAILURE_RATIO_KEY
+            );
+        }
+        let abort_failure_min_sample = parse_optional_var::<usize>(ABORT_FAILURE_MIN_SAMPLE_KEY)?
+            .unwrap_or(DEFAULT_ABORT_FAILURE_MIN_SAMPLE);
+        if abort_failure_min_sample == 0 {
+            anyhow::bail!("{} must be at least 1", ABORT_FAILURE_MIN_SAMPLE_KEY);
+        }
+        let max_failures_kept = parse_optional_var::<usize>(MAX_FAILURES_KEPT_KEY)?
+            .unwrap_or(DEFAULT_MAX_FAILURES_KEPT);
+        let max_run_secs = parse_optional_var::<u64>(MAX_RUN_SECS_KEY)?;
+        if max_run_secs == Some(0) {
+            anyhow::bail!("{} must be at least 1", MAX_RUN_SECS_KEY);
+        }
+
+        let pool_max_idle_per_host = parse_optional_var::<usize>(POOL_MAX_IDLE_PER_HOST_KEY)?;
+        let pool_idle_timeout_secs = parse_optional_var::<u64>(POOL_IDLE_TIMEOUT_SECS_KEY)?;
+        let request_content_type = optional_var(REQUEST_CONTENT_TYPE_KEY)
+            .unwrap_or_else(|| DEFAULT_REQUEST_CONTENT_TYPE.to_string());
+        if reqwest::header::HeaderValue::from_str(&request_content_type).is_err() {
+            anyhow::bail!(
+                "invalid content type '{}' for {}. must be a valid header value",
+                request_content_type,
+                REQUEST_CONTENT_TYPE_KEY
+            );
+        }
+
+        let http_trace = parse_optional_var::<bool>(HTTP_TRACE_KEY)?.unwrap_or(false);
+
+        let client_cert_path = optional_var(CLIENT_CERT_PATH_KEY).map(PathBuf::from);
+        let client_key_path = optional_var(CLIENT_KEY_PATH_KEY).map(PathBuf::from);
+        let client_cert_password = optional_var(CLIENT_CERT_PASSWORD_KEY);
+        if client_cert_path.is_none()
+            && (client_key_path.is_some() || client_cert_password.is_some())
+        {
+            anyhow::bail!(
+                "{} and {} require {} to be set",
+                CLIENT_KEY_PATH_KEY,
+                CLIENT_CERT_PASSWORD_KEY,
+                CLIENT_CERT_PATH_KEY
+            );
+        }
+        let ca_bundle_path = optional_var(CA_BUNDLE_PATH_KEY).map(PathBuf::from);
+
+        let notify_webhook_url = optional_var(NOTIFY_WEBHOOK_URL_KEY)
+            .map(|url| {
+                Url::parse(&url).with_context(|| {
+                    format!("invalid URL format for {}: {}", NOTIFY_WEBHOOK_URL_KEY, url)
+                })
+            })
+            .transpose()?;
+        let notify_webhook_format = match optional_var(NOTIFY_WEBHOOK_FORMAT_KEY)
+            .map(|format| format.to_lowercase())
+            .as_deref()
+        {
+            None | Some("json") => WebhookFormat::Json,
+            Some("slack") => WebhookFormat::Slack,
+            Some(other) => {
+                anyhow::bail!(
+                    "invalid webhook format '{}' for {}. must be one of: json, slack",
+                    other,
+                    NOTIFY_WEBHOOK_FORMAT_KEY
+                );
+            }
+        };
+        let noop_exit_code = parse_optional_var::<i32>(NOOP_EXIT_CODE_KEY)?;
+        if let Some(code) = noop_exit_code
+            && !(1..=255).contains(&code)
+        {
+            anyhow::bail!(
+                "invalid exit code '{}' for {}. must be between 1 and 255",
+                code,
+                NOOP_EXIT_CODE_KEY
+            );
+        }
+
         Ok(Self {
             base_resource_url,
+            allow_insecure_http,
             token_url,
             client_id,
             client_secret,
+            grant,
+            token_extra_fields,
             action_ids_resources,
             action_id_custom_field_id,
+            field_check_action,
+            expires_in_unit,
+            token_cache_path,
             log_level,
+            max_note_len,
+            max_note_len_mode,
+            max_batch_bytes,
+            output_dir,
+            compress_old_logs_days,
+            allow_empty_report,
+            report_retry_delay_secs,
+            report_parse_retries,
+            report_chunk_days,
+            report_chunk_start,
+            report_concurrency,
+            file_concurrency,
+            ordered_concurrency,
+            report_cache_secs,
+            outcome_column,
+            default_outcome,
+            default_result,
+            extra_note_columns,
+            extra_note_separator,
+            sqlite_table,
+            note_prefix,
+            note_suffix,
+            source_timezone,
+            timezone_column,
+            csv_count_rows,
+            file_order,
+            within_file_duplicate,
+            leading_zeros,
+            empty_numeric_cell,
+            number_locale,
+            bool_format,
+            empty_note,
+            required_fields,
+            bad_date_policy,
+            sort_by_date,
+            actions_url,
+            outcome_endpoints,
+            staging_base_url,
+            staging_sample,
+            staging_client_id,
+            staging_client_secret,
+            ticket_id_map_path,
+            ticket_id_map_pass_through,
+            agent_map_path,
+            agent_map_pass_through,
+            omitted_payload_fields,
+            source_file_custom_field_id,
+            source_file_detail,
+            sample_rate,
+            sample_seed,
+            trace_sample_rate,
+            abort_failure_ratio,
+            abort_failure_min_sample,
+            max_failures_kept,
+            max_run_secs,
+            pool_max_idle_per_host,
+            pool_idle_timeout_secs,
+            request_content_type,
+            http_trace,
+            client_cert_path,
+            client_key_path,
+            client_cert_password,
+            ca_bundle_path,
+            notify_webhook_url,
+            notify_webhook_format,
+            noop_exit_code,
+            offline,
         })
     }
+
+    /// The endpoint an action with the given outcome should be posted to.
+    pub fn endpoint_for(&self, outcome: &str) -> &Url {
+        let outcome = outcome.trim();
+        self.outcome_endpoints
+            .iter()
+            .find(|(mapped, _)| mapped.eq_ignore_ascii_case(outcome))
+            .map(|(_, url)| url)
+            .unwrap_or(&self.actions_url)
+    }
+
+    /// This config with every Halo URL moved onto `STAGING_BASE_URL` and signing in with the
+    /// staging credentials, or `None` when it isn't set. Fails without `STAGING_CLIENT_ID`
+    /// and `STAGING_CLIENT_SECRET` rather than send the production credentials to staging.
+    pub fn staging(&self) -> anyhow::Result<Option<Config>> {
+        let Some(base) = self.staging_base_url.as_ref() else {
+            return Ok(None);
+        };
+        let (Some(client_id), Some(client_secret)) =
+            (&self.staging_client_id, &self.staging_client_secret)
+        else {
+            anyhow::bail!(
+                "{} requires {} and {}, so the production credentials are never sent to the staging instance",
+                STAGING_BASE_URL_KEY,
+                STAGING_CLIENT_ID_KEY,
+                STAGING_CLIENT_SECRET_KEY
+            );
+        };
+        let mut staging = self.clone();
+        staging.client_id = client_id.clone();
+        staging.client_secret = client_secret.clone();
+        // The production grant may carry a user's password or tenant fields
+        staging.grant = Grant::ClientCredentials;
+        staging.token_extra_fields = Vec::new();
+        staging.base_resource_url = base.clone();
+        staging.token_url = rebase_url(&self.token_url, base);
+        staging.action_ids_resources = self
+            .action_ids_resources
+            .iter()
+            .map(|url| rebase_url(url, base))
+            .collect();
+        staging.actions_url = rebase_url(&self.actions_url, base);
+        staging.outcome_endpoints = self
+            .outcome_endpoints
+            .iter()
+            .map(|(outcome, url)| (outcome.clone(), rebase_url(url, base)))
+            .collect();
+        staging.staging_base_url = None;
+        // The cache holds the production token; staging runs are rare enough to skip it
+        staging.token_cache_path = None;
+        Ok(Some(staging))
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +1510,27 @@ mod tests {
     fn config_from_env() {
         assert!(Config::from_env().is_ok());
     }
+
+    #[test]
+    fn rebase_url_keeps_path_and_query() {
+        let url = Url::parse("https://prod.halo.example/api/Report?from=%7Bfrom%7D").unwrap();
+        let base = Url::parse("https://staging.halo.example:8443/").unwrap();
+        assert_eq!(
+            rebase_url(&url, &base).as_str(),
+            "https://staging.halo.example:8443/api/Report?from=%7Bfrom%7D"
+        );
+    }
+
+    #[test]
+    fn token_extra_fields_reject_reserved_names() {
+        assert_eq!(
+            parse_token_extra_fields("audience=halo, tenant = acme,").unwrap(),
+            vec![
+                ("audience".to_string(), "halo".to_string()),
+                ("tenant".to_string(), "acme".to_string())
+            ]
+        );
+        assert!(parse_token_extra_fields("client_secret=other").is_err());
+        assert!(parse_token_extra_fields("audience").is_err());
+    }
 }
@@ -0,0 +1,111 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Agent display name to Halo agent ID mapping loaded from `AGENT_MAP_PATH`.
+///
+/// The file is a two-column CSV of `name,agent_id`; names match case-insensitively and
+/// a first row with a non-numeric ID is treated as a header. With `pass_through` set,
+/// unmapped names are posted as-is instead of failing the row.
+#[derive(Debug, Clone)]
+pub struct AgentMap {
+    ids: HashMap<String, u32>,
+    pass_through: bool,
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl AgentMap {
+    pub fn load(path: &Path, pass_through: bool) -> anyhow::Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .with_context(|| format!("Failed to open agent map: {}", path.display()))?;
+        let mut ids = HashMap::new();
+        for (idx, record) in rdr.records().enumerate() {
+            let line = idx + 1;
+            let record = record.with_context(|| {
+                format!(
+                    "Failed to read line {} of agent map: {}",
+                    line,
+                    path.display()
+                )
+            })?;
+            let name = record.get(0).map(str::trim).unwrap_or_default();
+            let agent = record.get(1).map(str::trim).unwrap_or_default();
+            if name.is_empty() && agent.is_empty() {
+                continue;
+            }
+            let Ok(agent_id) = agent.parse::<u32>() else {
+                if line == 1 {
+                    continue;
+                }
+                anyhow::bail!(
+                    "invalid agent map entry on line {} of {}: expected name,agent_id but got '{},{}'",
+                    line,
+                    path.display(),
+                    name,
+                    agent
+                );
+            };
+            if name.is_empty() {
+                anyhow::bail!(
+                    "invalid agent map entry on line {} of {}: agent ID {} has no name",
+                    line,
+                    path.display(),
+                    agent_id
+                );
+            }
+            if let Some(previous) = ids.insert(normalize_name(name), agent_id)
+                && previous != agent_id
+            {
+                anyhow::bail!(
+                    "conflicting agent map entries for '{}' in {}: {} and {}",
+                    name,
+                    path.display(),
+                    previous,
+                    agent_id
+                );
+            }
+        }
+        Ok(Self { ids, pass_through })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn pass_through(&self) -> bool {
+        self.pass_through
+    }
+
+    /// The agent ID for `name`, if mapped.
+    pub fn lookup(&self, name: &str) -> Option<u32> {
+        self.ids.get(&normalize_name(name)).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn loads_map_and_matches_names_case_insensitively() {
+        let path = TempPath::new("agent_map.csv");
+        std::fs::write(&path, "name,agent_id\nSample Agent,12\n  Other Agent ,34\n").unwrap();
+        let map = AgentMap::load(&path, false).unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.lookup("sample agent"), Some(12));
+        assert_eq!(map.lookup("Other Agent"), Some(34));
+        assert_eq!(map.lookup("Unknown"), None);
+    }
+}
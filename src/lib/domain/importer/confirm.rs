@@ -0,0 +1,86 @@
+use crate::config::Config;
+use crate::domain::importer::processor::format_number;
+use crate::domain::importer::scan::prescan_files;
+use crate::inbound::file::ColumnMapping;
+use anyhow::Context;
+use std::collections::HashSet;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use tracing::info;
+
+/// Asks the operator to confirm a real import before anything is posted.
+///
+/// `assume_yes` (`--yes`) skips the prompt entirely. Without it the prompt is only
+/// possible on an interactive terminal; non-interactive runs are refused.
+pub fn confirm_import(
+    config: &Config,
+    files_to_process: &[(PathBuf, String)],
+    existing_ids: &HashSet<String>,
+    assume_yes: bool,
+) -> anyhow::Result<()> {
+    if assume_yes {
+        info!("Confirmation skipped (--yes)");
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to post actions without confirmation in a non-interactive session. Re-run with --yes to proceed"
+        );
+    }
+    info!("Scanning files to build the import plan for confirmation");
+    let plan = prescan_files(
+        files_to_process,
+        existing_ids,
+        &ColumnMapping::from_config(config),
+        config.bad_date_policy,
+    );
+    let mut stdout = std::io::stdout();
+    writeln!(stdout)?;
+    writeln!(stdout, "About to import into: {}", config.base_resource_url)?;
+    writeln!(
+        stdout,
+        "  Files:            {}",
+        format_number(files_to_process.len())
+    )?;
+    writeln!(
+        stdout,
+        "  Estimated rows:   {}",
+        format_number(plan.total_rows())
+    )?;
+    writeln!(
+        stdout,
+        "  Will be skipped:  {}",
+        format_number(plan.to_skip)
+    )?;
+    writeln!(
+        stdout,
+        "  Will be posted:   {}",
+        format_number(plan.to_post)
+    )?;
+    if plan.invalid > 0 {
+        writeln!(
+            stdout,
+            "  Invalid rows:     {}",
+            format_number(plan.invalid)
+        )?;
+    }
+    if !ask_yes_no("Proceed with import?")? {
+        anyhow::bail!("Import cancelled by operator");
+    }
+    info!("Import confirmed by operator");
+    Ok(())
+}
+
+/// Prints `question` with a `[y/N]` prompt and reads the answer from stdin. Anything but
+/// `y`/`yes` is a no.
+pub(crate) fn ask_yes_no(question: &str) -> anyhow::Result<bool> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "{} [y/N]: ", question)?;
+    stdout.flush()?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read confirmation from stdin")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,167 @@
+use crate::config::WithinFileDuplicate;
+use crate::domain::models::action_object::ActionObject;
+use std::collections::{HashMap, HashSet};
+
+/// Outcome of the duplicate check for one row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DuplicateVerdict {
+    Keep,
+    /// Another occurrence of the action ID is kept instead.
+    Drop {
+        kept_row: usize,
+    },
+    /// Every occurrence fails; the message lists the rows.
+    Reject(String),
+}
+
+/// Enforces `WITHIN_FILE_DUPLICATE` over the rows of one file.
+///
+/// Rows are numbered from 1 in the order the reader yields them, including rows that fail
+/// to parse. `first` is decided as rows stream in; `last` and `error` need to know about
+/// later rows, so they use an index built by a pre-pass over the file.
+#[derive(Debug)]
+pub enum DuplicateCheck {
+    Allow,
+    First(HashMap<String, usize>),
+    Indexed {
+        policy: WithinFileDuplicate,
+        /// Rows of every action ID that appears more than once.
+        rows: HashMap<String, Vec<usize>>,
+    },
+}
+
+impl DuplicateCheck {
+    /// Whether `policy` needs [`index_duplicates`] run over the file first.
+    pub fn needs_index(policy: WithinFileDuplicate) -> bool {
+        matches!(
+            policy,
+            WithinFileDuplicate::Last | WithinFileDuplicate::Error
+        )
+    }
+
+    /// `rows` is the pre-pass index, required when [`Self::needs_index`] is true.
+    pub fn new(policy: WithinFileDuplicate, rows: HashMap<String, Vec<usize>>) -> Self {
+        match policy {
+            WithinFileDuplicate::Allow => Self::Allow,
+            WithinFileDuplicate::First => Self::First(HashMap::new()),
+            WithinFileDuplicate::Last | WithinFileDuplicate::Error => {
+                Self::Indexed { policy, rows }
+            }
+        }
+    }
+
+    pub fn check(&mut self, action_id: &str, row: usize) -> DuplicateVerdict {
+        match self {
+            Self::Allow => DuplicateVerdict::Keep,
+            Self::First(seen) => match seen.get(action_id) {
+                Some(&kept_row) => DuplicateVerdict::Drop { kept_row },
+                None => {
+                    seen.insert(action_id.to_string(), row);
+                    DuplicateVerdict::Keep
+                }
+            },
+            Self::Indexed { policy, rows } => {
+                let Some(occurrences) = rows.get(action_id) else {
+                    return DuplicateVerdict::Keep;
+                };
+                if *policy == WithinFileDuplicate::Error {
+                    let row_list: Vec<String> =
+                        occurrences.iter().map(|row| row.to_string()).collect();
+                    return DuplicateVerdict::Reject(format!(
+                        "Action ID: {} appears {} times (rows {})",
+                        action_id,
+                        occurrences.len(),
+                        row_list.join(", ")
+                    ));
+                }
+                let kept_row = occurrences.last().copied().unwrap_or(row);
+                if kept_row == row {
+                    DuplicateVerdict::Keep
+                } else {
+                    DuplicateVerdict::Drop { kept_row }
+                }
+            }
+        }
+    }
+}
+
+/// Rows each repeated action ID appears on, numbered as [`DuplicateCheck`] expects.
+pub fn index_duplicates<I>(iter: I) -> HashMap<String, Vec<usize>>
+where
+    I: Iterator<Item = anyhow::Result<ActionObject>>,
+{
+    let mut rows: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut repeated: HashSet<String> = HashSet::new();
+    for (idx, result) in iter.enumerate() {
+        let Ok(action) = result else {
+            continue;
+        };
+        let entry = rows.entry(action.action_id().to_string()).or_default();
+        entry.push(idx + 1);
+        if entry.len() == 2 {
+            repeated.insert(action.action_id().to_string());
+        }
+    }
+    rows.retain(|action_id, _| repeated.contains(action_id));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::action_object::ActionId;
+
+    fn rows(ids: &[&str]) -> Vec<anyhow::Result<ActionObject>> {
+        ids.iter()
+            .map(|id| {
+                Ok(ActionObject::new(
+                    1,
+                    None,
+                    None,
+                    "note",
+                    "agent",
+                    ActionId::new(*id),
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn policies_keep_or_reject_repeated_ids() {
+        let ids = ["a", "b", "a", "a"];
+        let index = index_duplicates(rows(&ids).into_iter());
+        assert_eq!(index.len(), 1);
+        assert_eq!(index["a"], vec![1, 3, 4]);
+
+        let verdicts = |policy| {
+            let mut check = DuplicateCheck::new(policy, index.clone());
+            ids.iter()
+                .enumerate()
+                .map(|(idx, id)| check.check(id, idx + 1))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            verdicts(WithinFileDuplicate::First),
+            vec![
+                DuplicateVerdict::Keep,
+                DuplicateVerdict::Keep,
+                DuplicateVerdict::Drop { kept_row: 1 },
+                DuplicateVerdict::Drop { kept_row: 1 },
+            ]
+        );
+        assert_eq!(
+            verdicts(WithinFileDuplicate::Last),
+            vec![
+                DuplicateVerdict::Drop { kept_row: 4 },
+                DuplicateVerdict::Keep,
+                DuplicateVerdict::Drop { kept_row: 4 },
+                DuplicateVerdict::Keep,
+            ]
+        );
+        let errors = verdicts(WithinFileDuplicate::Error);
+        assert_eq!(errors[1], DuplicateVerdict::Keep);
+        assert!(
+            matches!(&errors[0], DuplicateVerdict::Reject(msg) if msg.contains("rows 1, 3, 4"))
+        );
+    }
+}
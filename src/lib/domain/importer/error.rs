@@ -0,0 +1,280 @@
+use crate::domain::importer::run_history::RejectsWriter;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why a single row or action failed to import.
+#[derive(Debug, Clone)]
+pub enum ImportError {
+    /// The row could not be deserialized into an `ActionObject`.
+    Deserialize(String),
+    /// The row parsed but was rejected locally before posting.
+    Validation(String),
+    /// Halo answered with a non-success status.
+    Http {
+        status: u16,
+        request_id: String,
+        body: String,
+    },
+    /// The request could not be sent or no response was received.
+    Network(String),
+    /// The row was fine but was still queued when the run aborted, so it was never posted.
+    NotPosted(String),
+}
+
+impl ImportError {
+    /// Every value `category` returns.
+    pub const CATEGORIES: [&'static str; 5] =
+        ["deserialize", "validation", "http", "network", "not_posted"];
+
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Deserialize(_) => "deserialize",
+            Self::Validation(_) => "validation",
+            Self::Http { .. } => "http",
+            Self::Network(_) => "network",
+            Self::NotPosted(_) => "not_posted",
+        }
+    }
+
+    /// Classifies an error returned by the action client, keeping HTTP failures typed.
+    pub fn from_client_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<ImportError>() {
+            Some(import_error) => import_error.clone(),
+            None => Self::Network(format!("{:#}", error)),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deserialize(msg)
+            | Self::Validation(msg)
+            | Self::Network(msg)
+            | Self::NotPosted(msg) => write!(f, "{}", msg),
+            Self::Http {
+                status,
+                request_id,
+                body,
+            } => write!(
+                f,
+                "Action object POST failed for batch: status {}, request ID: {}, error: {}",
+                status, request_id, body
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Halo accepted some actions of a batch and rejected others (207 Multi-Status).
+///
+/// Returned as the POST's error so callers that treat a batch as one outcome still see a
+/// failure; the processor downcasts it to record each action on its own.
+#[derive(Debug, Clone)]
+pub struct PartialBatch {
+    /// Position in the posted batch and error of each rejected action.
+    pub rejected: Vec<(usize, ImportError)>,
+    /// Actions in the posted batch.
+    pub posted: usize,
+}
+
+impl fmt::Display for PartialBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Halo rejected {} of {} action(s) in the batch",
+            self.rejected.len(),
+            self.posted
+        )
+    }
+}
+
+impl std::error::Error for PartialBatch {}
+
+/// A failed row together with the action ID it belonged to (`"unknown"` if it never parsed).
+#[derive(Debug, Clone)]
+pub struct FailedAction {
+    pub action_id: String,
+    pub error: ImportError,
+}
+
+/// The failures of a file or run.
+///
+/// Every failure is counted, but only the first `limit` are held in memory
+/// (`MAX_FAILURES_KEPT`). Later ones go straight to the rejects file, so a run where every
+/// row fails, such as against a wrong endpoint, doesn't grow without bound. Without a
+/// rejects file (`--no-manifest`, parse-only) their details are discarded, with a warning.
+#[derive(Debug, Default)]
+pub struct FailedActions {
+    kept: Vec<FailedAction>,
+    not_kept: usize,
+    by_category: BTreeMap<&'static str, usize>,
+    /// Latest error of each category, for the `ABORT_FAILURE_RATIO` reason.
+    latest: BTreeMap<&'static str, String>,
+    /// `None` keeps every failure.
+    limit: Option<usize>,
+    rejects: Option<Arc<RejectsWriter>>,
+    /// Set once a failure past the limit could not be written anywhere, so the warning is
+    /// logged once rather than per row.
+    spill_warned: bool,
+}
+
+impl FailedActions {
+    pub fn new(limit: Option<usize>, rejects: Option<Arc<RejectsWriter>>) -> Self {
+        Self {
+            limit,
+            rejects,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, failure: FailedAction) {
+        let category = failure.error.category();
+        *self.by_category.entry(category).or_insert(0) += 1;
+        self.latest.insert(category, failure.error.to_string());
+        self.keep(failure);
+    }
+
+    /// Adds the failures of a later file, keeping this list's limit.
+    pub fn append(&mut self, other: FailedActions) {
+        for (category, count) in other.by_category {
+            *self.by_category.entry(category).or_insert(0) += count;
+        }
+        self.latest.extend(other.latest);
+        self.not_kept += other.not_kept;
+        self.spill_warned |= other.spill_warned;
+        for failure in other.kept {
+            self.keep(failure);
+        }
+    }
+
+    fn keep(&mut self, failure: FailedAction) {
+        if self.limit.is_none_or(|limit| self.kept.len() < limit) {
+            self.kept.push(failure);
+            return;
+        }
+        self.not_kept += 1;
+        if self.spill_warned {
+            if let Some(rejects) = &self.rejects {
+                let _ = rejects.write(std::slice::from_ref(&failure));
+            }
+            return;
+        }
+        match &self.rejects {
+            Some(rejects) => {
+                if let Err(e) = rejects.write(std::slice::from_ref(&failure)) {
+                    self.spill_warned = true;
+                    tracing::warn!(
+                        "Failed to write failures past MAX_FAILURES_KEPT to the rejects file: {:#}",
+                        e
+                    );
+                }
+            }
+            None => {
+                self.spill_warned = true;
+                tracing::warn!(
+                    "More than {} failures: details of later failures are discarded because no rejects file is written (--no-manifest or parse-only); only their count is kept",
+                    self.kept.len()
+                );
+            }
+        }
+    }
+
+    /// Every failure, including those not kept.
+    pub fn len(&self) -> usize {
+        self.kept.len() + self.not_kept
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The failures held in memory, in the order they happened.
+    pub fn kept(&self) -> &[FailedAction] {
+        &self.kept
+    }
+
+    /// Failures past the limit, counted but not held.
+    pub fn not_kept(&self) -> usize {
+        self.not_kept
+    }
+
+    pub fn by_category(&self) -> &BTreeMap<&'static str, usize> {
+        &self.by_category
+    }
+
+    /// The latest error of a category.
+    pub fn latest(&self, category: &str) -> Option<&str> {
+        self.latest.get(category).map(String::as_str)
+    }
+}
+
+impl FromIterator<FailedAction> for FailedActions {
+    fn from_iter<T: IntoIterator<Item = FailedAction>>(iter: T) -> Self {
+        let mut failed = Self::default();
+        for failure in iter {
+            failed.push(failure);
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn client_errors_keep_http_status_and_fall_back_to_network() {
+        let http: anyhow::Error = ImportError::Http {
+            status: 400,
+            request_id: "abc".to_string(),
+            body: "bad note".to_string(),
+        }
+        .into();
+        assert!(matches!(
+            ImportError::from_client_error(&http),
+            ImportError::Http { status: 400, .. }
+        ));
+        let network = anyhow::anyhow!("connection reset");
+        assert_eq!(
+            ImportError::from_client_error(&network).category(),
+            "network"
+        );
+    }
+
+    #[test]
+    fn failures_past_the_limit_are_counted_and_written_to_rejects() {
+        let path = TempPath::new("rejects.csv");
+        let rejects = Arc::new(RejectsWriter::new(path.to_path_buf()));
+        let failure = |id: usize, error: ImportError| FailedAction {
+            action_id: id.to_string(),
+            error,
+        };
+        let mut run = FailedActions::new(Some(2), Some(rejects.clone()));
+        let mut file = FailedActions::new(Some(2), Some(rejects.clone()));
+        for id in 1..=3 {
+            file.push(failure(id, ImportError::Network("timed out".to_string())));
+        }
+        run.push(failure(0, ImportError::Validation("no ticket".to_string())));
+        run.append(file);
+        rejects.write(run.kept()).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+
+        assert_eq!(run.len(), 4);
+        assert_eq!(run.not_kept(), 2);
+        let kept: Vec<&str> = run.kept().iter().map(|f| f.action_id.as_str()).collect();
+        assert_eq!(kept, ["0", "1"]);
+        assert_eq!(run.by_category().get("network"), Some(&3));
+        assert_eq!(run.latest("validation"), Some("no ticket"));
+        // Each failure is in the rejects file once: the spilled ones first
+        let ids: Vec<&str> = written
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap())
+            .collect();
+        assert_eq!(ids, ["3", "2", "0", "1"]);
+    }
+}
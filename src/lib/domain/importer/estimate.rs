@@ -0,0 +1,161 @@
+use crate::domain::importer::processor::{format_duration, format_number};
+use chrono::{DateTime, Local, TimeDelta};
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Run duration extrapolated from the time the first rows of the run took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunEstimate {
+    pub sample_rows: usize,
+    pub sample_secs: f64,
+    /// Rows the pre-scan expects to post after the sample.
+    pub remaining_rows: usize,
+}
+
+impl RunEstimate {
+    pub fn secs_per_row(&self) -> f64 {
+        self.sample_secs / self.sample_rows.max(1) as f64
+    }
+
+    pub fn remaining_secs(&self) -> f64 {
+        self.secs_per_row() * self.remaining_rows as f64
+    }
+
+    pub fn completion_time(&self, now: DateTime<Local>) -> DateTime<Local> {
+        now + TimeDelta::milliseconds((self.remaining_secs() * 1000.0) as i64)
+    }
+
+    pub fn log(&self) {
+        info!(
+            "Estimate from the first {} posted action(s): {:.2}s per action, about {} for the remaining {}, finishing around {}",
+            format_number(self.sample_rows),
+            self.secs_per_row(),
+            format_duration(self.remaining_secs()),
+            format_number(self.remaining_rows),
+            self.completion_time(Local::now()).format("%Y-%m-%d %H:%M")
+        );
+    }
+}
+
+/// Where the `--estimate` checkpoint stands.
+#[derive(Debug)]
+enum Checkpoint {
+    Sampling { posted: usize },
+    Continue,
+    Declined,
+}
+
+/// The `--estimate` checkpoint: times the first `sample_rows` rows the run posts, logs the
+/// estimated duration for the rest, and asks the operator whether to go on.
+///
+/// Shared by every file of the run, so the sample spans files and the question is asked
+/// once. The wall clock starts when the estimator is created, so the sample's time includes
+/// reading and skipping rows as well as posting.
+#[derive(Debug)]
+pub struct RunEstimator {
+    sample_rows: usize,
+    expected_rows: usize,
+    assume_yes: bool,
+    started: Instant,
+    checkpoint: Mutex<Checkpoint>,
+}
+
+impl RunEstimator {
+    /// `expected_rows` is the number of rows the run should post, from a pre-scan.
+    pub fn new(sample_rows: usize, expected_rows: usize, assume_yes: bool) -> Self {
+        Self {
+            sample_rows: sample_rows.max(1),
+            expected_rows,
+            assume_yes,
+            started: Instant::now(),
+            checkpoint: Mutex::new(Checkpoint::Sampling { posted: 0 }),
+        }
+    }
+
+    /// Counts a posted row. The row that completes the sample logs the estimate and, unless
+    /// `assume_yes`, blocks on the prompt. `false` once the operator has declined.
+    pub fn record_posted(&self) -> bool {
+        let mut checkpoint = self.checkpoint.lock().unwrap_or_else(|e| e.into_inner());
+        let Checkpoint::Sampling { posted } = &mut *checkpoint else {
+            return !matches!(*checkpoint, Checkpoint::Declined);
+        };
+        *posted += 1;
+        if *posted < self.sample_rows {
+            return true;
+        }
+        let estimate = RunEstimate {
+            sample_rows: *posted,
+            sample_secs: self.started.elapsed().as_secs_f64(),
+            remaining_rows: self.expected_rows.saturating_sub(*posted),
+        };
+        estimate.log();
+        let go_on = self.assume_yes || estimate.remaining_rows == 0 || ask_to_continue();
+        *checkpoint = if go_on {
+            Checkpoint::Continue
+        } else {
+            Checkpoint::Declined
+        };
+        go_on
+    }
+
+    /// Whether the operator chose to stop after the sample.
+    pub fn declined(&self) -> bool {
+        matches!(
+            *self.checkpoint.lock().unwrap_or_else(|e| e.into_inner()),
+            Checkpoint::Declined
+        )
+    }
+}
+
+/// Asks on the terminal; anything but yes, or a failed read, stops the run.
+fn ask_to_continue() -> bool {
+    // `confirm_import` has already refused non-interactive runs without --yes
+    if !std::io::stdin().is_terminal() {
+        return true;
+    }
+    let mut stdout = std::io::stdout();
+    let mut answer = String::new();
+    let read = write!(stdout, "Continue with the import? [y/N]: ")
+        .and_then(|()| stdout.flush())
+        .and_then(|()| std::io::stdin().lock().read_line(&mut answer));
+    if let Err(e) = read {
+        warn!("Failed to read confirmation from stdin: {}", e);
+    }
+    let go_on = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if go_on {
+        info!("Import continued by operator after the estimate");
+    } else {
+        info!("Import stopped by operator after the estimate");
+    }
+    go_on
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn extrapolates_the_sample_to_the_remaining_rows() {
+        let estimate = RunEstimate {
+            sample_rows: 200,
+            sample_secs: 100.0,
+            remaining_rows: 7_000,
+        };
+        assert_eq!(estimate.secs_per_row(), 0.5);
+        assert_eq!(estimate.remaining_secs(), 3_500.0);
+        let now = Local.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap();
+        assert_eq!(
+            estimate.completion_time(now),
+            Local.with_ymd_and_hms(2024, 1, 15, 9, 58, 20).unwrap()
+        );
+
+        let estimator = RunEstimator::new(2, 10, true);
+        assert!(estimator.record_posted());
+        assert!(estimator.record_posted());
+        assert!(estimator.record_posted());
+        assert!(!estimator.declined());
+    }
+}
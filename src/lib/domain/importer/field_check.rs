@@ -0,0 +1,90 @@
+use serde_json::Value;
+
+/// Reads the value a Halo action holds in custom field `field_id`.
+///
+/// On failure the message says what to fix: when another custom field that looks like the
+/// action ID field has a value, its ID is suggested for `ACTION_ID_CUSTOM_FIELD_ID`.
+pub fn custom_field_value(action: &Value, field_id: u32) -> Result<String, String> {
+    let fields = action
+        .get("customfields")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let value_of = |field: &Value| -> Option<String> {
+        let value = field.get("value").filter(|v| !v.is_null())?;
+        let text = match value {
+            Value::String(s) => s.trim().to_string(),
+            other => other.to_string(),
+        };
+        (!text.is_empty()).then_some(text)
+    };
+
+    let configured = fields
+        .iter()
+        .find(|field| field.get("id").and_then(Value::as_u64) == Some(u64::from(field_id)));
+    if let Some(value) = configured.and_then(value_of) {
+        return Ok(value);
+    }
+
+    let lookalike = fields.iter().find_map(|field| {
+        let name = field.get("name").and_then(Value::as_str)?;
+        let normalized: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        if !normalized.contains("actionid") {
+            return None;
+        }
+        let id = field.get("id").and_then(Value::as_u64)?;
+        value_of(field).map(|value| (name.to_string(), id, value))
+    });
+    match (configured, lookalike) {
+        (_, Some((name, id, value))) if id != u64::from(field_id) => Err(format!(
+            "custom field {} has no value, but '{}' (ID {}) holds '{}'. ACTION_ID_CUSTOM_FIELD_ID is probably wrong; set it to {}",
+            field_id, name, id, value, id
+        )),
+        (Some(_), _) => Err(format!(
+            "custom field {} is on the action but empty, so imported actions won't be found by the report. check that the POST body sets it",
+            field_id
+        )),
+        (None, _) => Err(format!(
+            "the action has no custom field {}. check ACTION_ID_CUSTOM_FIELD_ID and that the action was created by this importer",
+            field_id
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_value_or_suggests_the_right_field() {
+        let action = serde_json::json!({
+            "id": 12345,
+            "customfields": [
+                { "id": 7, "name": "CFOther", "value": "x" },
+                { "id": 123, "name": "CFActionID", "value": "1001" }
+            ]
+        });
+        assert_eq!(custom_field_value(&action, 123), Ok("1001".to_string()));
+
+        let err = custom_field_value(&action, 99).unwrap_err();
+        assert!(err.contains("set it to 123"), "{}", err);
+
+        let empty = serde_json::json!({
+            "customfields": [{ "id": 123, "name": "CFActionID", "value": null }]
+        });
+        assert!(
+            custom_field_value(&empty, 123)
+                .unwrap_err()
+                .contains("empty")
+        );
+        assert!(
+            custom_field_value(&serde_json::json!({}), 123)
+                .unwrap_err()
+                .contains("no custom field 123")
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::domain::importer::error::ImportError;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why a row was skipped instead of posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The action ID is already in the existing-ID report.
+    AlreadyExists,
+    /// An earlier POST found the row's ticket doesn't exist.
+    MissingTicket,
+    /// Another occurrence of the action ID in the same file is kept (`WITHIN_FILE_DUPLICATE`).
+    Duplicate { kept_row: usize },
+    /// The row's note is empty and `SKIP_EMPTY_NOTE` is `skip`.
+    EmptyNote,
+}
+
+/// Called with the action ID and ticket ID of every action a POST created.
+pub type ImportedHook = Arc<dyn Fn(&str, u32) + Send + Sync>;
+/// Called with the action ID of every skipped row and why it was skipped.
+pub type SkippedHook = Arc<dyn Fn(&str, SkipReason) + Send + Sync>;
+/// Called with the action ID (`"unknown"` if the row never parsed) and error of every failure.
+pub type FailedHook = Arc<dyn Fn(&str, &ImportError) + Send + Sync>;
+
+/// Per-row callbacks for crates embedding the importer, as an alternative to parsing logs.
+///
+/// Every hook defaults to a no-op. Hooks run inline on the processing task, so slow work
+/// should be handed off (e.g. to a channel) rather than done in the callback. Parse-only
+/// runs post nothing, so `on_imported` never fires there.
+#[derive(Clone)]
+pub struct ProcessHooks {
+    pub on_imported: ImportedHook,
+    pub on_skipped: SkippedHook,
+    pub on_failed: FailedHook,
+}
+
+impl ProcessHooks {
+    pub fn on_imported(mut self, hook: impl Fn(&str, u32) + Send + Sync + 'static) -> Self {
+        self.on_imported = Arc::new(hook);
+        self
+    }
+
+    pub fn on_skipped(mut self, hook: impl Fn(&str, SkipReason) + Send + Sync + 'static) -> Self {
+        self.on_skipped = Arc::new(hook);
+        self
+    }
+
+    pub fn on_failed(mut self, hook: impl Fn(&str, &ImportError) + Send + Sync + 'static) -> Self {
+        self.on_failed = Arc::new(hook);
+        self
+    }
+}
+
+impl Default for ProcessHooks {
+    fn default() -> Self {
+        Self {
+            on_imported: Arc::new(|_, _| {}),
+            on_skipped: Arc::new(|_, _| {}),
+            on_failed: Arc::new(|_, _| {}),
+        }
+    }
+}
+
+impl fmt::Debug for ProcessHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessHooks").finish_non_exhaustive()
+    }
+}
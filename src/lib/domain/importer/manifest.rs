@@ -0,0 +1,74 @@
+use crate::domain::models::action_object::ActionObject;
+use anyhow::Context;
+use chrono::Utc;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Permanent record of every action this run created, written as each POST succeeds.
+///
+/// Rows are flushed one POST at a time so a crash still leaves every import before it
+/// on disk.
+#[derive(Debug)]
+pub struct ImportManifest {
+    path: PathBuf,
+    writer: Mutex<csv::Writer<File>>,
+}
+
+impl ImportManifest {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create import manifest: {}", path.display()))?;
+        writer
+            .write_record(["imported_at", "action_id", "ticket_id", "source"])
+            .and_then(|()| writer.flush().map_err(csv::Error::from))
+            .with_context(|| format!("Failed to write import manifest: {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the actions of one successful POST and flushes them to disk.
+    pub fn record(&self, actions: &[ActionObject], source: &str) -> anyhow::Result<()> {
+        let imported_at = Utc::now().to_rfc3339();
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("import manifest lock poisoned"))?;
+        for action in actions {
+            writer.write_record([
+                imported_at.as_str(),
+                action.action_id(),
+                &action.ticket_id.to_string(),
+                source,
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::action_object::ActionId;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn records_are_on_disk_after_each_write() {
+        let path = TempPath::new("manifest.csv");
+        let manifest = ImportManifest::create(&path).unwrap();
+        let action = ActionObject::new(2997, None, None, "note", "agent", ActionId::new("1001"));
+        manifest.record(&[action], "actions.csv").unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "imported_at,action_id,ticket_id,source");
+        assert!(lines[1].ends_with(",1001,2997,actions.csv"));
+    }
+}
@@ -1,8 +1,45 @@
+pub mod agent_map;
+pub mod confirm;
+pub mod duplicates;
+pub mod error;
+pub mod estimate;
+pub mod field_check;
+pub mod hooks;
+pub mod manifest;
 pub mod processor;
+pub mod replay;
+pub mod run_history;
+pub mod sample;
+pub mod scan;
+pub mod self_test;
 pub mod setup;
+pub mod shutdown;
+pub mod staging;
 pub mod summary;
+pub mod ticket_map;
+pub mod verify;
 
-pub use processor::{process_csv_file, process_excel_file, ProcessingStats};
+pub use confirm::confirm_import;
+pub use error::{FailedAction, FailedActions, ImportError, PartialBatch};
+pub use estimate::{RunEstimate, RunEstimator};
+pub use hooks::{ProcessHooks, SkipReason};
+pub use manifest::ImportManifest;
+pub use processor::{
+    process_csv_file, process_excel_file, process_input_file, FailureThreshold, FileResult,
+    ProcessOptions, ProcessingStats, RunGuard,
+};
+#[cfg(feature = "sqlite")]
+pub use processor::process_sqlite_file;
+pub use replay::ReplayedRun;
+pub use run_history::{combined_summary, find_manifests, PastRun};
+pub use scan::{prescan_files, FileScan, PreScan};
+pub use sample::RowSampler;
+pub use self_test::run_self_test;
 pub use setup::{setup, SetupResult};
-pub use summary::{log_summary, ImportSummary};
+pub use shutdown::Shutdown;
+pub use staging::run_staging_phase;
+pub use summary::{log_summary, ImportSummary, LatencySamples, SummaryFormat};
 
+pub use agent_map::AgentMap;
+pub use ticket_map::TicketIdMap;
+pub use verify::{HeaderCheck, VerifyReport, check_headers, verify_file};
@@ -1,15 +1,527 @@
-use crate::inbound::file::{Reader, csv::Csv, excel::Excel};
+use crate::config::{
+    BadDatePolicy, EmptyNote, NoteLengthMode, RequiredField, SourceFileDetail, UndatedRows,
+    WithinFileDuplicate,
+};
+use crate::domain::importer::agent_map::AgentMap;
+use crate::domain::importer::duplicates::{DuplicateCheck, DuplicateVerdict, index_duplicates};
+use crate::domain::importer::error::{FailedAction, FailedActions, ImportError, PartialBatch};
+use crate::domain::importer::estimate::RunEstimator;
+use crate::domain::importer::hooks::{ProcessHooks, SkipReason};
+use crate::domain::importer::manifest::ImportManifest;
+use crate::domain::importer::run_history::RejectsWriter;
+use crate::domain::importer::sample::RowSampler;
+use crate::domain::importer::shutdown::Shutdown;
+use crate::domain::importer::ticket_map::TicketIdMap;
+use crate::domain::models::action_object::{ActionDate, ActionObject, DEFAULT_OUTCOME};
+use crate::domain::models::timezone::SourceTimezone;
+#[cfg(feature = "sqlite")]
+use crate::inbound::file::sqlite::Sqlite;
+use crate::inbound::file::{ColumnMapping, InputKind, Reader, csv::Csv, excel::Excel};
 use crate::outbound::client::action::ActionClient;
-use std::collections::HashSet;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::time::Instant;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
 pub struct ProcessingStats {
     pub processed: usize,
     pub imported: usize,
     pub skipped: usize,
-    pub failed: Vec<(String, String)>,
+    pub failed: FailedActions,
+    pub row_times: Vec<f64>,
+    /// Set when processing stopped early; the remaining rows and files should not be processed.
+    pub aborted: Option<String>,
+    /// Rows posted (or, in parse-only mode, that would have been posted), counted against `max_rows`.
+    pub posted: usize,
+    /// Processing stopped because `max_rows` was reached.
+    pub row_cap_reached: bool,
+    /// Processing stopped because the run's `deadline` passed.
+    pub deadline_reached: bool,
+    /// Processing stopped because the operator declined to go on after `--estimate`.
+    pub estimate_declined: bool,
+    /// Processing stopped because a shutdown was requested (SIGTERM).
+    pub shutdown_requested: bool,
+    /// Rows left out by `SAMPLE_RATE`; not counted as processed.
+    pub sampled_out: usize,
+    /// Rows left out for an empty note (`SKIP_EMPTY_NOTE=skip`); counted as processed.
+    pub skipped_empty_note: usize,
+}
+
+/// What processing one file (or Excel sheet, or SQLite table) came to, with where the rows
+/// came from.
+pub struct FileResult {
+    pub file_name: String,
+    /// The Excel sheet or SQLite table the rows were read from; `None` for CSV files.
+    pub sheet_name: Option<String>,
+    pub stats: ProcessingStats,
+    /// Time spent on the sheet, from its first row to the end of its last post.
+    pub duration: Duration,
+}
+
+/// Stops the run once failures pass a share of its finished rows (`ABORT_FAILURE_RATIO`).
+///
+/// A row is finished once it has been imported, skipped, or failed. The caller passes the
+/// run's totals from earlier files so the ratio covers the whole run, not one file. Files
+/// processed at the same time share a [`RunGuard`] instead, leaving the totals at zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailureThreshold {
+    /// Abort when more than this fraction of finished rows has failed.
+    pub ratio: f64,
+    /// Finished rows needed before the ratio is checked, so a few early failures can't abort.
+    pub min_sample: usize,
+    pub finished_before: usize,
+    pub failed_before: usize,
+}
+
+impl FailureThreshold {
+    /// Whether `failed` of `finished` rows, this file's or with a [`RunGuard`] every
+    /// file's, put the run over the ratio.
+    fn passed(&self, finished: usize, failed: usize) -> bool {
+        let finished = self.finished_before + finished;
+        let failed = self.failed_before + failed;
+        finished >= self.min_sample && finished > 0 && failed as f64 / finished as f64 > self.ratio
+    }
+
+    /// Why the run should stop, when `failed` of `finished` rows push the ratio over the
+    /// threshold. The most common failure in `file_failed` is named as the likely cause.
+    fn exceeded(
+        &self,
+        finished: usize,
+        failed: usize,
+        file_failed: &FailedActions,
+    ) -> Option<String> {
+        if !self.passed(finished, failed) {
+            return None;
+        }
+        let finished = self.finished_before + finished;
+        let failed_total = self.failed_before + failed;
+        let ratio = failed_total as f64 / finished as f64;
+        let mut cause = String::new();
+        if let Some((category, count)) = file_failed
+            .by_category()
+            .iter()
+            .map(|(category, count)| (*category, *count))
+            .max_by_key(|&(category, count)| (count, std::cmp::Reverse(category)))
+        {
+            let latest = file_failed.latest(category).unwrap_or_default();
+            cause = format!(
+                ". Most failures in this file are {} errors ({}), latest: {}",
+                category,
+                format_number(count),
+                latest
+            );
+        }
+        Some(format!(
+            "{} of {} finished rows failed ({:.1}%), over ABORT_FAILURE_RATIO of {:.1}%{}. This usually means something systemic, such as a wrong endpoint or expired credentials or permissions",
+            format_number(failed_total),
+            format_number(finished),
+            ratio * 100.0,
+            self.ratio * 100.0,
+            cause
+        ))
+    }
+}
+
+/// What the files processed at the same time (`FILE_CONCURRENCY`) have learned from each
+/// other, checked by every file before each post.
+///
+/// Each file adds its finished and failed rows as it goes, so the failure ratio covers the
+/// files running alongside it. Once one file aborts the run, under fail-fast or
+/// `ABORT_FAILURE_RATIO`, the others post nothing more and count their queued rows as not
+/// posted. Sheet times are collected here too, so the average sheet time logged as each
+/// sheet completes covers every file.
+#[derive(Debug, Default)]
+pub struct RunGuard {
+    finished: AtomicUsize,
+    failed: AtomicUsize,
+    aborted: Mutex<Option<String>>,
+    sheet_times: Mutex<Vec<f64>>,
+}
+
+impl RunGuard {
+    fn add(&self, finished: usize, failed: usize) {
+        self.finished.fetch_add(finished, Ordering::SeqCst);
+        self.failed.fetch_add(failed, Ordering::SeqCst);
+    }
+
+    /// Finished and failed rows across every file so far.
+    fn totals(&self) -> (usize, usize) {
+        (
+            self.finished.load(Ordering::SeqCst),
+            self.failed.load(Ordering::SeqCst),
+        )
+    }
+
+    /// Aborts the run; only the first reason is kept.
+    fn abort(&self, reason: &str) {
+        self.aborted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(|| reason.to_string());
+    }
+
+    /// Why the run was aborted, once any file has aborted it.
+    pub fn aborted(&self) -> Option<String> {
+        self.aborted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Adds a completed sheet's time and returns the average across every file's sheets.
+    fn add_sheet_time(&self, secs: f64) -> f64 {
+        let mut sheet_times = self.sheet_times.lock().unwrap_or_else(|e| e.into_inner());
+        sheet_times.push(secs);
+        sheet_times.iter().sum::<f64>() / sheet_times.len() as f64
+    }
+}
+
+/// Run-wide options shared by every file the processor handles.
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    pub only_parse: bool,
+    pub batch_size: usize,
+    /// Batches in flight at once (`ORDERED_CONCURRENCY`). Batches sharing a ticket are still
+    /// posted in source order, and every result is recorded in source order.
+    pub ordered_concurrency: usize,
+    /// Stop at the first failed import instead of continuing with the remaining rows.
+    pub fail_fast: bool,
+    /// Maximum note length in characters; longer notes are handled per `note_length_mode`.
+    pub max_note_len: Option<usize>,
+    pub note_length_mode: NoteLengthMode,
+    /// Maximum POST body size; a batch is sent early rather than grow past it.
+    pub max_batch_bytes: Option<usize>,
+    /// Suppress per-row success and skip lines; progress, summary, warnings, and errors still log.
+    pub quiet: bool,
+    /// Count CSV rows up front so progress can show a total and ETA; costs a second read.
+    pub csv_count_rows: bool,
+    /// Stop once this many rows have been posted from this file; the caller passes the
+    /// remaining run-wide budget.
+    pub max_rows: Option<usize>,
+    /// Header mapping and defaults applied by the file readers.
+    pub column_mapping: ColumnMapping,
+    /// Rewrites source ticket IDs to Halo IDs before skip checks and posting.
+    pub ticket_id_map: Option<Arc<TicketIdMap>>,
+    pub agent_map: Option<Arc<AgentMap>>,
+    /// Processes only the rows this selects when set (`SAMPLE_RATE`).
+    pub sampler: Option<RowSampler>,
+    /// Timezone for naive action dates in rows without a timezone column value.
+    pub source_timezone: Option<SourceTimezone>,
+    /// Records every successful POST when set.
+    pub manifest: Option<Arc<ImportManifest>>,
+    /// Failures each file holds in memory (`MAX_FAILURES_KEPT`); all when unset.
+    pub max_failures_kept: Option<usize>,
+    /// Where failures past `max_failures_kept` are written as they happen.
+    pub rejects: Option<Arc<RejectsWriter>>,
+    /// Handling of action IDs repeated within one file.
+    pub within_file_duplicate: WithinFileDuplicate,
+    /// Handling of rows whose note is empty or only whitespace (`SKIP_EMPTY_NOTE`).
+    pub empty_note: EmptyNote,
+    /// Fields a row must have non-empty (`REQUIRED_FIELDS`). Blank ticket and action IDs
+    /// already fail as the row is read or resolved.
+    pub required_fields: Vec<RequiredField>,
+    /// Handling of rows whose date matches no accepted format (`BAD_DATE_POLICY`).
+    pub bad_date_policy: BadDatePolicy,
+    /// Per-row callbacks for embedders; no-ops by default.
+    pub hooks: ProcessHooks,
+    /// Aborts the run once too many of its rows have failed.
+    pub failure_threshold: Option<FailureThreshold>,
+    /// Buffer each sheet and post its rows in date order (`SORT_BY_DATE`).
+    pub sort_by_date: Option<UndatedRows>,
+    /// Stop taking new rows once this passes (`MAX_RUN_SECS`); the batch being built is
+    /// still posted.
+    pub deadline: Option<Instant>,
+    /// Times the run's first posted rows and asks whether to go on (`--estimate`).
+    pub estimator: Option<Arc<RunEstimator>>,
+    /// Stop taking new rows once a shutdown is requested; the batch being built is still
+    /// posted.
+    pub shutdown: Option<Arc<Shutdown>>,
+    /// Shared by files processed at the same time, so an abort in one stops them all.
+    pub run_guard: Option<Arc<RunGuard>>,
+    /// Put before and after every note, with `{date}` already expanded.
+    pub note_prefix: String,
+    pub note_suffix: String,
+    /// Records each row's file (and sheet and row, per `SOURCE_FILE_DETAIL`) on the action
+    /// when set; only needed when `SOURCE_FILE_CUSTOM_FIELD_ID` is.
+    pub source_location: Option<SourceFileDetail>,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            only_parse: false,
+            batch_size: 1,
+            ordered_concurrency: 1,
+            fail_fast: false,
+            max_note_len: None,
+            note_length_mode: NoteLengthMode::default(),
+            max_batch_bytes: None,
+            quiet: false,
+            csv_count_rows: true,
+            max_rows: None,
+            column_mapping: ColumnMapping::default(),
+            ticket_id_map: None,
+            agent_map: None,
+            sampler: None,
+            source_timezone: None,
+            manifest: None,
+            max_failures_kept: None,
+            rejects: None,
+            within_file_duplicate: WithinFileDuplicate::default(),
+            empty_note: EmptyNote::default(),
+            required_fields: RequiredField::ALWAYS.to_vec(),
+            bad_date_policy: BadDatePolicy::default(),
+            hooks: ProcessHooks::default(),
+            failure_threshold: None,
+            sort_by_date: None,
+            deadline: None,
+            estimator: None,
+            shutdown: None,
+            run_guard: None,
+            note_prefix: String::new(),
+            note_suffix: String::new(),
+            source_location: None,
+        }
+    }
+}
+
+/// Sets the Halo ticket ID from the row's source reference, through the map when one is
+/// loaded, returning the rejection message if it is unmapped or, without a map, not numeric.
+fn resolve_ticket_id(action: &mut ActionObject, options: &ProcessOptions) -> Result<(), String> {
+    let source_id = action.source_ticket_id.as_str();
+    let halo_id = match &options.ticket_id_map {
+        Some(map) => map.lookup(source_id).ok_or_else(|| {
+            format!(
+                "Ticket ID: {} for action ID: {} is not in the ticket ID map",
+                source_id,
+                action.action_id()
+            )
+        })?,
+        None => source_id.parse().map_err(|_| {
+            format!(
+                "Ticket ID: '{}' for action ID: {} is not a numeric Halo ticket ID. Map source references to Halo IDs with TICKET_ID_MAP_PATH",
+                source_id,
+                action.action_id()
+            )
+        })?,
+    };
+    action.ticket_id = halo_id;
+    Ok(())
+}
+
+/// The first `REQUIRED_FIELDS` field the row leaves blank. `note_empty` is checked before
+/// `NOTE_PREFIX`/`NOTE_SUFFIX` are added; a date that can't be read isn't blank, and is
+/// left to `BAD_DATE_POLICY`.
+fn check_required_fields(
+    action: &ActionObject,
+    note_empty: bool,
+    options: &ProcessOptions,
+) -> Result<(), &'static str> {
+    let blank = options.required_fields.iter().find(|field| match field {
+        RequiredField::Note => note_empty,
+        RequiredField::ActionWho => action.actionwho.trim().is_empty(),
+        RequiredField::ActionDate => action.actiondate.is_none() && action.unparsed_date.is_none(),
+        RequiredField::TicketId | RequiredField::ActionId => false,
+    });
+    match blank {
+        Some(field) => Err(field.name()),
+        None => Ok(()),
+    }
+}
+
+/// Resolves the row's `actionwho` to a Halo agent ID, returning the rejection message if unmapped.
+fn apply_agent_map(action: &mut ActionObject, options: &ProcessOptions) -> Result<(), String> {
+    let Some(map) = &options.agent_map else {
+        return Ok(());
+    };
+    match map.lookup(&action.actionwho) {
+        Some(agent_id) => {
+            action.agent_id = Some(agent_id);
+            Ok(())
+        }
+        None if map.pass_through() => Ok(()),
+        None => Err(format!(
+            "Agent: '{}' for action ID: {} is not in the agent map",
+            action.actionwho,
+            action.action_id()
+        )),
+    }
+}
+
+/// Sets the timezone the row's naive date is read in, from its timezone column or the
+/// run-wide default, returning the rejection message if the row's value is unrecognized.
+fn apply_source_timezone(
+    action: &mut ActionObject,
+    options: &ProcessOptions,
+) -> Result<(), String> {
+    let row_timezone = action
+        .source_timezone
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let timezone = match row_timezone {
+        Some(value) => Some(SourceTimezone::parse(value).map_err(|e| {
+            format!(
+                "{} in the timezone column for action ID: {}",
+                e,
+                action.action_id()
+            )
+        })?),
+        None => options.source_timezone,
+    };
+    if let (Some(timezone), Some(date)) = (timezone, action.actiondate.as_mut()) {
+        date.set_timezone(timezone);
+    }
+    Ok(())
+}
+
+/// Gives a blank or whitespace-only outcome the default. Both readers read an empty outcome
+/// cell as `""`, which would otherwise be posted as is.
+fn apply_default_outcome(action: &mut ActionObject, options: &ProcessOptions) {
+    if action.outcome.trim().is_empty() {
+        action.outcome = options
+            .column_mapping
+            .default_outcome
+            .clone()
+            .unwrap_or_else(|| DEFAULT_OUTCOME.to_string());
+    }
+}
+
+/// Wraps the note in `NOTE_PREFIX` and `NOTE_SUFFIX`. `note_html` is serialized from the
+/// same field, so it gets them too.
+fn apply_note_affixes(action: &mut ActionObject, options: &ProcessOptions) {
+    if options.note_prefix.is_empty() && options.note_suffix.is_empty() {
+        return;
+    }
+    action.note = format!(
+        "{}{}{}",
+        options.note_prefix, action.note, options.note_suffix
+    );
+}
+
+/// Readies an action built outside a file, such as by the `post` command, with the same
+/// per-row steps as an import: default outcome, `NOTE_PREFIX`/`NOTE_SUFFIX`,
+/// `REQUIRED_FIELDS`, the ticket and agent maps, the timezone, and `MAX_NOTE_LEN`. Returns
+/// the rejection message a file row would have failed with.
+pub fn prepare_action(action: &mut ActionObject, options: &ProcessOptions) -> Result<(), String> {
+    let note_empty = action.note.trim().is_empty();
+    apply_default_outcome(action, options);
+    apply_note_affixes(action, options);
+    check_required_fields(action, note_empty, options).map_err(|field| {
+        format!(
+            "Action ID: {} has no {}, which REQUIRED_FIELDS lists",
+            action.action_id(),
+            field
+        )
+    })?;
+    if note_empty && options.empty_note != EmptyNote::Post {
+        return Err(format!(
+            "Action ID: {} has an empty note",
+            action.action_id()
+        ));
+    }
+    resolve_ticket_id(action, options)?;
+    apply_agent_map(action, options)?;
+    apply_source_timezone(action, options)?;
+    action.action_id.cfactionid()?;
+    enforce_note_limit(action, options)
+}
+
+/// Where a row came from, as written to `SOURCE_FILE_CUSTOM_FIELD_ID`.
+fn source_location(
+    detail: SourceFileDetail,
+    file_name: &str,
+    sheet_name: Option<&str>,
+    row: usize,
+) -> String {
+    let mut parts = vec![file_name.to_string()];
+    if detail != SourceFileDetail::File
+        && let Some(sheet) = sheet_name
+    {
+        parts.push(sheet.to_string());
+    }
+    if detail == SourceFileDetail::Row {
+        parts.push(format!("row {}", row));
+    }
+    parts.join(" / ")
+}
+
+const NOTE_TRUNCATION_MARKER: &str = " [truncated]";
+
+/// Applies the note length cap before posting, returning the rejection message in fail mode.
+fn enforce_note_limit(action: &mut ActionObject, options: &ProcessOptions) -> Result<(), String> {
+    let Some(max_len) = options.max_note_len else {
+        return Ok(());
+    };
+    let note_len = action.note.chars().count();
+    if note_len <= max_len {
+        return Ok(());
+    }
+    match options.note_length_mode {
+        NoteLengthMode::Fail => Err(format!(
+            "Note for action ID: {} (ticket ID: {}) is {} characters, exceeding the limit of {}",
+            action.action_id(),
+            action.ticket_id,
+            note_len,
+            max_len
+        )),
+        NoteLengthMode::Truncate => {
+            let marker_len = NOTE_TRUNCATION_MARKER.chars().count();
+            action.note = if max_len > marker_len {
+                let mut truncated: String =
+                    action.note.chars().take(max_len - marker_len).collect();
+                truncated.push_str(NOTE_TRUNCATION_MARKER);
+                truncated
+            } else {
+                action.note.chars().take(max_len).collect()
+            };
+            warn!(
+                "Truncated note for action ID: {} (ticket ID: {}) from {} to {} characters",
+                action.action_id(),
+                action.ticket_id,
+                note_len,
+                max_len
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Serialized size of the action in a POST body, or the rejection message if it can't fit
+/// under `max_batch_bytes` even when sent alone. `None` when there is no limit.
+fn check_payload_size(
+    action: &ActionObject,
+    client: Option<&ActionClient>,
+    options: &ProcessOptions,
+) -> Result<Option<usize>, String> {
+    let Some(max_bytes) = options.max_batch_bytes else {
+        return Ok(None);
+    };
+    let bytes = match client {
+        Some(client) => client.payload_bytes(action),
+        // Parse-only: no client, so size the payload with every field included and the
+        // widest field ID, which a real POST body can't be larger than
+        None => serde_json::to_vec(&action.payload(u32::MAX, &[]))
+            .map(|bytes| bytes.len())
+            .unwrap_or(0),
+    };
+    // A batch on its own is `[` + action + `]`
+    if bytes + 2 > max_bytes {
+        return Err(format!(
+            "Payload for action ID: {} (ticket ID: {}) is {} bytes, exceeding MAX_BATCH_BYTES of {}",
+            action.action_id(),
+            action.ticket_id,
+            bytes + 2,
+            max_bytes
+        ));
+    }
+    Ok(Some(bytes))
 }
 
 struct ProcessConfig<'a> {
@@ -17,11 +529,28 @@ struct ProcessConfig<'a> {
     action_client: Option<&'a ActionClient>,
     sheet_times: &'a mut Vec<f64>,
     file_name: &'a str,
+    sheet_name: Option<&'a str>,
+    /// The file is a SQLite database and `sheet_name` is the table it was read from.
+    is_database: bool,
     sheet_number: usize,
     total_sheets: usize,
-    only_parse: bool,
+    options: &'a ProcessOptions,
     missing_tickets: &'a mut HashSet<u32>,
-    batch_size: usize,
+    duplicates: DuplicateCheck,
+    progress_every_rows: usize,
+    progress_every_secs: u64,
+}
+
+impl ProcessConfig<'_> {
+    fn source(&self) -> String {
+        match self.sheet_name {
+            Some(table) if self.is_database => {
+                format!("SQLite database '{}', table '{}'", self.file_name, table)
+            }
+            Some(sheet) => format!("Excel file '{}', sheet '{}'", self.file_name, sheet),
+            None => format!("CSV file '{}'", self.file_name),
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -33,584 +562,1140 @@ pub async fn process_csv_file(
     file_name: &str,
     sheet_number: usize,
     total_sheets: usize,
-    only_parse: bool,
-    batch_size: usize,
-) -> anyhow::Result<ProcessingStats> {
+    options: &ProcessOptions,
+) -> anyhow::Result<FileResult> {
     let mut missing_tickets: HashSet<u32> = HashSet::new();
+    let (progress_every_rows, progress_every_secs) = if options.only_parse {
+        (10_000, 5)
+    } else {
+        (100, 60)
+    };
+    let iter = if options.csv_count_rows {
+        <Reader as Csv>::csv_action_iter(file_path, &options.column_mapping)?
+    } else {
+        // Skip the counting pass; progress is logged without a total
+        let file = std::fs::File::open(file_path)
+            .with_context(|| format!("failed to open csv file: {}", file_name))?;
+        <Reader as Csv>::csv_action_iter_from_reader(file, file_name, &options.column_mapping)
+    };
+    // One error for the file rather than the same one for every row
+    ColumnMapping::check_required_columns(iter.headers(), &format!("csv file '{}'", file_name))?;
+    let duplicate_rows = if DuplicateCheck::needs_index(options.within_file_duplicate) {
+        let file = std::fs::File::open(file_path)
+            .with_context(|| format!("failed to open csv file: {}", file_name))?;
+        index_duplicates(<Reader as Csv>::csv_action_iter_from_reader(
+            file,
+            file_name,
+            &options.column_mapping,
+        ))
+    } else {
+        HashMap::new()
+    };
     let config = ProcessConfig {
         existing_ids,
         action_client,
         sheet_times,
         file_name,
+        sheet_name: None,
+        is_database: false,
         sheet_number,
         total_sheets,
-        only_parse,
+        options,
         missing_tickets: &mut missing_tickets,
-        batch_size,
+        duplicates: DuplicateCheck::new(options.within_file_duplicate, duplicate_rows),
+        progress_every_rows,
+        progress_every_secs,
     };
-    let iter = <Reader as Csv>::csv_action_iter(file_path)?;
     let total_rows = iter.total_rows();
-    let mut processed = 0;
-    let mut imported = 0;
-    let mut skipped = 0;
-    let mut failed = Vec::new();
+    Ok(process_rows(iter, total_rows, config).await)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn process_excel_file(
+    file_path: &Path,
+    existing_ids: &HashSet<String>,
+    action_client: Option<&ActionClient>,
+    sheet_times: &mut Vec<f64>,
+    file_name: &str,
+    sheet_number: usize,
+    total_sheets: usize,
+    options: &ProcessOptions,
+) -> anyhow::Result<FileResult> {
+    let mut missing_tickets: HashSet<u32> = HashSet::new();
+    let iter = <Reader as Excel>::excel_action_iter(file_path, &options.column_mapping)?;
+    let total_rows = iter.total_rows();
+    let sheet_name = iter.sheet_name().to_string();
+    ColumnMapping::check_required_columns(
+        iter.headers(),
+        &format!("sheet '{}' of excel file '{}'", sheet_name, file_name),
+    )?;
+    // A second read of the sheet, only for the policies that need to see later rows
+    let duplicate_rows = if DuplicateCheck::needs_index(options.within_file_duplicate) {
+        index_duplicates(<Reader as Excel>::excel_action_iter(
+            file_path,
+            &options.column_mapping,
+        )?)
+    } else {
+        HashMap::new()
+    };
+    let config = ProcessConfig {
+        existing_ids,
+        action_client,
+        sheet_times,
+        file_name,
+        sheet_name: Some(&sheet_name),
+        is_database: false,
+        sheet_number,
+        total_sheets,
+        options,
+        missing_tickets: &mut missing_tickets,
+        duplicates: DuplicateCheck::new(options.within_file_duplicate, duplicate_rows),
+        progress_every_rows: 300,
+        progress_every_secs: 60,
+    };
+    Ok(process_rows(iter, total_rows, config).await)
+}
+
+/// Imports the `SQLITE_TABLE` table or view of a SQLite database, one row per action.
+#[cfg(feature = "sqlite")]
+pub async fn process_sqlite_file(
+    file_path: &Path,
+    existing_ids: &HashSet<String>,
+    action_client: Option<&ActionClient>,
+    sheet_times: &mut Vec<f64>,
+    sheet_number: usize,
+    total_sheets: usize,
+    options: &ProcessOptions,
+) -> anyhow::Result<FileResult> {
+    let mut missing_tickets: HashSet<u32> = HashSet::new();
+    let file_name = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown file");
+    let iter = <Reader as Sqlite>::sqlite_action_iter(file_path, &options.column_mapping)?;
+    let total_rows = iter.total_rows();
+    let table = iter.table().to_string();
+    ColumnMapping::check_required_columns(
+        iter.headers(),
+        &format!("table '{}' of sqlite database '{}'", table, file_name),
+    )?;
+    let duplicate_rows = if DuplicateCheck::needs_index(options.within_file_duplicate) {
+        index_duplicates(<Reader as Sqlite>::sqlite_action_iter(
+            file_path,
+            &options.column_mapping,
+        )?)
+    } else {
+        HashMap::new()
+    };
+    let config = ProcessConfig {
+        existing_ids,
+        action_client,
+        sheet_times,
+        file_name,
+        sheet_name: Some(&table),
+        is_database: true,
+        sheet_number,
+        total_sheets,
+        options,
+        missing_tickets: &mut missing_tickets,
+        duplicates: DuplicateCheck::new(options.within_file_duplicate, duplicate_rows),
+        progress_every_rows: 300,
+        progress_every_secs: 60,
+    };
+    Ok(process_rows(iter, total_rows, config).await)
+}
+
+/// Processes one input file with the reader its extension calls for. `None` for a file type
+/// the importer doesn't read.
+#[allow(clippy::too_many_arguments)]
+pub async fn process_input_file(
+    file_path: &Path,
+    file_name: &str,
+    existing_ids: &HashSet<String>,
+    action_client: Option<&ActionClient>,
+    sheet_times: &mut Vec<f64>,
+    sheet_number: usize,
+    total_sheets: usize,
+    options: &ProcessOptions,
+) -> Option<anyhow::Result<FileResult>> {
+    Some(match InputKind::of(file_path)? {
+        InputKind::Csv => {
+            process_csv_file(
+                file_path,
+                existing_ids,
+                action_client,
+                sheet_times,
+                file_name,
+                sheet_number,
+                total_sheets,
+                options,
+            )
+            .await
+        }
+        InputKind::Spreadsheet => {
+            process_excel_file(
+                file_path,
+                existing_ids,
+                action_client,
+                sheet_times,
+                file_name,
+                sheet_number,
+                total_sheets,
+                options,
+            )
+            .await
+        }
+        #[cfg(feature = "sqlite")]
+        InputKind::Sqlite => {
+            process_sqlite_file(
+                file_path,
+                existing_ids,
+                action_client,
+                sheet_times,
+                sheet_number,
+                total_sheets,
+                options,
+            )
+            .await
+        }
+        // Archives are extracted when the files are discovered
+        InputKind::Archive => return None,
+    })
+}
+
+/// A row and its 1-based position in the file, kept through sorting for row references.
+type NumberedRow = (usize, anyhow::Result<ActionObject>);
+
+/// Where a row lands in `SORT_BY_DATE` order: a rank group, then the instant within it.
+type SortKey = (u8, Option<DateTime<Utc>>);
+
+/// Numbers the rows and, with `SORT_BY_DATE`, puts them in date order before processing.
+async fn process_rows<I>(
+    iter: I,
+    total_rows: Option<usize>,
+    config: ProcessConfig<'_>,
+) -> FileResult
+where
+    I: Iterator<Item = anyhow::Result<ActionObject>>,
+{
+    let rows = iter.enumerate().map(|(idx, row)| (idx + 1, row));
+    match config.options.sort_by_date {
+        Some(undated) => {
+            let sorted = sort_rows_by_date(rows, undated, config.options);
+            info!(
+                "Sorted {} rows of {} by action date",
+                format_number(sorted.len()),
+                config.source()
+            );
+            process_actions(sorted.into_iter(), total_rows, config).await
+        }
+        None => process_actions(rows, total_rows, config).await,
+    }
+}
+
+/// The instant a row sorts by, read in the timezone `apply_source_timezone` will give it.
+fn sort_instant(action: &ActionObject, options: &ProcessOptions) -> Option<DateTime<Utc>> {
+    let mut date = action.actiondate?;
+    let row_timezone = action
+        .source_timezone
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .and_then(|value| SourceTimezone::parse(value).ok());
+    if let Some(timezone) = row_timezone.or(options.source_timezone) {
+        date.set_timezone(timezone);
+    }
+    Some(date.to_utc())
+}
+
+/// Buffers every row of a sheet and orders them by action date. Rows that failed to parse
+/// come first so they are reported before anything is posted; ties keep file order.
+fn sort_rows_by_date(
+    rows: impl Iterator<Item = NumberedRow>,
+    undated: UndatedRows,
+    options: &ProcessOptions,
+) -> Vec<NumberedRow> {
+    let mut keyed: Vec<(SortKey, NumberedRow)> = rows
+        .map(|row| {
+            let key = match &row.1 {
+                Err(_) => (0, None),
+                Ok(action)
+                    if options.bad_date_policy == BadDatePolicy::Fail
+                        && action.date_error().is_some() =>
+                {
+                    (0, None)
+                }
+                Ok(action) => match sort_instant(action, options) {
+                    Some(instant) => (2, Some(instant)),
+                    None if undated == UndatedRows::First => (1, None),
+                    None => (3, None),
+                },
+            };
+            (key, row)
+        })
+        .collect();
+    keyed.sort_by_key(|(key, _)| *key);
+    keyed.into_iter().map(|(_, row)| row).collect()
+}
+
+struct SheetState {
+    processed: usize,
+    imported: usize,
+    skipped: usize,
+    sampled_out: usize,
+    skipped_empty_note: usize,
+    failed: FailedActions,
+    row_times: Vec<f64>,
+    pending_skips: usize,
+    batch: Vec<ActionObject>,
+    /// Serialized size of `batch` as a JSON array.
+    batch_bytes: usize,
+    /// Full batches waiting to be posted together under `ORDERED_CONCURRENCY`.
+    window: Vec<Vec<ActionObject>>,
+    batch_start: Instant,
+    aborted: Option<String>,
+    run_guard: Option<Arc<RunGuard>>,
+    /// Finished and failed rows already added to `run_guard`.
+    reported: (usize, usize),
+    quiet: bool,
+    hooks: ProcessHooks,
+}
+
+impl SheetState {
+    fn new(options: &ProcessOptions) -> Self {
+        Self {
+            processed: 0,
+            imported: 0,
+            skipped: 0,
+            sampled_out: 0,
+            skipped_empty_note: 0,
+            failed: FailedActions::new(options.max_failures_kept, options.rejects.clone()),
+            row_times: Vec::new(),
+            pending_skips: 0,
+            batch: Vec::new(),
+            batch_bytes: 2,
+            window: Vec::new(),
+            batch_start: Instant::now(),
+            aborted: None,
+            run_guard: options.run_guard.clone(),
+            reported: (0, 0),
+            quiet: options.quiet,
+            hooks: options.hooks.clone(),
+        }
+    }
+
+    fn finished(&self) -> usize {
+        self.imported + self.skipped + self.skipped_empty_note + self.failed.len()
+    }
+
+    /// Adds the rows finished and failed since the last call to the shared [`RunGuard`].
+    fn report_to_run(&mut self) {
+        let counts = (self.finished(), self.failed.len());
+        if let Some(run_guard) = &self.run_guard {
+            run_guard.add(counts.0 - self.reported.0, counts.1 - self.reported.1);
+            self.reported = counts;
+        }
+    }
+
+    /// Finished and failed rows the failure ratio is checked against: this file's, or with
+    /// a [`RunGuard`] every file's.
+    fn ratio_counts(&mut self) -> (usize, usize) {
+        self.report_to_run();
+        match &self.run_guard {
+            Some(run_guard) => run_guard.totals(),
+            None => (self.finished(), self.failed.len()),
+        }
+    }
+
+    /// Stops this file and, with a [`RunGuard`], the files running alongside it.
+    fn abort(&mut self, reason: String) {
+        if self.aborted.is_some() {
+            return;
+        }
+        if let Some(run_guard) = &self.run_guard {
+            run_guard.abort(&reason);
+        }
+        self.aborted = Some(reason);
+    }
+
+    /// Whether nothing more should be posted: this file aborted, or another file aborted
+    /// the run, in which case this file takes its reason.
+    fn check_aborted(&mut self) -> bool {
+        if self.aborted.is_none() {
+            self.aborted = self.run_guard.as_ref().and_then(|guard| guard.aborted());
+        }
+        self.aborted.is_some()
+    }
+
+    fn flush_pending_skips(&mut self) {
+        if self.pending_skips > 0 && !self.quiet {
+            info!(
+                "Skipped {} entries (already exist)",
+                format_number(self.pending_skips)
+            );
+        }
+        self.pending_skips = 0;
+    }
+
+    fn record_skip(&mut self, action_id: &str, reason: SkipReason) {
+        self.skipped += 1;
+        (self.hooks.on_skipped)(action_id, reason);
+    }
+
+    fn record_failure(&mut self, action_id: String, error: ImportError) {
+        error!("{}", error);
+        (self.hooks.on_failed)(&action_id, &error);
+        self.failed.push(FailedAction { action_id, error });
+    }
+
+    /// Records a failed POST; `error_msg` adds the action and ticket context to the log line.
+    fn record_import_failure(
+        &mut self,
+        action_id: String,
+        error_msg: String,
+        error: ImportError,
+        fail_fast: bool,
+    ) {
+        error!("{}", error_msg);
+        (self.hooks.on_failed)(&action_id, &error);
+        if fail_fast {
+            self.abort(format!("fail-fast triggered by {}", error_msg));
+        }
+        self.failed.push(FailedAction { action_id, error });
+    }
+}
+
+async fn process_actions<I>(
+    iter: I,
+    total_rows: Option<usize>,
+    mut config: ProcessConfig<'_>,
+) -> FileResult
+where
+    I: Iterator<Item = NumberedRow>,
+{
+    let source = config.source();
     let sheet_start = Instant::now();
-    let mut row_times: Vec<f64> = Vec::new();
     let mut last_progress_log = Instant::now();
-    let mut pending_skips = 0usize;
-    let mut batch: Vec<crate::domain::models::action_object::ActionObject> = Vec::new();
-    let mut batch_start = Instant::now();
+    let mut state = SheetState::new(config.options);
+    let mut rows_left = config.options.max_rows;
+    let mut posted = 0;
     if let Some(total) = total_rows {
         info!(
-            "Processing sheet {} of {}: CSV file '{}' ({} rows)",
+            "Processing sheet {} of {}: {} ({} rows)",
             config.sheet_number,
             config.total_sheets,
-            config.file_name,
+            source,
             format_number(total)
         );
     } else {
         info!(
-            "Processing sheet {} of {}: CSV file '{}'",
-            config.sheet_number, config.total_sheets, config.file_name
+            "Processing sheet {} of {}: {}",
+            config.sheet_number, config.total_sheets, source
         );
     }
-    for action_result in iter {
-        let action = match action_result {
+    for (row, action_result) in iter {
+        let mut action = match action_result {
             Ok(a) => a,
             Err(e) => {
-                if pending_skips > 0 {
-                    info!(
-                        "Skipped {} entries (already exist)",
-                        format_number(pending_skips)
+                state.flush_pending_skips();
+                let error_msg = format!("Failed to deserialize row in {}: {}", source, e);
+                state.record_failure("unknown".to_string(), ImportError::Deserialize(error_msg));
+                continue;
+            }
+        };
+        if let Some(date_error) = action.date_error() {
+            match config.options.bad_date_policy {
+                BadDatePolicy::Fail => {
+                    state.flush_pending_skips();
+                    let error_msg =
+                        format!("Failed to deserialize row in {}: {}", source, date_error);
+                    state.record_failure(
+                        action.action_id().to_string(),
+                        ImportError::Deserialize(error_msg),
+                    );
+                    continue;
+                }
+                BadDatePolicy::Null => {
+                    warn!(
+                        "Action ID: {} on row {} of {}: {}, posting it without a date",
+                        action.action_id(),
+                        row,
+                        source,
+                        date_error
+                    );
+                }
+                BadDatePolicy::Now => {
+                    warn!(
+                        "Action ID: {} on row {} of {}: {}, dating it now",
+                        action.action_id(),
+                        row,
+                        source,
+                        date_error
                     );
-                    pending_skips = 0;
+                    action.actiondate = Some(ActionDate::utc(Utc::now().naive_utc()));
                 }
-                let error_msg = format!(
-                    "Failed to deserialize row in CSV file '{}': {}",
-                    config.file_name, e
+            }
+        }
+        if let Some(sampler) = &config.options.sampler
+            && !sampler.keeps(action.action_id())
+        {
+            state.sampled_out += 1;
+            continue;
+        }
+        state.processed += 1;
+        // Checked before NOTE_PREFIX/NOTE_SUFFIX would make every note non-empty
+        let note_empty = action.note.trim().is_empty();
+        apply_default_outcome(&mut action, config.options);
+        apply_note_affixes(&mut action, config.options);
+        if let Some(detail) = config.options.source_location {
+            action.source_location = Some(source_location(
+                detail,
+                config.file_name,
+                config.sheet_name,
+                row,
+            ));
+        }
+        let action_id = action.action_id().to_string();
+        match config.duplicates.check(&action_id, row) {
+            DuplicateVerdict::Keep => {}
+            DuplicateVerdict::Drop { kept_row } => {
+                state.record_skip(&action_id, SkipReason::Duplicate { kept_row });
+                warn!(
+                    "Skipped duplicate action ID: {} on row {} of {}, keeping row {}",
+                    action_id, row, source, kept_row
                 );
-                error!("{}", error_msg);
-                failed.push(("unknown".to_string(), error_msg));
                 continue;
             }
+            DuplicateVerdict::Reject(error_msg) => {
+                state.flush_pending_skips();
+                state.record_failure(
+                    action_id,
+                    ImportError::Validation(format!("{} in {}", error_msg, source)),
+                );
+                continue;
+            }
+        }
+        let already_exists = config.existing_ids.contains(&action_id);
+        // Existing actions are skipped regardless of whether their ticket is mapped
+        let mapped = if already_exists {
+            Ok(())
+        } else {
+            check_required_fields(&action, note_empty, config.options)
+                .map_err(|field| {
+                    format!(
+                        "Action ID: {} on row {} of {} has no {}, which REQUIRED_FIELDS lists",
+                        action_id, row, source, field
+                    )
+                })
+                .and_then(|()| resolve_ticket_id(&mut action, config.options))
+                .and_then(|()| apply_agent_map(&mut action, config.options))
+                .and_then(|()| apply_source_timezone(&mut action, config.options))
+                .and_then(|()| action.action_id.cfactionid().map(|_| ()))
         };
-        processed += 1;
-        let action_id = action.action_id().to_string();
         let ticket_id = action.ticket_id;
-        if config.only_parse {
-            if config.existing_ids.contains(&action_id) {
-                skipped += 1;
+        if config.options.only_parse && already_exists {
+            state.record_skip(&action_id, SkipReason::AlreadyExists);
+        } else if let Err(error_msg) = mapped {
+            state.flush_pending_skips();
+            state.record_failure(action_id, ImportError::Validation(error_msg));
+        } else if !config.options.only_parse
+            && (already_exists || config.missing_tickets.contains(&ticket_id))
+        {
+            let reason = if already_exists {
+                SkipReason::AlreadyExists
             } else {
-                imported += 1;
+                SkipReason::MissingTicket
+            };
+            state.record_skip(&action_id, reason);
+            state.pending_skips += 1;
+        } else if note_empty && config.options.empty_note == EmptyNote::Skip {
+            state.skipped_empty_note += 1;
+            (state.hooks.on_skipped)(&action_id, SkipReason::EmptyNote);
+            if !state.quiet {
+                info!(
+                    "Skipped action ID: {} on row {} of {}: empty note",
+                    action_id, row, source
+                );
             }
-        } else if config.existing_ids.contains(&action_id)
-            || config.missing_tickets.contains(&ticket_id)
-        {
-            skipped += 1;
-            pending_skips += 1;
+        } else if note_empty && config.options.empty_note == EmptyNote::Fail {
+            state.flush_pending_skips();
+            state.record_failure(
+                action_id.clone(),
+                ImportError::Validation(format!(
+                    "Action ID: {} on row {} of {} has an empty note",
+                    action_id, row, source
+                )),
+            );
+        } else if let Err(error_msg) = enforce_note_limit(&mut action, config.options) {
+            state.flush_pending_skips();
+            state.record_failure(action_id, ImportError::Validation(error_msg));
         } else {
-            batch.push(action.clone());
-            if batch.len() >= config.batch_size {
-                if pending_skips > 0 {
-                    info!(
-                        "Skipped {} entries (already exist)",
-                        format_number(pending_skips)
-                    );
-                    pending_skips = 0;
+            match check_payload_size(&action, config.action_client, config.options) {
+                Err(error_msg) => {
+                    state.flush_pending_skips();
+                    state.record_failure(action_id, ImportError::Validation(error_msg));
                 }
-                if let Some(client) = config.action_client {
-                    match client.post_action_objects(batch.clone()).await {
-                        Ok(_) => {
-                            let batch_count = batch.len();
-                            imported += batch_count;
-                            if config.batch_size == 1 {
-                                info!(
-                                    "Success: imported action ID: {} (ticket ID: {})",
-                                    batch[0].action_id(),
-                                    batch[0].ticket_id
-                                );
-                            } else {
-                                let action_ids: Vec<String> =
-                                    batch.iter().map(|a| a.action_id().to_string()).collect();
-                                let ticket_ids: Vec<String> = {
-                                    let mut ids: Vec<u32> =
-                                        batch.iter().map(|a| a.ticket_id).collect();
-                                    ids.sort_unstable();
-                                    ids.dedup();
-                                    ids.iter().map(|id| id.to_string()).collect()
-                                };
-                                info!(
-                                    "Success: imported batch of {} actions | action IDs: {} | ticket IDs: {}",
-                                    format_number(batch_count),
-                                    action_ids.join(", "),
-                                    ticket_ids.join(", ")
-                                );
+                Ok(payload_bytes) => {
+                    if config.options.only_parse {
+                        state.imported += 1;
+                    } else {
+                        if let (Some(max_bytes), Some(bytes)) =
+                            (config.options.max_batch_bytes, payload_bytes)
+                        {
+                            // Send what we have first if this action would push the body over the limit
+                            if !state.batch.is_empty() && state.batch_bytes + bytes + 1 > max_bytes
+                            {
+                                flush_batch(&mut state, &mut config).await;
                             }
-                            let batch_time = batch_start.elapsed().as_secs_f64();
-                            let per_row_time = batch_time / batch_count as f64;
-                            row_times.push(per_row_time);
-                            batch.clear();
-                            batch_start = Instant::now();
+                            state.batch_bytes += bytes + usize::from(!state.batch.is_empty());
                         }
-                        Err(e) => {
-                            let error_str = e.to_string();
-                            for action in &batch {
-                                let action_id = action.action_id().to_string();
-                                let ticket_id = action.ticket_id;
-                                let is_not_found = error_str.contains("not found")
-                                    || error_str.contains("Not Found")
-                                    || error_str.contains("404")
-                                    || error_str.contains("does not exist")
-                                    || error_str.contains("doesn't exist");
-                                if is_not_found {
-                                    config.missing_tickets.insert(ticket_id);
-                                    warn!(
-                                        "Ticket ID: {} not found - will skip future actions for this ticket",
-                                        ticket_id
-                                    );
-                                }
-                                let error_msg = format!(
-                                    "Failed to import action ID: {} (ticket ID: {}): {}",
-                                    action_id, ticket_id, e
-                                );
-                                error!("{}", error_msg);
-                                failed.push((action_id, error_msg.clone()));
-                            }
-                            batch.clear();
-                            batch_start = Instant::now();
+                        state.batch.push(action);
+                        if state.batch.len() >= config.options.batch_size {
+                            flush_batch(&mut state, &mut config).await;
                         }
                     }
-                } else {
-                    for action in &batch {
-                        let action_id = action.action_id().to_string();
-                        let ticket_id = action.ticket_id;
-                        let error_msg = format!(
-                            "Action client not available for action ID: {} (ticket ID: {})",
-                            action_id, ticket_id
-                        );
-                        error!("{}", error_msg);
-                        failed.push((action_id, error_msg.clone()));
+                    posted += 1;
+                    if let Some(left) = rows_left.as_mut() {
+                        *left = left.saturating_sub(1);
+                    }
+                    if let Some(estimator) = &config.options.estimator
+                        && !config.options.only_parse
+                    {
+                        estimator.record_posted();
                     }
-                    batch.clear();
-                    batch_start = Instant::now();
                 }
             }
         }
-        let should_log_progress = if only_parse {
-            last_progress_log.elapsed().as_secs() >= 5 || processed % 10_000 == 0
-        } else {
-            last_progress_log.elapsed().as_secs() >= 60 || processed % 100 == 0
-        };
-        if should_log_progress {
+        if last_progress_log.elapsed().as_secs() >= config.progress_every_secs
+            || state.processed.is_multiple_of(config.progress_every_rows)
+        {
             log_progress(ProgressParams {
                 sheet_number: config.sheet_number,
                 total_sheets: config.total_sheets,
                 file_name: config.file_name,
-                sheet_name: None,
-                processed,
+                sheet_name: config.sheet_name,
+                processed: state.processed,
                 total_rows,
-                imported,
-                skipped,
-                row_times: &row_times,
+                imported: state.imported,
+                skipped: state.skipped,
+                row_times: &state.row_times,
             });
             last_progress_log = Instant::now();
         }
-    }
-    if !batch.is_empty() {
-        if pending_skips > 0 {
-            info!(
-                "Skipped {} entries (already exist)",
-                format_number(pending_skips)
-            );
-            pending_skips = 0;
-        }
-        if let Some(client) = config.action_client {
-            match client.post_action_objects(batch.clone()).await {
-                Ok(_) => {
-                    let batch_count = batch.len();
-                    imported += batch_count;
-                    if config.batch_size == 1 {
-                        info!(
-                            "Success: imported action ID: {} (ticket ID: {})",
-                            batch[0].action_id(),
-                            batch[0].ticket_id
-                        );
-                    } else {
-                        let action_ids: Vec<String> =
-                            batch.iter().map(|a| a.action_id().to_string()).collect();
-                        let ticket_ids: Vec<String> = {
-                            let mut ids: Vec<u32> = batch.iter().map(|a| a.ticket_id).collect();
-                            ids.sort_unstable();
-                            ids.dedup();
-                            ids.iter().map(|id| id.to_string()).collect()
-                        };
-                        info!(
-                            "Success: imported batch of {} actions | action IDs: {} | ticket IDs: {}",
-                            format_number(batch_count),
-                            action_ids.join(", "),
-                            ticket_ids.join(", ")
-                        );
-                    }
-                    let batch_time = batch_start.elapsed().as_secs_f64();
-                    let per_row_time = batch_time / batch_count as f64;
-                    row_times.push(per_row_time);
-                }
-                Err(e) => {
-                    let error_str = e.to_string();
-                    for action in &batch {
-                        let action_id = action.action_id().to_string();
-                        let ticket_id = action.ticket_id;
-                        let is_not_found = error_str.contains("not found")
-                            || error_str.contains("Not Found")
-                            || error_str.contains("404")
-                            || error_str.contains("does not exist")
-                            || error_str.contains("doesn't exist");
-                        if is_not_found {
-                            config.missing_tickets.insert(ticket_id);
-                            warn!(
-                                "Ticket ID: {} not found - will skip future actions for this ticket",
-                                ticket_id
-                            );
-                        }
-                        let error_msg = format!(
-                            "Failed to import action ID: {} (ticket ID: {}): {}",
-                            action_id, ticket_id, e
-                        );
-                        error!("{}", error_msg);
-                        failed.push((action_id, error_msg.clone()));
-                    }
-                }
-            }
-        } else {
-            for action in &batch {
-                let action_id = action.action_id().to_string();
-                let ticket_id = action.ticket_id;
-                let error_msg = format!(
-                    "Action client not available for action ID: {} (ticket ID: {})",
-                    action_id, ticket_id
-                );
-                error!("{}", error_msg);
-                failed.push((action_id, error_msg.clone()));
+        if state.aborted.is_none()
+            && let Some(threshold) = config.options.failure_threshold
+        {
+            let (finished, failed) = state.ratio_counts();
+            if let Some(reason) = threshold.exceeded(finished, failed, &state.failed) {
+                state.abort(reason);
             }
         }
+        if state.check_aborted()
+            || rows_left == Some(0)
+            || deadline_passed(config.options)
+            || estimate_declined(config.options)
+            || shutdown_requested(config.options)
+        {
+            break;
+        }
     }
-    if pending_skips > 0 {
-        info!(
-            "Skipped {} entries (already exist)",
-            format_number(pending_skips)
-        );
+    if !state.batch.is_empty() && !state.check_aborted() {
+        flush_batch(&mut state, &mut config).await;
     }
-    let sheet_duration = sheet_start.elapsed().as_secs_f64();
+    if !state.window.is_empty()
+        && !state.check_aborted()
+        && let Some(client) = config.action_client
+    {
+        post_window(&mut state, &mut config, client).await;
+    }
+    // Rows queued when the run aborted were processed but never sent, so they count as not posted
+    if state.aborted.is_some() {
+        let batch = std::mem::take(&mut state.batch);
+        for batch in std::mem::take(&mut state.window).into_iter().chain([batch]) {
+            record_not_posted(&mut state, &batch);
+        }
+    }
+    state.report_to_run();
+    state.flush_pending_skips();
+    let duration = sheet_start.elapsed();
+    let sheet_duration = duration.as_secs_f64();
     config.sheet_times.push(sheet_duration);
-    let avg_sheet_time = config.sheet_times.iter().sum::<f64>() / config.sheet_times.len() as f64;
+    // Files running at the same time each keep their own `sheet_times`
+    let avg_sheet_time = match &config.options.run_guard {
+        Some(run_guard) => run_guard.add_sheet_time(sheet_duration),
+        None => config.sheet_times.iter().sum::<f64>() / config.sheet_times.len() as f64,
+    };
     info!(
-        "Completed sheet {} of {}: CSV file '{}' | {} processed, {} imported, {} skipped in {:.1}s | avg sheet time: {:.1}s",
+        "Completed sheet {} of {}: {} | {} processed, {} imported, {} skipped in {:.1}s | avg sheet time: {:.1}s",
         config.sheet_number,
         config.total_sheets,
-        config.file_name,
-        format_number(processed),
-        format_number(imported),
-        format_number(skipped),
+        source,
+        format_number(state.processed),
+        format_number(state.imported),
+        format_number(state.skipped),
         sheet_duration,
         avg_sheet_time
     );
-    Ok(ProcessingStats {
-        processed,
-        imported,
-        skipped,
-        failed,
-    })
+    if let Some(reason) = &state.aborted {
+        error!("Stopped processing {} early: {}", source, reason);
+    }
+    let row_cap_reached = state.aborted.is_none() && rows_left == Some(0);
+    if row_cap_reached {
+        warn!("Stopped processing {} early: row cap reached", source);
+    }
+    let deadline_reached =
+        state.aborted.is_none() && !row_cap_reached && deadline_passed(config.options);
+    if deadline_reached {
+        warn!(
+            "Stopped processing {} early: MAX_RUN_SECS time budget reached",
+            source
+        );
+    }
+    let estimate_declined = state.aborted.is_none() && estimate_declined(config.options);
+    if estimate_declined {
+        warn!(
+            "Stopped processing {} early: import stopped after the estimate",
+            source
+        );
+    }
+    let shutdown_requested = state.aborted.is_none() && shutdown_requested(config.options);
+    if shutdown_requested {
+        warn!("Stopped processing {} early: shutdown requested", source);
+    }
+    let stats = ProcessingStats {
+        processed: state.processed,
+        imported: state.imported,
+        skipped: state.skipped,
+        failed: state.failed,
+        row_times: state.row_times,
+        aborted: state.aborted,
+        posted,
+        row_cap_reached,
+        deadline_reached,
+        estimate_declined,
+        shutdown_requested,
+        sampled_out: state.sampled_out,
+        skipped_empty_note: state.skipped_empty_note,
+    };
+    FileResult {
+        file_name: config.file_name.to_string(),
+        sheet_name: config.sheet_name.map(str::to_string),
+        stats,
+        duration,
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn process_excel_file(
-    file_path: &Path,
-    existing_ids: &HashSet<String>,
-    action_client: Option<&ActionClient>,
-    sheet_times: &mut Vec<f64>,
-    sheet_number: usize,
-    total_sheets: usize,
-    only_parse: bool,
-    batch_size: usize,
-) -> anyhow::Result<ProcessingStats> {
-    let mut missing_tickets: HashSet<u32> = HashSet::new();
-    let config = ProcessConfig {
-        existing_ids,
-        action_client,
-        sheet_times,
-        file_name: file_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown file"),
-        sheet_number,
-        total_sheets,
-        only_parse,
-        missing_tickets: &mut missing_tickets,
-        batch_size,
-    };
-    let iter = <Reader as Excel>::excel_action_iter(file_path)?;
-    let total_rows = iter.total_rows();
-    let sheet_name = iter.sheet_name().to_string();
-    let mut processed = 0;
-    let mut imported = 0;
-    let mut skipped = 0;
-    let mut failed = Vec::new();
-    let sheet_start = Instant::now();
-    let mut row_times: Vec<f64> = Vec::new();
-    let mut last_progress_log = Instant::now();
-    let mut pending_skips = 0usize;
-    let mut batch: Vec<crate::domain::models::action_object::ActionObject> = Vec::new();
-    let mut batch_start = Instant::now();
-    info!(
-        "Processing sheet {} of {}: Excel file '{}', sheet '{}' ({} rows)",
-        config.sheet_number,
-        config.total_sheets,
-        config.file_name,
-        sheet_name,
-        format_number(total_rows)
-    );
-    for action_result in iter {
-        let action = match action_result {
-            Ok(a) => a,
-            Err(e) => {
-                if pending_skips > 0 {
-                    info!(
-                        "Skipped {} entries (already exist)",
-                        format_number(pending_skips)
-                    );
-                    pending_skips = 0;
-                }
-                let error_msg = format!(
-                    "Failed to deserialize row in Excel file '{}', sheet '{}': {}",
-                    config.file_name, sheet_name, e
-                );
-                error!("{}", error_msg);
-                failed.push(("unknown".to_string(), error_msg));
+fn estimate_declined(options: &ProcessOptions) -> bool {
+    options
+        .estimator
+        .as_ref()
+        .is_some_and(|estimator| estimator.declined())
+}
+
+fn shutdown_requested(options: &ProcessOptions) -> bool {
+    options
+        .shutdown
+        .as_ref()
+        .is_some_and(|shutdown| shutdown.requested())
+}
+
+fn deadline_passed(options: &ProcessOptions) -> bool {
+    options
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+async fn flush_batch(state: &mut SheetState, config: &mut ProcessConfig<'_>) {
+    state.flush_pending_skips();
+    let batch = std::mem::take(&mut state.batch);
+    state.batch_bytes = 2;
+    let fail_fast = config.options.fail_fast;
+    if let Some(client) = config.action_client
+        && config.options.ordered_concurrency > 1
+    {
+        state.window.push(batch);
+        if state.window.len() >= config.options.ordered_concurrency {
+            post_window(state, config, client).await;
+        }
+    } else if let Some(client) = config.action_client {
+        // Actions routed to different endpoints can't share a request
+        for (endpoint, group) in client.group_by_endpoint(batch) {
+            if state.check_aborted() {
+                record_not_posted(state, &group);
                 continue;
             }
-        };
-        processed += 1;
-        let action_id = action.action_id().to_string();
-        let ticket_id = action.ticket_id;
-        if config.only_parse {
-            if config.existing_ids.contains(&action_id) {
-                skipped += 1;
-            } else {
-                imported += 1;
+            post_group(state, config, client, &endpoint, group).await;
+        }
+    } else {
+        for action in &batch {
+            let action_id = action.action_id().to_string();
+            let ticket_id = action.ticket_id;
+            let error_msg = format!(
+                "Action client not available for action ID: {} (ticket ID: {})",
+                action_id, ticket_id
+            );
+            state.record_import_failure(
+                action_id,
+                error_msg.clone(),
+                ImportError::Network(error_msg),
+                fail_fast,
+            );
+        }
+    }
+    state.batch_start = Instant::now();
+}
+
+/// Splits the queued batches into lanes: batches that share a ticket, directly or through
+/// other batches, go in one lane in source order. Lanes share no ticket, so they can be
+/// posted at the same time without changing any ticket's timeline.
+fn ticket_lanes(window: &[Vec<ActionObject>]) -> Vec<Vec<usize>> {
+    let mut lanes: Vec<(HashSet<u32>, Vec<usize>)> = Vec::new();
+    for (idx, batch) in window.iter().enumerate() {
+        let mut lane: (HashSet<u32>, Vec<usize>) =
+            (batch.iter().map(|a| a.ticket_id).collect(), vec![idx]);
+        let (sharing, others): (Vec<_>, Vec<_>) = lanes
+            .into_iter()
+            .partition(|(tickets, _)| !tickets.is_disjoint(&lane.0));
+        for (tickets, batches) in sharing {
+            lane.0.extend(tickets);
+            lane.1.extend(batches);
+        }
+        lane.1.sort_unstable();
+        lanes = others;
+        lanes.push(lane);
+    }
+    lanes.into_iter().map(|(_, batches)| batches).collect()
+}
+
+/// How one endpoint group of a queued batch came out of [`post_lane`].
+enum LanePost {
+    Posted(anyhow::Result<()>, f64),
+    /// Held back because an earlier post in the window found its ticket missing.
+    MissingTicket,
+    /// Held back because an earlier post in the window failed under fail-fast or pushed
+    /// the run over `ABORT_FAILURE_RATIO`, or another file aborted the run.
+    NotPosted,
+}
+
+/// What the lanes of a window have learned from their posts so far, checked by every lane
+/// before each post. The lanes run on one task, so cells are enough to share it.
+struct LaneGuard<'a> {
+    options: &'a ProcessOptions,
+    /// This file's finished and failed rows, including the window's posts so far.
+    finished: Cell<usize>,
+    failed: Cell<usize>,
+    /// This file's rows already counted in `options.run_guard` when the window started.
+    reported: (usize, usize),
+    stopped: Cell<bool>,
+    missing_tickets: RefCell<HashSet<u32>>,
+}
+
+impl LaneGuard<'_> {
+    /// Whether an earlier post in the window, or another file, has stopped the run.
+    fn stopped(&self) -> bool {
+        self.stopped.get()
+            || self
+                .options
+                .run_guard
+                .as_ref()
+                .is_some_and(|guard| guard.aborted().is_some())
+    }
+
+    /// This file's finished and failed rows, or with a [`RunGuard`] every file's.
+    fn ratio_counts(&self) -> (usize, usize) {
+        let (finished, failed) = (self.finished.get(), self.failed.get());
+        match &self.options.run_guard {
+            Some(guard) => {
+                let (run_finished, run_failed) = guard.totals();
+                (
+                    run_finished - self.reported.0 + finished,
+                    run_failed - self.reported.1 + failed,
+                )
             }
-        } else if config.existing_ids.contains(&action_id)
-            || config.missing_tickets.contains(&ticket_id)
-        {
-            skipped += 1;
-            pending_skips += 1;
-        } else {
-            batch.push(action.clone());
-            if batch.len() >= config.batch_size {
-                if pending_skips > 0 {
-                    info!(
-                        "Skipped {} entries (already exist)",
-                        format_number(pending_skips)
-                    );
-                    pending_skips = 0;
-                }
-                if let Some(client) = config.action_client {
-                    match client.post_action_objects(batch.clone()).await {
-                        Ok(_) => {
-                            let batch_count = batch.len();
-                            imported += batch_count;
-                            if config.batch_size == 1 {
-                                info!(
-                                    "Success: imported action ID: {} (ticket ID: {})",
-                                    batch[0].action_id(),
-                                    batch[0].ticket_id
-                                );
-                            } else {
-                                let action_ids: Vec<String> =
-                                    batch.iter().map(|a| a.action_id().to_string()).collect();
-                                let ticket_ids: Vec<String> = {
-                                    let mut ids: Vec<u32> =
-                                        batch.iter().map(|a| a.ticket_id).collect();
-                                    ids.sort_unstable();
-                                    ids.dedup();
-                                    ids.iter().map(|id| id.to_string()).collect()
-                                };
-                                info!(
-                                    "Success: imported batch of {} actions | action IDs: {} | ticket IDs: {}",
-                                    format_number(batch_count),
-                                    action_ids.join(", "),
-                                    ticket_ids.join(", ")
-                                );
-                            }
-                            let batch_time = batch_start.elapsed().as_secs_f64();
-                            let per_row_time = batch_time / batch_count as f64;
-                            row_times.push(per_row_time);
-                            batch.clear();
-                            batch_start = Instant::now();
-                        }
-                        Err(e) => {
-                            let error_str = e.to_string();
-                            for action in &batch {
-                                let action_id = action.action_id().to_string();
-                                let ticket_id = action.ticket_id;
-                                let is_not_found = error_str.contains("not found")
-                                    || error_str.contains("Not Found")
-                                    || error_str.contains("404")
-                                    || error_str.contains("does not exist")
-                                    || error_str.contains("doesn't exist");
-                                if is_not_found {
-                                    config.missing_tickets.insert(ticket_id);
-                                    warn!(
-                                        "Ticket ID: {} not found - will skip future actions for this ticket",
-                                        ticket_id
-                                    );
-                                }
-                                let error_msg = format!(
-                                    "Failed to import action ID: {} (ticket ID: {}): {}",
-                                    action_id, ticket_id, e
-                                );
-                                error!("{}", error_msg);
-                                failed.push((action_id, error_msg.clone()));
-                            }
-                            batch.clear();
-                            batch_start = Instant::now();
-                        }
-                    }
-                } else {
-                    for action in &batch {
-                        let action_id = action.action_id().to_string();
-                        let ticket_id = action.ticket_id;
-                        let error_msg = format!(
-                            "Action client not available for action ID: {} (ticket ID: {})",
-                            action_id, ticket_id
-                        );
-                        error!("{}", error_msg);
-                        failed.push((action_id, error_msg.clone()));
-                    }
-                    batch.clear();
-                    batch_start = Instant::now();
-                }
+            None => (finished, failed),
+        }
+    }
+
+    fn record(&self, group: &[ActionObject], result: &anyhow::Result<()>) {
+        let rejected: Vec<(&ActionObject, String)> = match result {
+            Ok(()) => Vec::new(),
+            Err(e) => match e.downcast_ref::<PartialBatch>() {
+                Some(partial) => partial
+                    .rejected
+                    .iter()
+                    .filter_map(|(idx, error)| group.get(*idx).map(|a| (a, error.to_string())))
+                    .collect(),
+                None => group.iter().map(|a| (a, e.to_string())).collect(),
+            },
+        };
+        for (action, error) in &rejected {
+            if is_not_found(error) {
+                self.missing_tickets.borrow_mut().insert(action.ticket_id);
             }
         }
-        if last_progress_log.elapsed().as_secs() >= 60 || processed % 300 == 0 {
-            log_progress(ProgressParams {
-                sheet_number: config.sheet_number,
-                total_sheets: config.total_sheets,
-                file_name: config.file_name,
-                sheet_name: Some(&sheet_name),
-                processed,
-                total_rows: Some(total_rows),
-                imported,
-                skipped,
-                row_times: &row_times,
-            });
-            last_progress_log = Instant::now();
+        self.finished.set(self.finished.get() + group.len());
+        self.failed.set(self.failed.get() + rejected.len());
+        let over_ratio = self.options.failure_threshold.is_some_and(|threshold| {
+            let (finished, failed) = self.ratio_counts();
+            threshold.passed(finished, failed)
+        });
+        if (self.options.fail_fast && !rejected.is_empty()) || over_ratio {
+            self.stopped.set(true);
         }
     }
-    if !batch.is_empty() {
-        if pending_skips > 0 {
-            info!(
-                "Skipped {} entries (already exist)",
-                format_number(pending_skips)
-            );
-            pending_skips = 0;
-        }
-        if let Some(client) = config.action_client {
-            match client.post_action_objects(batch.clone()).await {
-                Ok(_) => {
-                    let batch_count = batch.len();
-                    imported += batch_count;
-                    if config.batch_size == 1 {
-                        info!(
-                            "Success: imported action ID: {} (ticket ID: {})",
-                            batch[0].action_id(),
-                            batch[0].ticket_id
-                        );
-                    } else {
-                        let action_ids: Vec<String> =
-                            batch.iter().map(|a| a.action_id().to_string()).collect();
-                        let ticket_ids: Vec<String> = {
-                            let mut ids: Vec<u32> = batch.iter().map(|a| a.ticket_id).collect();
-                            ids.sort_unstable();
-                            ids.dedup();
-                            ids.iter().map(|id| id.to_string()).collect()
-                        };
-                        info!(
-                            "Success: imported batch of {} actions | action IDs: {} | ticket IDs: {}",
-                            format_number(batch_count),
-                            action_ids.join(", "),
-                            ticket_ids.join(", ")
-                        );
-                    }
-                    let batch_time = batch_start.elapsed().as_secs_f64();
-                    let per_row_time = batch_time / batch_count as f64;
-                    row_times.push(per_row_time);
-                }
-                Err(e) => {
-                    let error_str = e.to_string();
-                    for action in &batch {
-                        let action_id = action.action_id().to_string();
-                        let ticket_id = action.ticket_id;
-                        let is_not_found = error_str.contains("not found")
-                            || error_str.contains("Not Found")
-                            || error_str.contains("404")
-                            || error_str.contains("does not exist")
-                            || error_str.contains("doesn't exist");
-                        if is_not_found {
-                            config.missing_tickets.insert(ticket_id);
-                            warn!(
-                                "Ticket ID: {} not found - will skip future actions for this ticket",
-                                ticket_id
-                            );
-                        }
-                        let error_msg = format!(
-                            "Failed to import action ID: {} (ticket ID: {}): {}",
-                            action_id, ticket_id, e
-                        );
-                        error!("{}", error_msg);
-                        failed.push((action_id, error_msg.clone()));
-                    }
+}
+
+/// Posts one lane's endpoint groups in order, holding back any that a post already made in
+/// the window has ruled out.
+async fn post_lane<F, Fut>(
+    groups: Vec<(usize, Url, Vec<ActionObject>)>,
+    guard: &LaneGuard<'_>,
+    post: F,
+) -> Vec<(usize, Vec<ActionObject>, LanePost)>
+where
+    F: Fn(Url, Vec<ActionObject>) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let mut sent = Vec::new();
+    for (idx, endpoint, group) in groups {
+        if guard.stopped() {
+            sent.push((idx, group, LanePost::NotPosted));
+            continue;
+        }
+        let (missing, group): (Vec<_>, Vec<_>) = group
+            .into_iter()
+            .partition(|action| guard.missing_tickets.borrow().contains(&action.ticket_id));
+        if !missing.is_empty() {
+            sent.push((idx, missing, LanePost::MissingTicket));
+        }
+        if group.is_empty() {
+            continue;
+        }
+        let start = Instant::now();
+        let result = post(endpoint, group.clone()).await;
+        guard.record(&group, &result);
+        sent.push((
+            idx,
+            group,
+            LanePost::Posted(result, start.elapsed().as_secs_f64()),
+        ));
+    }
+    sent
+}
+
+/// Posts the queued batches, each lane of [`ticket_lanes`] alongside the others, then
+/// records the results in source order, as if the batches had been posted one by one.
+async fn post_window(
+    state: &mut SheetState,
+    config: &mut ProcessConfig<'_>,
+    client: &ActionClient,
+) {
+    let window = std::mem::take(&mut state.window);
+    let lanes = ticket_lanes(&window);
+    let mut window: Vec<Option<Vec<ActionObject>>> = window.into_iter().map(Some).collect();
+    let guard = LaneGuard {
+        options: config.options,
+        finished: Cell::new(state.finished()),
+        failed: Cell::new(state.failed.len()),
+        reported: state.reported,
+        stopped: Cell::new(state.check_aborted()),
+        missing_tickets: RefCell::new(config.missing_tickets.clone()),
+    };
+    let lanes = lanes.into_iter().map(|lane| {
+        let groups = lane
+            .into_iter()
+            .filter_map(|idx| window[idx].take().map(|batch| (idx, batch)))
+            .flat_map(|(idx, batch)| {
+                client
+                    .group_by_endpoint(batch)
+                    .into_iter()
+                    .map(move |(endpoint, group)| (idx, endpoint, group))
+            })
+            .collect();
+        post_lane(groups, &guard, |endpoint, group| async move {
+            client.post_action_objects_to(&endpoint, group).await
+        })
+    });
+    let mut sent: Vec<_> = futures::future::join_all(lanes)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    // Stable, so a batch's endpoint groups keep their order
+    sent.sort_by_key(|(idx, ..)| *idx);
+    for (_, group, post) in sent {
+        match post {
+            LanePost::Posted(result, secs) => record_post(state, config, group, result, secs),
+            LanePost::MissingTicket => {
+                for action in &group {
+                    state.record_skip(action.action_id(), SkipReason::MissingTicket);
+                    state.pending_skips += 1;
                 }
             }
-        } else {
-            for action in &batch {
-                let action_id = action.action_id().to_string();
-                let ticket_id = action.ticket_id;
-                let error_msg = format!(
-                    "Action client not available for action ID: {} (ticket ID: {})",
-                    action_id, ticket_id
-                );
-                error!("{}", error_msg);
-                failed.push((action_id, error_msg.clone()));
+            LanePost::NotPosted => record_not_posted(state, &group),
+        }
+    }
+    state.batch_start = Instant::now();
+}
+
+/// Records actions held back once the file was stopped, so every processed row is counted
+/// as imported, skipped, or failed.
+fn record_not_posted(state: &mut SheetState, batch: &[ActionObject]) {
+    for action in batch {
+        state.record_failure(
+            action.action_id().to_string(),
+            ImportError::NotPosted(format!(
+                "Action ID: {} (ticket ID: {}) not posted: run aborted",
+                action.action_id(),
+                action.ticket_id
+            )),
+        );
+    }
+}
+
+async fn post_group(
+    state: &mut SheetState,
+    config: &mut ProcessConfig<'_>,
+    client: &ActionClient,
+    endpoint: &Url,
+    batch: Vec<ActionObject>,
+) {
+    let result = client.post_action_objects_to(endpoint, batch.clone()).await;
+    let batch_secs = state.batch_start.elapsed().as_secs_f64();
+    record_post(state, config, batch, result, batch_secs);
+    state.batch_start = Instant::now();
+}
+
+/// Counts, logs, and records a posted batch; `batch_secs` is the time it took.
+///
+/// When Halo accepted part of the batch ([`PartialBatch`]), the accepted actions are
+/// recorded as imported and only the rejected ones as failed, so a rerun doesn't post the
+/// accepted ones again.
+fn record_post(
+    state: &mut SheetState,
+    config: &mut ProcessConfig<'_>,
+    batch: Vec<ActionObject>,
+    result: anyhow::Result<()>,
+    batch_secs: f64,
+) {
+    let e = match result {
+        Ok(()) => {
+            record_imported(state, config, batch, batch_secs);
+            return;
+        }
+        Err(e) => e,
+    };
+    if let Some(partial) = e.downcast_ref::<PartialBatch>() {
+        let posted = batch.len();
+        let mut rejected = Vec::new();
+        let mut imported = Vec::new();
+        for (idx, action) in batch.into_iter().enumerate() {
+            match partial
+                .rejected
+                .iter()
+                .find(|(rejected_idx, _)| *rejected_idx == idx)
+            {
+                Some((_, error)) => rejected.push((action, error.clone())),
+                None => imported.push(action),
             }
         }
+        let imported_secs = batch_secs * imported.len() as f64 / posted.max(1) as f64;
+        record_imported(state, config, imported, imported_secs);
+        for (action, error) in rejected {
+            record_rejected(state, config, &action, &error.to_string(), error);
+        }
+        return;
+    }
+    let error_str = e.to_string();
+    let import_error = ImportError::from_client_error(&e);
+    for action in &batch {
+        record_rejected(state, config, action, &error_str, import_error.clone());
+    }
+}
+
+/// Counts, logs, and records actions Halo accepted.
+fn record_imported(
+    state: &mut SheetState,
+    config: &mut ProcessConfig<'_>,
+    batch: Vec<ActionObject>,
+    batch_secs: f64,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let batch_count = batch.len();
+    state.imported += batch_count;
+    for action in &batch {
+        (state.hooks.on_imported)(action.action_id(), action.ticket_id);
+    }
+    if let Some(manifest) = &config.options.manifest
+        && let Err(e) = manifest.record(&batch, config.file_name)
+    {
+        warn!(
+            "Failed to write {} imported action(s) to manifest {}: {:#}",
+            format_number(batch_count),
+            manifest.path().display(),
+            e
+        );
     }
-    if pending_skips > 0 {
+    let log_rows = !config.options.quiet;
+    if log_rows && config.options.batch_size == 1 {
         info!(
-            "Skipped {} entries (already exist)",
-            format_number(pending_skips)
+            "Success: imported action ID: {} (ticket ID: {})",
+            batch[0].action_id(),
+            batch[0].ticket_id
+        );
+    } else if log_rows {
+        let action_ids: Vec<String> = batch.iter().map(|a| a.action_id().to_string()).collect();
+        let ticket_ids: Vec<String> = {
+            let mut ids: Vec<u32> = batch.iter().map(|a| a.ticket_id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids.iter().map(|id| id.to_string()).collect()
+        };
+        info!(
+            "Success: imported batch of {} actions | action IDs: {} | ticket IDs: {}",
+            format_number(batch_count),
+            action_ids.join(", "),
+            ticket_ids.join(", ")
         );
     }
-    let sheet_duration = sheet_start.elapsed().as_secs_f64();
-    config.sheet_times.push(sheet_duration);
-    let avg_sheet_time = config.sheet_times.iter().sum::<f64>() / config.sheet_times.len() as f64;
-    info!(
-        "Completed sheet {} of {}: Excel file '{}', sheet '{}' | {} processed, {} imported, {} skipped in {:.1}s | avg sheet time: {:.1}s",
-        config.sheet_number,
-        config.total_sheets,
-        config.file_name,
-        sheet_name,
-        format_number(processed),
-        format_number(imported),
-        format_number(skipped),
-        sheet_duration,
-        avg_sheet_time
+    let per_row_time = batch_secs / batch_count as f64;
+    state.row_times.push(per_row_time);
+}
+
+/// Records an action Halo rejected, remembering its ticket as missing when the error says
+/// the ticket wasn't found.
+fn record_rejected(
+    state: &mut SheetState,
+    config: &mut ProcessConfig<'_>,
+    action: &ActionObject,
+    error_str: &str,
+    import_error: ImportError,
+) {
+    let action_id = action.action_id().to_string();
+    let ticket_id = action.ticket_id;
+    if is_not_found(error_str) {
+        config.missing_tickets.insert(ticket_id);
+        warn!(
+            "Ticket ID: {} not found - will skip future actions for this ticket",
+            ticket_id
+        );
+    }
+    let error_msg = format!(
+        "Failed to import action ID: {} (ticket ID: {}): {}",
+        action_id, ticket_id, error_str
     );
-    Ok(ProcessingStats {
-        processed,
-        imported,
-        skipped,
-        failed,
-    })
+    state.record_import_failure(action_id, error_msg, import_error, config.options.fail_fast);
+}
+
+/// Whether a failed post's error says the ticket doesn't exist.
+fn is_not_found(error_str: &str) -> bool {
+    error_str.contains("not found")
+        || error_str.contains("Not Found")
+        || error_str.contains("404")
+        || error_str.contains("does not exist")
+        || error_str.contains("doesn't exist")
 }
 
 struct ProgressParams<'a> {
@@ -625,7 +1710,8 @@ struct ProgressParams<'a> {
     row_times: &'a [f64],
 }
 
-fn format_number(n: usize) -> String {
+/// Formats a count with thousands separators, e.g. `1,234,567`.
+pub fn format_number(n: usize) -> String {
     let s = n.to_string();
     let mut result = String::new();
     let chars: Vec<char> = s.chars().collect();
@@ -638,7 +1724,7 @@ fn format_number(n: usize) -> String {
     result
 }
 
-fn format_duration(seconds: f64) -> String {
+pub(crate) fn format_duration(seconds: f64) -> String {
     let total_seconds = seconds as u64;
     let days = total_seconds / 86400;
     let hours = (total_seconds % 86400) / 3600;
@@ -720,3 +1806,600 @@ fn log_progress(params: ProgressParams<'_>) {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::action_object::{ActionDate, ActionId};
+    use crate::test_support::TempPath;
+
+    fn action_with_note(note: &str) -> ActionObject {
+        ActionObject::new(1, None, None, note, "tester", ActionId::new("1"))
+    }
+
+    /// A CSV fixture in the temp directory, deleted when dropped so a failing assertion
+    /// doesn't leave it behind.
+    /// Runs `process_csv_file` on `contents` as `file_name`, without a client.
+    async fn process_test_csv(
+        contents: &str,
+        file_name: &str,
+        existing_ids: &HashSet<String>,
+        options: &ProcessOptions,
+    ) -> anyhow::Result<FileResult> {
+        let csv = TempPath::file("actions.csv", contents);
+        process_csv_file(
+            &csv,
+            existing_ids,
+            None,
+            &mut Vec::new(),
+            file_name,
+            1,
+            1,
+            options,
+        )
+        .await
+    }
+
+    #[test]
+    fn prepare_action_applies_the_row_steps() {
+        let options = ProcessOptions {
+            max_note_len: Some(24),
+            note_prefix: "[import] ".to_string(),
+            note_suffix: " (end)".to_string(),
+            ..ProcessOptions::default()
+        };
+        let mut action = action_with_note("called the customer back");
+        action.outcome = " ".to_string();
+        prepare_action(&mut action, &options).unwrap();
+        assert_eq!(action.note, "[import] cal [truncated]");
+        assert_eq!(action.outcome, DEFAULT_OUTCOME);
+
+        let mut unmapped = action_with_note("note");
+        unmapped.source_ticket_id = "INC-42".to_string();
+        let e = prepare_action(&mut unmapped, &options).unwrap_err();
+        assert!(e.contains("is not a numeric Halo ticket ID"), "{}", e);
+    }
+
+    #[test]
+    fn note_limit_truncates_with_marker() {
+        let options = ProcessOptions {
+            max_note_len: Some(20),
+            ..ProcessOptions::default()
+        };
+        let mut action = action_with_note("abcdefghijklmnopqrstuvwxyz");
+        assert!(enforce_note_limit(&mut action, &options).is_ok());
+        assert_eq!(action.note, "abcdefgh [truncated]");
+        assert_eq!(action.note.chars().count(), 20);
+    }
+
+    #[test]
+    fn note_limit_fail_mode_rejects_row() {
+        let options = ProcessOptions {
+            max_note_len: Some(5),
+            note_length_mode: NoteLengthMode::Fail,
+            ..ProcessOptions::default()
+        };
+        let mut action = action_with_note("too long");
+        assert!(enforce_note_limit(&mut action, &options).is_err());
+        let mut short = action_with_note("ok");
+        assert!(enforce_note_limit(&mut short, &options).is_ok());
+    }
+
+    #[test]
+    fn payload_size_rejects_action_too_big_to_send_alone() {
+        let options = ProcessOptions {
+            max_batch_bytes: Some(400),
+            ..ProcessOptions::default()
+        };
+        let small = action_with_note("short");
+        let bytes = check_payload_size(&small, None, &options).unwrap().unwrap();
+        assert!(bytes + 2 <= 400);
+        let big = action_with_note(&"x".repeat(400));
+        assert!(check_payload_size(&big, None, &options).is_err());
+        let unlimited = ProcessOptions::default();
+        assert_eq!(check_payload_size(&big, None, &unlimited), Ok(None));
+    }
+
+    #[test]
+    fn batches_sharing_a_ticket_share_a_lane() {
+        let batch = |tickets: &[u32]| {
+            tickets
+                .iter()
+                .map(|&ticket| {
+                    ActionObject::new(ticket, None, None, "n", "tester", ActionId::new("1"))
+                })
+                .collect::<Vec<_>>()
+        };
+        // The batch for tickets 1 and 2 joins their lanes into one
+        let window = [
+            batch(&[1]),
+            batch(&[2]),
+            batch(&[4]),
+            batch(&[1, 2]),
+            batch(&[5]),
+            batch(&[2]),
+        ];
+        assert_eq!(
+            ticket_lanes(&window),
+            vec![vec![2], vec![4], vec![0, 1, 3, 5]]
+        );
+    }
+
+    #[tokio::test]
+    async fn lanes_hold_back_posts_after_a_missing_ticket_or_fail_fast() {
+        let endpoint = Url::parse("https://halo.example/api/actions").unwrap();
+        let batch = |ticket: u32, id: &str| {
+            vec![ActionObject::new(
+                ticket,
+                None,
+                None,
+                "n",
+                "tester",
+                ActionId::new(id),
+            )]
+        };
+        let guard = |options| LaneGuard {
+            options,
+            finished: Cell::new(0),
+            failed: Cell::new(0),
+            reported: (0, 0),
+            stopped: Cell::new(false),
+            missing_tickets: RefCell::new(HashSet::new()),
+        };
+        let posts = Cell::new(0);
+        let post = |status: &'static str| {
+            let posts = &posts;
+            move |_: Url, _: Vec<ActionObject>| async move {
+                posts.set(posts.get() + 1);
+                Err(anyhow::anyhow!(
+                    "Action object POST failed for batch: status {}",
+                    status
+                ))
+            }
+        };
+        let outcomes = |sent: Vec<(usize, Vec<ActionObject>, LanePost)>| {
+            sent.into_iter()
+                .map(|(idx, _, post)| match post {
+                    LanePost::Posted(..) => (idx, "posted"),
+                    LanePost::MissingTicket => (idx, "missing ticket"),
+                    LanePost::NotPosted => (idx, "not posted"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // A 404 for the ticket holds back its second batch
+        let options = ProcessOptions::default();
+        let missing = guard(&options);
+        let groups = vec![
+            (0, endpoint.clone(), batch(7, "1001")),
+            (1, endpoint.clone(), batch(7, "1002")),
+        ];
+        let sent = post_lane(groups, &missing, post("404 Not Found")).await;
+        assert_eq!(posts.get(), 1);
+        assert_eq!(outcomes(sent), vec![(0, "posted"), (1, "missing ticket")]);
+
+        // Under fail-fast any failure holds back the rest, whatever the ticket
+        let options = ProcessOptions {
+            fail_fast: true,
+            ..ProcessOptions::default()
+        };
+        let fail_fast = guard(&options);
+        let groups = vec![
+            (0, endpoint.clone(), batch(1, "1003")),
+            (1, endpoint, batch(2, "1004")),
+        ];
+        let sent = post_lane(groups, &fail_fast, post("500")).await;
+        assert_eq!(posts.get(), 2);
+        assert_eq!(outcomes(sent), vec![(0, "posted"), (1, "not posted")]);
+    }
+
+    #[test]
+    fn failure_threshold_waits_for_sample_then_aborts_over_ratio() {
+        let threshold = FailureThreshold {
+            ratio: 0.5,
+            min_sample: 10,
+            finished_before: 6,
+            failed_before: 3,
+        };
+        let failures = |n: usize| {
+            (0..n)
+                .map(|i| FailedAction {
+                    action_id: i.to_string(),
+                    error: ImportError::Network("connection refused".to_string()),
+                })
+                .collect::<FailedActions>()
+        };
+        // 3 + 3 of 9 failed: not enough rows yet
+        assert!(threshold.exceeded(3, 3, &failures(3)).is_none());
+        // 5 of 10 failed: at the ratio, not over it
+        assert!(threshold.exceeded(4, 2, &failures(2)).is_none());
+        let reason = threshold.exceeded(4, 3, &failures(3)).unwrap();
+        assert!(
+            reason.contains("6 of 10 finished rows failed (60.0%)"),
+            "{}",
+            reason
+        );
+        assert!(reason.contains("network errors (3)"), "{}", reason);
+    }
+
+    #[test]
+    fn sort_by_date_orders_rows_and_places_undated() {
+        let dated = |id: &str, date: &str| {
+            let mut action = action_with_note("n");
+            action.actiondate = ActionDate::parse(date).unwrap();
+            action.action_id = ActionId::new(id);
+            action
+        };
+        let rows = || {
+            vec![
+                (1, Ok(dated("late", "2024-03-01 09:00:00"))),
+                (2, Ok(dated("undated", ""))),
+                (3, Err(anyhow::anyhow!("bad row"))),
+                (4, Ok(dated("early", "2024-01-01 09:00:00"))),
+                (5, Ok(dated("zoned", "2024-02-01T09:00:00Z"))),
+            ]
+            .into_iter()
+        };
+        let order = |undated| {
+            sort_rows_by_date(rows(), undated, &ProcessOptions::default())
+                .into_iter()
+                .map(|(row, _)| row)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(UndatedRows::Last), vec![3, 4, 5, 1, 2]);
+        assert_eq!(order(UndatedRows::First), vec![3, 2, 4, 5, 1]);
+    }
+
+    #[test]
+    fn blank_outcome_uses_default_from_either_reader() {
+        let options = ProcessOptions {
+            column_mapping: ColumnMapping {
+                default_outcome: Some("Migrated".to_string()),
+                ..ColumnMapping::default()
+            },
+            ..ProcessOptions::default()
+        };
+        let outcomes = |rows: Vec<anyhow::Result<ActionObject>>| {
+            rows.into_iter()
+                .map(|row| {
+                    let mut action = row.unwrap();
+                    apply_default_outcome(&mut action, &options);
+                    action.outcome
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let no_column = "ticket_id,note,actionwho,cfactionid,actiondate\n1,n,tester,1001,\n";
+        let csv = "ticket_id,note,actionwho,cfactionid,actiondate,outcome\n\
+                   1,n,tester,1001,,\n\
+                   1,n,tester,1002,,   \n\
+                   1,n,tester,1003,,Email Sent\n";
+        for (data, expected) in [
+            (no_column, vec!["Migrated"]),
+            (csv, vec!["Migrated", "Migrated", "Email Sent"]),
+        ] {
+            let iter = <Reader as Csv>::csv_action_iter_from_reader(
+                data.as_bytes(),
+                "outcome.csv",
+                &options.column_mapping,
+            );
+            assert_eq!(outcomes(iter.collect()), expected);
+        }
+
+        for (bytes, expected) in [
+            (
+                include_bytes!("../../../../fixtures/sample_actions.xlsx").to_vec(),
+                vec!["Migrated", "Migrated"],
+            ),
+            (
+                include_bytes!("../../../../fixtures/outcome_actions.xlsx").to_vec(),
+                vec!["Migrated", "Migrated", "Email Sent"],
+            ),
+        ] {
+            let iter = <Reader as Excel>::excel_action_iter_from_bytes(
+                bytes,
+                "outcome",
+                &options.column_mapping,
+            )
+            .unwrap();
+            assert_eq!(outcomes(iter.collect()), expected);
+        }
+
+        let mut unset = action_with_note("n");
+        unset.outcome = " ".to_string();
+        apply_default_outcome(&mut unset, &ProcessOptions::default());
+        assert_eq!(unset.outcome, DEFAULT_OUTCOME);
+    }
+
+    #[test]
+    fn note_affixes_wrap_the_note() {
+        let options = ProcessOptions {
+            note_prefix: "[Imported 2024-01-15] ".to_string(),
+            note_suffix: "\n-- migrated".to_string(),
+            ..ProcessOptions::default()
+        };
+        let mut action = action_with_note("Called customer");
+        apply_note_affixes(&mut action, &options);
+        assert_eq!(
+            action.note,
+            "[Imported 2024-01-15] Called customer\n-- migrated"
+        );
+
+        let mut untouched = action_with_note("Called customer");
+        apply_note_affixes(&mut untouched, &ProcessOptions::default());
+        assert_eq!(untouched.note, "Called customer");
+    }
+
+    #[tokio::test]
+    async fn hooks_see_skipped_and_failed_rows() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,new,tester,1001\n\
+                   1,2024-01-01 10:00:00,old,tester,1002\n\
+                   ,,bad,tester,1003\n\
+                   INC-7,2024-01-01 10:00:00,alpha,tester,1004\n";
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (skipped, failed) = (events.clone(), events.clone());
+        let options = ProcessOptions {
+            only_parse: true,
+            hooks: ProcessHooks::default()
+                .on_skipped(move |id, reason| {
+                    skipped.lock().unwrap().push(format!("{} {:?}", id, reason))
+                })
+                .on_failed(move |id, error| {
+                    failed
+                        .lock()
+                        .unwrap()
+                        .push(format!("{} {}", id, error.category()))
+                }),
+            ..ProcessOptions::default()
+        };
+        let existing_ids = HashSet::from(["1002".to_string()]);
+        let stats = process_test_csv(csv, "hooks.csv", &existing_ids, &options)
+            .await
+            .unwrap()
+            .stats;
+
+        assert_eq!(stats.imported, 1);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "1002 AlreadyExists",
+                "unknown deserialize",
+                "1004 validation"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn passed_deadline_stops_after_the_row_in_hand() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,a,tester,1001\n\
+                   1,2024-01-01 10:00:00,b,tester,1002\n";
+        let options = ProcessOptions {
+            only_parse: true,
+            deadline: Some(Instant::now()),
+            ..ProcessOptions::default()
+        };
+        let result = process_test_csv(csv, "deadline.csv", &HashSet::new(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(result.file_name, "deadline.csv");
+        assert_eq!(result.sheet_name, None);
+        let stats = result.stats;
+        assert_eq!(stats.processed, 1);
+        assert!(stats.deadline_reached);
+        assert!(!stats.row_cap_reached);
+    }
+
+    #[tokio::test]
+    async fn rows_queued_when_the_run_aborts_are_counted_as_not_posted() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,a,tester,1001\n\
+                   1,2024-01-01 10:00:00,b,tester,1002\n\
+                   1,2024-01-01 10:00:00,,tester,1003\n\
+                   1,2024-01-01 10:00:00,d,tester,1004\n";
+        let options = ProcessOptions {
+            batch_size: 5,
+            empty_note: EmptyNote::Fail,
+            failure_threshold: Some(FailureThreshold {
+                ratio: 0.5,
+                min_sample: 1,
+                finished_before: 0,
+                failed_before: 0,
+            }),
+            ..ProcessOptions::default()
+        };
+        let stats = process_test_csv(csv, "aborted.csv", &HashSet::new(), &options)
+            .await
+            .unwrap()
+            .stats;
+
+        assert!(stats.aborted.is_some());
+        assert_eq!(stats.processed, 3);
+        assert_eq!(stats.imported, 0);
+        let failed: Vec<&str> = stats
+            .failed
+            .kept()
+            .iter()
+            .map(|failure| failure.action_id.as_str())
+            .collect();
+        assert_eq!(failed, vec!["1003", "1001", "1002"]);
+        assert_eq!(stats.failed.by_category().get("validation"), Some(&1));
+        assert_eq!(stats.failed.by_category().get("not_posted"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn another_file_aborting_the_run_stops_this_one() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,a,tester,1001\n\
+                   1,2024-01-01 10:00:00,b,tester,1002\n";
+        let run_guard = Arc::new(RunGuard::default());
+        run_guard.abort("fail-fast triggered by other.csv");
+        let options = ProcessOptions {
+            batch_size: 5,
+            run_guard: Some(run_guard.clone()),
+            ..ProcessOptions::default()
+        };
+        let stats = process_test_csv(csv, "running.csv", &HashSet::new(), &options)
+            .await
+            .unwrap()
+            .stats;
+
+        assert_eq!(
+            stats.aborted.as_deref(),
+            Some("fail-fast triggered by other.csv")
+        );
+        assert_eq!(stats.processed, 1);
+        assert_eq!(stats.failed.by_category().get("not_posted"), Some(&1));
+        assert_eq!(run_guard.totals(), (1, 1));
+        // The sheet's time counts towards the average logged by every file
+        assert_eq!(run_guard.sheet_times.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failure_ratio_counts_the_files_running_alongside() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,,tester,1001\n\
+                   1,2024-01-01 10:00:00,b,tester,1002\n";
+        let run_guard = Arc::new(RunGuard::default());
+        // Another file has finished 4 rows, 1 of them failed
+        run_guard.add(4, 1);
+        let options = ProcessOptions {
+            only_parse: true,
+            empty_note: EmptyNote::Fail,
+            failure_threshold: Some(FailureThreshold {
+                ratio: 0.3,
+                min_sample: 5,
+                finished_before: 0,
+                failed_before: 0,
+            }),
+            run_guard: Some(run_guard.clone()),
+            ..ProcessOptions::default()
+        };
+        let stats = process_test_csv(csv, "running.csv", &HashSet::new(), &options)
+            .await
+            .unwrap()
+            .stats;
+
+        // 2 of 5 failed across both files, though this file alone is under the sample size
+        assert_eq!(stats.processed, 1);
+        let reason = stats.aborted.unwrap();
+        assert!(reason.contains("2 of 5 finished rows failed"), "{}", reason);
+        assert_eq!(run_guard.aborted(), Some(reason));
+    }
+
+    #[tokio::test]
+    async fn missing_required_column_fails_the_file_once() {
+        let csv = "RequestID,actionDate,note,actionWho\n\
+                   1,2024-01-01 10:00:00,a,tester\n";
+        let options = ProcessOptions {
+            only_parse: true,
+            ..ProcessOptions::default()
+        };
+        let result = process_test_csv(csv, "missing.csv", &HashSet::new(), &options).await;
+
+        let Err(e) = result else {
+            panic!("a file without an action ID column should fail");
+        };
+        assert_eq!(
+            e.to_string(),
+            "csv file 'missing.csv' is missing required column(s): action_id (looked for actionid, cfactionid, cdactionid). Headers found: [RequestID, actionDate, note, actionWho]. Header names are matched ignoring case, spaces, and underscores; rename the column to one of the names looked for"
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_note_policy_posts_skips_or_fails_blank_notes() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,called back,tester,1001\n\
+                   1,2024-01-01 10:00:00,,tester,1002\n\
+                   1,2024-01-01 10:00:00,   ,tester,1003\n";
+        let mut results = Vec::new();
+        for empty_note in [EmptyNote::Post, EmptyNote::Skip, EmptyNote::Fail] {
+            // The prefix mustn't hide an empty note
+            let options = ProcessOptions {
+                only_parse: true,
+                empty_note,
+                note_prefix: "[Imported] ".to_string(),
+                ..ProcessOptions::default()
+            };
+            let stats = process_test_csv(csv, "empty_note.csv", &HashSet::new(), &options)
+                .await
+                .unwrap()
+                .stats;
+            results.push((
+                stats.processed,
+                stats.imported,
+                stats.skipped_empty_note,
+                stats.failed.len(),
+            ));
+        }
+
+        assert_eq!(results, vec![(3, 3, 0, 0), (3, 1, 2, 0), (3, 1, 0, 2)]);
+    }
+
+    #[tokio::test]
+    async fn required_fields_fail_rows_that_leave_them_blank() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,called back,tester,1001\n\
+                   1,,called back,tester,1002\n\
+                   1,2024-01-01 10:00:00,called back,  ,1003\n\
+                   1,2024-01-01 10:00:00,,tester,1004\n";
+        let mut results = Vec::new();
+        for required in [
+            vec![],
+            vec![RequiredField::ActionWho, RequiredField::ActionDate],
+        ] {
+            let options = ProcessOptions {
+                only_parse: true,
+                required_fields: [RequiredField::ALWAYS.to_vec(), required].concat(),
+                ..ProcessOptions::default()
+            };
+            let stats = process_test_csv(csv, "required_fields.csv", &HashSet::new(), &options)
+                .await
+                .unwrap()
+                .stats;
+            results.push((stats.imported, stats.failed.len()));
+            if let Some(failure) = stats.failed.kept().first() {
+                assert!(
+                    failure
+                        .error
+                        .to_string()
+                        .contains("Action ID: 1002 on row 2 of CSV file 'required_fields.csv' has no actiondate"),
+                    "{}",
+                    failure.error
+                );
+            }
+        }
+
+        // The blank note is posted either way, since `note` isn't listed
+        assert_eq!(results, vec![(4, 0), (2, 2)]);
+    }
+
+    #[tokio::test]
+    async fn bad_date_policy_fails_or_keeps_rows_with_unreadable_dates() {
+        let csv = "ticket_id,actiondate,note,actionwho,cfactionid\n\
+                   1,2024-01-01 10:00:00,a,tester,1001\n\
+                   1,01/02/2024 noon,b,tester,1002\n\
+                   1,,c,tester,1003\n";
+        let mut results = Vec::new();
+        for bad_date_policy in [BadDatePolicy::Fail, BadDatePolicy::Null, BadDatePolicy::Now] {
+            let options = ProcessOptions {
+                only_parse: true,
+                bad_date_policy,
+                ..ProcessOptions::default()
+            };
+            let stats = process_test_csv(csv, "bad_date.csv", &HashSet::new(), &options)
+                .await
+                .unwrap()
+                .stats;
+            results.push((stats.imported, stats.failed.len()));
+            if let Some(failure) = stats.failed.kept().first() {
+                assert_eq!(failure.action_id, "1002");
+                assert_eq!(failure.error.category(), "deserialize");
+            }
+        }
+
+        assert_eq!(results, vec![(2, 1), (3, 0), (3, 0)]);
+    }
+}
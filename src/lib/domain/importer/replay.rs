@@ -0,0 +1,360 @@
+use crate::domain::importer::summary::{ImportSummary, LatencySamples};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Suffix of the per-action record `--replay-log` writes next to the log it read.
+pub const REPLAY_SUFFIX: &str = "_replay.csv";
+
+/// One action the log names, with what happened to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedAction {
+    /// Timestamp of the log line, as written.
+    pub logged_at: String,
+    /// `"unknown"` for rows that failed before their action ID was read.
+    pub action_id: String,
+    /// Empty when the line doesn't say, as for batch posts.
+    pub ticket_id: String,
+    /// `imported`, `skipped`, or `failed`.
+    pub outcome: &'static str,
+    /// Failure category, for failed actions.
+    pub category: Option<&'static str>,
+    pub detail: String,
+}
+
+/// What a past run's log file records about it (`--replay-log`).
+///
+/// Read from the log lines the processor writes for each imported, skipped, and failed
+/// action, so the input files aren't needed. Quiet runs don't log each import or skip, so
+/// their totals come from the per-sheet lines instead, and a sheet that never finished
+/// counts only the actions it logged.
+#[derive(Debug, Default)]
+pub struct ReplayedRun {
+    pub run_id: Option<String>,
+    pub actions: Vec<LoggedAction>,
+    pub failures_by_category: BTreeMap<&'static str, usize>,
+    /// The first reason a file was stopped early.
+    pub stopped_early: Option<String>,
+    /// Rows skipped as already existing, which are logged as counts without IDs.
+    skipped_existing: usize,
+    skipped_empty_note: usize,
+    sheet_imported: usize,
+    sheet_skipped: usize,
+    first_line: Option<DateTime<Utc>>,
+    last_line: Option<DateTime<Utc>>,
+}
+
+/// Splits a log line into its timestamp, level, target, and message. `None` for lines
+/// that aren't the start of a log entry, such as the rest of a multi-line error.
+fn split_line(line: &str) -> Option<(&str, &str, &str, &str)> {
+    let (logged_at, rest) = line.split_once(' ')?;
+    DateTime::parse_from_rfc3339(logged_at).ok()?;
+    let (level, mut rest) = rest.trim_start().split_once(' ')?;
+    loop {
+        let (head, tail) = rest.split_once(": ")?;
+        // Spans such as `run{run_id=1a2b3c4d}` come before the target
+        if head.contains('{') {
+            rest = tail;
+            continue;
+        }
+        return Some((logged_at, level, head, tail));
+    }
+}
+
+/// A count as the importer logs it, e.g. `1,234`.
+fn parse_count(value: &str) -> Option<usize> {
+    value.replace(',', "").parse().ok()
+}
+
+/// The action ID a free-form failure message names, e.g. `Action ID: 1001 on row 4`.
+fn action_id_in(message: &str) -> Option<&str> {
+    // Messages start with either `Action ID:` or `action ID:`
+    let start = message.find("ction ID: ")? + "ction ID: ".len();
+    message[start..]
+        .split([' ', ',', ')'])
+        .next()
+        .filter(|id| !id.is_empty())
+}
+
+impl ReplayedRun {
+    /// Reads a run's log file, gzipped (`COMPRESS_OLD_LOGS`) or not.
+    pub fn read(log_path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(log_path)
+            .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+        let gzipped = log_path.extension().is_some_and(|ext| ext == "gz");
+        let reader: Box<dyn BufRead> = if gzipped {
+            Box::new(BufReader::new(GzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        Self::from_reader(reader)
+            .with_context(|| format!("Failed to read log file: {}", log_path.display()))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> anyhow::Result<Self> {
+        let mut run = Self::default();
+        for line in reader.lines() {
+            run.read_line(&line?);
+        }
+        Ok(run)
+    }
+
+    fn read_line(&mut self, line: &str) {
+        let Some((logged_at, level, target, message)) = split_line(line) else {
+            return;
+        };
+        if let Ok(time) = DateTime::parse_from_rfc3339(logged_at) {
+            let time = time.with_timezone(&Utc);
+            self.first_line.get_or_insert(time);
+            self.last_line = Some(time);
+        }
+        let action = |action_id: &str, ticket_id: &str, outcome, detail: &str| LoggedAction {
+            logged_at: logged_at.to_string(),
+            action_id: action_id.to_string(),
+            ticket_id: ticket_id.to_string(),
+            outcome,
+            category: None,
+            detail: detail.to_string(),
+        };
+
+        if let Some(run_id) = message
+            .strip_prefix("Starting Halo action importer (run ID: ")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            self.run_id = Some(run_id.to_string());
+        } else if let Some(rest) = message.strip_prefix("Success: imported action ID: ") {
+            let (action_id, ticket_id) = rest.split_once(" (ticket ID: ").unwrap_or((rest, ""));
+            self.actions.push(action(
+                action_id,
+                ticket_id.trim_end_matches(')'),
+                "imported",
+                "",
+            ));
+        } else if let Some(rest) = message.strip_prefix("Success: imported batch of ") {
+            let action_ids = rest
+                .split(" | ")
+                .find_map(|part| part.strip_prefix("action IDs: "))
+                .unwrap_or_default();
+            for action_id in action_ids.split(", ").filter(|id| !id.is_empty()) {
+                self.actions
+                    .push(action(action_id, "", "imported", "posted in a batch"));
+            }
+        } else if let Some(count) = message
+            .strip_prefix("Skipped ")
+            .and_then(|rest| rest.strip_suffix(" entries (already exist)"))
+        {
+            self.skipped_existing += parse_count(count).unwrap_or_default();
+        } else if let Some(rest) = message.strip_prefix("Skipped duplicate action ID: ") {
+            let action_id = rest.split(' ').next().unwrap_or_default();
+            self.actions
+                .push(action(action_id, "", "skipped", "duplicate action ID"));
+        } else if let Some(rest) = message.strip_prefix("Skipped action ID: ")
+            && rest.ends_with(": empty note")
+        {
+            let action_id = rest.split(' ').next().unwrap_or_default();
+            self.skipped_empty_note += 1;
+            self.actions
+                .push(action(action_id, "", "skipped", "empty note"));
+        } else if message.starts_with("Completed sheet ") {
+            let counts = message.split(" | ").nth(1).unwrap_or_default();
+            for part in counts.split(", ") {
+                let mut words = part.split(' ');
+                let count = words.next().and_then(parse_count).unwrap_or_default();
+                match words.next() {
+                    Some("imported") => self.sheet_imported += count,
+                    Some("skipped") => self.sheet_skipped += count,
+                    _ => {}
+                }
+            }
+        } else if message.starts_with("Stopped processing ") {
+            if self.stopped_early.is_none()
+                && let Some((_, reason)) = message.split_once(" early: ")
+            {
+                self.stopped_early = Some(reason.to_string());
+            }
+        } else if level == "ERROR" && target.ends_with("::processor") {
+            let (mut failed, category) =
+                if let Some(rest) = message.strip_prefix("Failed to import action ID: ") {
+                    let (action_id, rest) = rest.split_once(" (ticket ID: ").unwrap_or((rest, ""));
+                    let (ticket_id, error) = rest.split_once("): ").unwrap_or((rest, ""));
+                    // The same text `ImportError::Http` displays, whatever the batch size
+                    let category = if error.contains("POST failed for batch: status ") {
+                        "http"
+                    } else {
+                        "network"
+                    };
+                    (action(action_id, ticket_id, "failed", error), category)
+                } else if message.starts_with("Failed to deserialize row in ") {
+                    (action("unknown", "", "failed", message), "deserialize")
+                } else if let Some(rest) = message
+                    .strip_prefix("Action ID: ")
+                    .and_then(|rest| rest.strip_suffix(") not posted: run aborted"))
+                {
+                    let (action_id, ticket_id) =
+                        rest.split_once(" (ticket ID: ").unwrap_or((rest, ""));
+                    (action(action_id, ticket_id, "failed", message), "not_posted")
+                } else {
+                    let action_id = action_id_in(message).unwrap_or("unknown");
+                    (action(action_id, "", "failed", message), "validation")
+                };
+            failed.category = Some(category);
+            *self.failures_by_category.entry(category).or_insert(0) += 1;
+            self.actions.push(failed);
+        }
+    }
+
+    fn count(&self, outcome: &str) -> usize {
+        self.actions
+            .iter()
+            .filter(|action| action.outcome == outcome)
+            .count()
+    }
+
+    pub fn imported(&self) -> usize {
+        self.count("imported").max(self.sheet_imported)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count("failed")
+    }
+
+    /// Rows skipped for any reason other than an empty note.
+    pub fn skipped(&self) -> usize {
+        let logged = self.skipped_existing + self.count("skipped") - self.skipped_empty_note;
+        logged.max(self.sheet_skipped.saturating_sub(self.skipped_empty_note))
+    }
+
+    /// The run's summary as far as its log records it. Retries, latencies, and unreadable
+    /// files aren't rebuilt, and the runtime is the time from the first log line to the
+    /// last.
+    pub fn summary(&self) -> ImportSummary {
+        let total_imported = self.imported();
+        let total_skipped = self.skipped();
+        let total_failed = self.failed();
+        ImportSummary {
+            total_processed: total_imported
+                + total_skipped
+                + self.skipped_empty_note
+                + total_failed,
+            total_imported,
+            total_skipped,
+            total_sampled_out: 0,
+            total_skipped_empty_note: self.skipped_empty_note,
+            total_failed,
+            failures_by_category: self.failures_by_category.clone(),
+            failures_not_kept: 0,
+            skipped_files: Vec::new(),
+            total_runtime_secs: match (self.first_line, self.last_line) {
+                (Some(first), Some(last)) => (last - first).as_seconds_f64(),
+                _ => 0.0,
+            },
+            sheet_times: Vec::new(),
+            row_latencies: LatencySamples::default(),
+            retries: Default::default(),
+            stopped_early: self.stopped_early.clone(),
+            report_stats: None,
+        }
+    }
+
+    /// Writes every action the log names, one row each, in log order.
+    pub fn write_actions(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create replay file: {}", path.display()))?;
+        writer.write_record([
+            "logged_at",
+            "action_id",
+            "ticket_id",
+            "outcome",
+            "category",
+            "detail",
+        ])?;
+        for action in &self.actions {
+            writer.write_record([
+                action.logged_at.as_str(),
+                &action.action_id,
+                &action.ticket_id,
+                action.outcome,
+                action.category.unwrap_or_default(),
+                &action.detail,
+            ])?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write replay file: {}", path.display()))
+    }
+}
+
+/// Where `--replay-log` writes the actions of a log, e.g. `<run>.log.gz` gives
+/// `<run>_replay.csv`.
+pub fn replay_path_for(log_path: &Path) -> PathBuf {
+    let name = log_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let run = name.strip_suffix(".gz").unwrap_or(name);
+    let run = run.strip_suffix(".log").unwrap_or(run);
+    log_path.with_file_name(format!("{}{}", run, REPLAY_SUFFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilds_actions_and_totals_from_a_log() {
+        let log = "\
+2024-01-15T09:30:00.000000000+00:00  INFO halo_action_importer::domain::importer::setup: Starting Halo action importer (run ID: 1a2b3c4d)
+2024-01-15T09:30:01.000000000+00:00  INFO run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Skipped 1,200 entries (already exist)
+2024-01-15T09:30:02.000000000+00:00  INFO run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Success: imported action ID: 1001 (ticket ID: 2997)
+2024-01-15T09:30:03.000000000+00:00  INFO run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Success: imported batch of 2 actions | action IDs: 1002, 1003 | ticket IDs: 2997
+2024-01-15T09:30:04.000000000+00:00 ERROR run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Failed to import action ID: 1004 (ticket ID: 5): Action object POST failed for batch: status 400, request ID: abc, error: Ticket not found
+2024-01-15T09:30:05.000000000+00:00 ERROR run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Action ID: 1005 on row 7 of CSV file 'a.csv' has an empty note
+second line of a multi-line error
+2024-01-15T09:30:05.500000000+00:00 ERROR run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Action ID: 1006 (ticket ID: 8) not posted: run aborted
+2024-01-15T09:30:06.000000000+00:00 ERROR run{run_id=1a2b3c4d}: halo_action_importer::domain::importer::processor: Stopped processing CSV file 'a.csv' early: fail-fast triggered
+2024-01-15T09:30:07.000000000+00:00 ERROR run{run_id=1a2b3c4d}: halo_action_importer: Failed to write run history: disk full
+";
+        let run = ReplayedRun::from_reader(log.as_bytes()).unwrap();
+        assert_eq!(run.run_id.as_deref(), Some("1a2b3c4d"));
+        let ids: Vec<(&str, &str)> = run
+            .actions
+            .iter()
+            .map(|action| (action.action_id.as_str(), action.outcome))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("1001", "imported"),
+                ("1002", "imported"),
+                ("1003", "imported"),
+                ("1004", "failed"),
+                ("1005", "failed"),
+                ("1006", "failed"),
+            ]
+        );
+        assert_eq!(run.actions[3].ticket_id, "5");
+        assert_eq!(run.actions[3].category, Some("http"));
+        assert_eq!(run.actions[4].category, Some("validation"));
+        assert_eq!(run.actions[5].ticket_id, "8");
+        assert_eq!(run.actions[5].category, Some("not_posted"));
+
+        let summary = run.summary();
+        assert_eq!(summary.total_imported, 3);
+        assert_eq!(summary.total_skipped, 1_200);
+        assert_eq!(summary.total_failed, 3);
+        assert_eq!(summary.total_processed, 1_206);
+        assert_eq!(summary.total_runtime_secs, 7.0);
+        assert_eq!(
+            summary.stopped_early.as_deref(),
+            Some("fail-fast triggered")
+        );
+        assert_eq!(
+            replay_path_for(Path::new("log/2024-01-15_09-30-00_1a2b3c4d.log.gz")),
+            Path::new("log/2024-01-15_09-30-00_1a2b3c4d_replay.csv")
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use crate::domain::importer::error::{FailedAction, ImportError};
+use crate::domain::importer::summary::{ImportSummary, LatencySamples};
+use crate::outbound::client::webhook::RunNotification;
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Artifact suffix of the import manifest, one row per created action.
+pub const MANIFEST_SUFFIX: &str = "_imported.csv";
+
+/// Artifact suffix of the rejects file, one row per failed action.
+pub const REJECTS_SUFFIX: &str = "_rejects.csv";
+
+/// The rejects file written alongside a run's import manifest.
+pub fn rejects_path_for(manifest_path: &Path) -> PathBuf {
+    let name = manifest_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let run = name.strip_suffix(MANIFEST_SUFFIX).unwrap_or(name);
+    manifest_path.with_file_name(format!("{}{}", run, REJECTS_SUFFIX))
+}
+
+/// Writes every failure of a run, so its summary can be rebuilt later (`--summary-only`).
+pub fn write_rejects(path: &Path, failed: &[FailedAction]) -> anyhow::Result<()> {
+    RejectsWriter::new(path.to_path_buf()).write(failed)
+}
+
+/// A run's rejects file, shared by every file of the run.
+///
+/// Failures past `MAX_FAILURES_KEPT` are written as they happen and the rest when the run
+/// ends, so the file lists every failure of the run, though not in order.
+#[derive(Debug)]
+pub struct RejectsWriter {
+    path: PathBuf,
+    writer: Mutex<Option<csv::Writer<File>>>,
+}
+
+impl RejectsWriter {
+    /// Nothing is created until the first write.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            writer: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends failures, creating the file with its header on the first call.
+    pub fn write(&self, failed: &[FailedAction]) -> anyhow::Result<()> {
+        let mut guard = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let writer = match &mut *guard {
+            Some(writer) => writer,
+            None => {
+                let mut writer = csv::Writer::from_path(&self.path).with_context(|| {
+                    format!("Failed to create rejects file: {}", self.path.display())
+                })?;
+                writer.write_record(["action_id", "category", "error"])?;
+                guard.insert(writer)
+            }
+        };
+        for failure in failed {
+            writer.write_record([
+                failure.action_id.as_str(),
+                failure.error.category(),
+                &failure.error.to_string(),
+            ])?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write rejects file: {}", self.path.display()))
+    }
+}
+
+/// File in the output directory that `--summary-format csv` appends one row per run to.
+pub const HISTORY_FILE: &str = "history.csv";
+
+/// Appends a run's totals to the running history, writing the header first if the file is
+/// new, so a migration's progress can be followed across days of runs.
+pub fn append_history(
+    path: &Path,
+    started_at: DateTime<Utc>,
+    run: &RunNotification,
+) -> anyhow::Result<()> {
+    let is_new = std::fs::metadata(path).map_or(true, |meta| meta.len() == 0);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open run history: {}", path.display()))?;
+    let mut writer = csv::Writer::from_writer(file);
+    if is_new {
+        writer.write_record([
+            "date",
+            "run_id",
+            "status",
+            "only_parse",
+            "processed",
+            "imported",
+            "skipped",
+            "failed",
+            "unreadable_files",
+            "runtime_secs",
+        ])?;
+    }
+    writer.write_record([
+        started_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+        run.run_id.clone(),
+        run.status.to_string(),
+        run.only_parse.to_string(),
+        run.processed.to_string(),
+        run.imported.to_string(),
+        run.skipped.to_string(),
+        run.failed.to_string(),
+        run.unreadable_files.to_string(),
+        format!("{:.1}", run.runtime_secs),
+    ])?;
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write run history: {}", path.display()))
+}
+
+/// What a past run's artifacts record about it.
+#[derive(Debug, Default)]
+pub struct PastRun {
+    /// Artifact name prefix, `<start time>_<run ID>`.
+    pub run: String,
+    pub imported: usize,
+    pub failures_by_category: BTreeMap<&'static str, usize>,
+    /// Runs from before rejects files were written have only their manifest.
+    pub has_rejects: bool,
+    first_import: Option<DateTime<Utc>>,
+    last_import: Option<DateTime<Utc>>,
+}
+
+impl PastRun {
+    /// Reads a run's import manifest and, when there is one, its rejects file.
+    pub fn read(manifest_path: &Path) -> anyhow::Result<Self> {
+        let name = manifest_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let mut past_run = Self {
+            run: name
+                .strip_suffix(MANIFEST_SUFFIX)
+                .unwrap_or(name)
+                .to_string(),
+            ..Self::default()
+        };
+        let mut manifest = csv::Reader::from_path(manifest_path).with_context(|| {
+            format!(
+                "Failed to open import manifest: {}",
+                manifest_path.display()
+            )
+        })?;
+        for (idx, record) in manifest.records().enumerate() {
+            let record = record.with_context(|| {
+                format!(
+                    "Failed to read row {} of import manifest: {}",
+                    idx + 1,
+                    manifest_path.display()
+                )
+            })?;
+            past_run.imported += 1;
+            if let Some(imported_at) = record
+                .get(0)
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|value| value.with_timezone(&Utc))
+            {
+                past_run.first_import = Some(
+                    past_run
+                        .first_import
+                        .map_or(imported_at, |t| t.min(imported_at)),
+                );
+                past_run.last_import = Some(
+                    past_run
+                        .last_import
+                        .map_or(imported_at, |t| t.max(imported_at)),
+                );
+            }
+        }
+
+        let rejects_path = rejects_path_for(manifest_path);
+        if !rejects_path.is_file() {
+            return Ok(past_run);
+        }
+        past_run.has_rejects = true;
+        let mut rejects = csv::Reader::from_path(&rejects_path)
+            .with_context(|| format!("Failed to open rejects file: {}", rejects_path.display()))?;
+        for (idx, record) in rejects.records().enumerate() {
+            let record = record.with_context(|| {
+                format!(
+                    "Failed to read row {} of rejects file: {}",
+                    idx + 1,
+                    rejects_path.display()
+                )
+            })?;
+            let category = record.get(1).unwrap_or_default();
+            let category = ImportError::CATEGORIES
+                .into_iter()
+                .find(|known| *known == category)
+                .with_context(|| {
+                    format!(
+                        "Unknown failure category '{}' in row {} of rejects file: {}",
+                        category,
+                        idx + 1,
+                        rejects_path.display()
+                    )
+                })?;
+            *past_run.failures_by_category.entry(category).or_insert(0) += 1;
+        }
+        Ok(past_run)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failures_by_category.values().sum()
+    }
+
+    /// Time from the run's first import to its last; the manifest records nothing earlier.
+    fn import_span_secs(&self) -> f64 {
+        match (self.first_import, self.last_import) {
+            (Some(first), Some(last)) => (last - first).as_seconds_f64(),
+            _ => 0.0,
+        }
+    }
+}
+
+/// The import manifests under `path`: the file itself, or every one in a directory in
+/// run order.
+pub fn find_manifests(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        anyhow::bail!(
+            "'{}' is not a manifest file or output directory",
+            path.display()
+        );
+    }
+    let mut manifests = Vec::new();
+    for entry in std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read output directory: {}", path.display()))?
+    {
+        let entry_path = entry
+            .with_context(|| {
+                format!(
+                    "Failed to read entry in output directory: {}",
+                    path.display()
+                )
+            })?
+            .path();
+        let is_manifest = entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| name.ends_with(MANIFEST_SUFFIX));
+        if is_manifest && entry_path.is_file() {
+            manifests.push(entry_path);
+        }
+    }
+    // Artifact names start with the run's start time
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// One summary over past runs, from what their artifacts record.
+///
+/// Skipped rows, unreadable files, and retries aren't in any artifact, so they read as
+/// zero. Rows processed are the imported and failed rows, and the runtime is the time
+/// between each run's first and last import.
+pub fn combined_summary(runs: &[PastRun]) -> ImportSummary {
+    let mut failures_by_category = BTreeMap::new();
+    for run in runs {
+        for (category, count) in &run.failures_by_category {
+            *failures_by_category.entry(*category).or_insert(0) += count;
+        }
+    }
+    let total_imported = runs.iter().map(|run| run.imported).sum::<usize>();
+    let total_failed = runs.iter().map(PastRun::failed).sum::<usize>();
+    ImportSummary {
+        total_processed: total_imported + total_failed,
+        total_imported,
+        total_skipped: 0,
+        total_sampled_out: 0,
+        total_skipped_empty_note: 0,
+        total_failed,
+        failures_by_category,
+        failures_not_kept: 0,
+        skipped_files: Vec::new(),
+        total_runtime_secs: runs.iter().map(PastRun::import_span_secs).sum(),
+        sheet_times: Vec::new(),
+        row_latencies: LatencySamples::default(),
+        retries: Default::default(),
+        stopped_early: None,
+        report_stats: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn rebuilds_totals_from_manifest_and_rejects() {
+        let dir = TempPath::dir("history");
+        let manifest_path = dir.join(format!("2024-01-15_09-30-00_1a2b3c4d{}", MANIFEST_SUFFIX));
+        std::fs::write(
+            &manifest_path,
+            "imported_at,action_id,ticket_id,source\n\
+             2024-01-15T09:30:00+00:00,1001,2997,actions.csv\n\
+             2024-01-15T09:31:30+00:00,1002,2997,actions.csv\n",
+        )
+        .unwrap();
+        write_rejects(
+            &rejects_path_for(&manifest_path),
+            &[FailedAction {
+                action_id: "1003".to_string(),
+                error: ImportError::Validation("ticket 5 not found".to_string()),
+            }],
+        )
+        .unwrap();
+        // A run from before rejects files were written
+        std::fs::write(
+            dir.join(format!("2024-01-14_09-30-00_5e6f7a8b{}", MANIFEST_SUFFIX)),
+            "imported_at,action_id,ticket_id,source\n\
+             2024-01-14T09:30:00+00:00,1000,2997,actions.csv\n",
+        )
+        .unwrap();
+
+        let manifests = find_manifests(&dir).unwrap();
+        let runs: Vec<PastRun> = manifests
+            .iter()
+            .map(|path| PastRun::read(path).unwrap())
+            .collect();
+
+        assert_eq!(runs[0].run, "2024-01-14_09-30-00_5e6f7a8b");
+        assert!(!runs[0].has_rejects);
+        assert!(runs[1].has_rejects);
+        let summary = combined_summary(&runs);
+        assert_eq!(summary.total_imported, 3);
+        assert_eq!(summary.total_failed, 1);
+        assert_eq!(summary.total_processed, 4);
+        assert_eq!(summary.failures_by_category.get("validation"), Some(&1));
+        assert_eq!(summary.total_runtime_secs, 90.0);
+    }
+
+    #[test]
+    fn history_gets_one_header_and_a_row_per_run() {
+        let path = TempPath::new("history.csv");
+        let mut summary = combined_summary(&[]);
+        summary.total_runtime_secs = 12.34;
+        let started_at = DateTime::parse_from_rfc3339("2024-01-15T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for run_id in ["1a2b3c4d", "5e6f7a8b"] {
+            append_history(
+                &path,
+                started_at,
+                &summary.notification(run_id, false, None),
+            )
+            .unwrap();
+        }
+        let history = std::fs::read_to_string(&path).unwrap();
+
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("date,run_id,status"));
+        assert_eq!(
+            lines[2],
+            "2024-01-15T09:30:00+00:00,5e6f7a8b,completed,false,0,0,0,0,0,12.3"
+        );
+    }
+}
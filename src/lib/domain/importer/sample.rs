@@ -0,0 +1,60 @@
+/// Deterministic row sampler for `SAMPLE_RATE`.
+///
+/// Each row is kept or dropped based on a hash of its action ID and the seed, so the same
+/// file, rate, and seed always select the same rows regardless of file order or batching.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowSampler {
+    rate: f64,
+    seed: u64,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl RowSampler {
+    /// `rate` is the fraction of rows to keep, between 0.0 and 1.0.
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Self { rate, seed }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether the row with this action ID is part of the sample.
+    pub fn keeps(&self, action_id: &str) -> bool {
+        // FNV-1a rather than `DefaultHasher`, whose output may change between Rust releases
+        let mut hash = FNV_OFFSET_BASIS;
+        for byte in self.seed.to_le_bytes().iter().chain(action_id.as_bytes()) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        // Top 53 bits give a uniform value in [0, 1)
+        let position = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        position < self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_is_deterministic_and_close_to_rate() {
+        let ids: Vec<String> = (0..10_000).map(|id| id.to_string()).collect();
+        let sampler = RowSampler::new(0.1, 42);
+        let kept: Vec<&String> = ids.iter().filter(|id| sampler.keeps(id)).collect();
+        let again: Vec<&String> = ids.iter().filter(|id| sampler.keeps(id)).collect();
+        assert_eq!(kept, again);
+        assert!((800..1200).contains(&kept.len()), "kept {}", kept.len());
+
+        let other_seed = RowSampler::new(0.1, 7);
+        assert!(kept.iter().any(|id| !other_seed.keeps(id)));
+        assert!(ids.iter().all(|id| RowSampler::new(1.0, 42).keeps(id)));
+        assert!(!ids.iter().any(|id| RowSampler::new(0.0, 42).keeps(id)));
+    }
+}
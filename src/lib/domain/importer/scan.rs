@@ -0,0 +1,233 @@
+use crate::config::BadDatePolicy;
+use crate::domain::importer::processor::format_number;
+use crate::domain::models::action_object::ActionObject;
+#[cfg(feature = "sqlite")]
+use crate::inbound::file::sqlite::Sqlite;
+use crate::inbound::file::{ColumnMapping, InputKind, Reader, csv::Csv, excel::Excel};
+use anyhow::Context;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+type ActionIter = Box<dyn Iterator<Item = anyhow::Result<ActionObject>>>;
+
+/// Counts gathered by parsing every file up front without posting anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreScan {
+    pub files: usize,
+    pub unreadable_files: usize,
+    /// Rows that deserialized into an `ActionObject`.
+    pub valid: usize,
+    /// Rows that failed to deserialize.
+    pub invalid: usize,
+    /// Valid rows whose action ID already exists in Halo.
+    pub to_skip: usize,
+    /// Valid rows that would be posted.
+    pub to_post: usize,
+    /// Valid rows whose action ID was already seen earlier in the scan.
+    pub duplicates: usize,
+    /// The same counts broken down by file, in scan order.
+    pub per_file: Vec<FileScan>,
+}
+
+/// Pre-scan counts for a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileScan {
+    pub file_name: String,
+    /// The file couldn't be opened; every count is zero.
+    pub unreadable: bool,
+    pub to_post: usize,
+    pub to_skip: usize,
+    pub invalid: usize,
+}
+
+impl FileScan {
+    pub fn total_rows(&self) -> usize {
+        self.to_post + self.to_skip + self.invalid
+    }
+}
+
+impl PreScan {
+    pub fn total_rows(&self) -> usize {
+        self.valid + self.invalid
+    }
+
+    pub fn log(&self) {
+        info!("=== Pre-scan ===");
+        info!(
+            "Files scanned: {} ({} unreadable)",
+            format_number(self.files),
+            format_number(self.unreadable_files)
+        );
+        info!("Total rows: {}", format_number(self.total_rows()));
+        info!(
+            "Valid: {} | invalid: {}",
+            format_number(self.valid),
+            format_number(self.invalid)
+        );
+        info!(
+            "Would skip (already exist): {} | would post: {} | duplicate action IDs: {}",
+            format_number(self.to_skip),
+            format_number(self.to_post),
+            format_number(self.duplicates)
+        );
+        if self.per_file.is_empty() {
+            return;
+        }
+        let name_width = self
+            .per_file
+            .iter()
+            .map(|file| file.file_name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("File".len());
+        info!(
+            "{:<name_width$}  {:>10}  {:>12}  {:>10}  {:>10}",
+            "File", "Total rows", "Would import", "Would skip", "Invalid"
+        );
+        for file in &self.per_file {
+            if file.unreadable {
+                info!("{:<name_width$}  unreadable", file.file_name);
+                continue;
+            }
+            info!(
+                "{:<name_width$}  {:>10}  {:>12}  {:>10}  {:>10}",
+                file.file_name,
+                format_number(file.total_rows()),
+                format_number(file.to_post),
+                format_number(file.to_skip),
+                format_number(file.invalid)
+            );
+        }
+    }
+
+    /// Writes the per-file breakdown as CSV.
+    pub fn write_csv(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create pre-scan report: {}", path.display()))?;
+        writer.write_record([
+            "file",
+            "total_rows",
+            "would_import",
+            "would_skip",
+            "invalid",
+            "unreadable",
+        ])?;
+        for file in &self.per_file {
+            writer.write_record([
+                file.file_name.clone(),
+                file.total_rows().to_string(),
+                file.to_post.to_string(),
+                file.to_skip.to_string(),
+                file.invalid.to_string(),
+                file.unreadable.to_string(),
+            ])?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write pre-scan report: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Parses every file and tallies valid, invalid, skip, and duplicate counts. Rows with an
+/// unreadable date are invalid under `BadDatePolicy::Fail`, as the import would fail them.
+pub fn prescan_files(
+    files_to_process: &[(PathBuf, String)],
+    existing_ids: &HashSet<String>,
+    mapping: &ColumnMapping,
+    bad_date_policy: BadDatePolicy,
+) -> PreScan {
+    let mut scan = PreScan::default();
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    for (file_path, file_name) in files_to_process {
+        let Some(kind) = InputKind::of(file_path) else {
+            continue;
+        };
+        let mut file_scan = FileScan {
+            file_name: file_name.clone(),
+            ..FileScan::default()
+        };
+        let opened = match kind {
+            InputKind::Csv => <Reader as Csv>::csv_action_iter(file_path, mapping)
+                .map(|iter| Box::new(iter) as ActionIter),
+            InputKind::Spreadsheet => <Reader as Excel>::excel_action_iter(file_path, mapping)
+                .map(|iter| Box::new(iter) as ActionIter),
+            #[cfg(feature = "sqlite")]
+            InputKind::Sqlite => <Reader as Sqlite>::sqlite_action_iter(file_path, mapping)
+                .map(|iter| Box::new(iter) as ActionIter),
+            InputKind::Archive => continue,
+        };
+        let actions = match opened {
+            Ok(actions) => actions,
+            Err(e) => {
+                warn!("Could not pre-scan {:?}: {}", file_name, e);
+                scan.unreadable_files += 1;
+                file_scan.unreadable = true;
+                scan.per_file.push(file_scan);
+                continue;
+            }
+        };
+        scan.files += 1;
+        for action_result in actions {
+            let action = match action_result {
+                Ok(action)
+                    if bad_date_policy != BadDatePolicy::Fail || action.date_error().is_none() =>
+                {
+                    action
+                }
+                _ => {
+                    scan.invalid += 1;
+                    file_scan.invalid += 1;
+                    continue;
+                }
+            };
+            scan.valid += 1;
+            if !seen_ids.insert(action.action_id().to_string()) {
+                scan.duplicates += 1;
+            }
+            if existing_ids.contains(action.action_id()) {
+                scan.to_skip += 1;
+                file_scan.to_skip += 1;
+            } else {
+                scan.to_post += 1;
+                file_scan.to_post += 1;
+            }
+        }
+        scan.per_file.push(file_scan);
+    }
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn counts_are_broken_down_per_file() {
+        let dir = TempPath::dir("prescan");
+        let header = "ticket_id,note,actionwho,cfactionid,actionDate\n";
+        let first = dir.join("a.csv");
+        let second = dir.join("b.csv");
+        std::fs::write(
+            &first,
+            format!("{header}1,note,tester,100,\n1,note,tester,101,\n"),
+        )
+        .unwrap();
+        std::fs::write(&second, format!("{header},note,tester,102,\n")).unwrap();
+        let files = vec![(first, "a.csv".to_string()), (second, "b.csv".to_string())];
+        let existing: HashSet<String> = ["100".to_string()].into_iter().collect();
+        let scan = prescan_files(
+            &files,
+            &existing,
+            &ColumnMapping::default(),
+            BadDatePolicy::default(),
+        );
+
+        assert_eq!(scan.per_file.len(), 2);
+        assert_eq!((scan.per_file[0].to_post, scan.per_file[0].to_skip), (1, 1));
+        assert_eq!(scan.per_file[1].invalid, 1);
+        assert_eq!(scan.total_rows(), 3);
+    }
+}
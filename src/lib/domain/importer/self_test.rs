@@ -0,0 +1,100 @@
+use crate::domain::models::action_object::{ActionId, ActionObject};
+use crate::inbound::file::{ColumnMapping, Reader, csv::Csv, excel::Excel};
+use anyhow::Context;
+use chrono::NaiveDate;
+use std::io::Write;
+
+const SAMPLE_CSV: &str = include_str!("../../../../fixtures/sample_actions.csv");
+const SAMPLE_XLSX: &[u8] = include_bytes!("../../../../fixtures/sample_actions.xlsx");
+const EXPECTED_PAYLOAD: &str = include_str!("../../../../fixtures/sample_payload.json");
+const SAMPLE_ROWS: usize = 2;
+/// `ACTION_ID_CUSTOM_FIELD_ID` the expected payload was written with.
+const SAMPLE_FIELD_ID: u32 = 123;
+
+fn expected_payload() -> anyhow::Result<serde_json::Value> {
+    serde_json::from_str(EXPECTED_PAYLOAD).context("embedded expected payload is not valid JSON")
+}
+
+fn check_payload(label: &str, action: &ActionObject) -> anyhow::Result<()> {
+    let actual = serde_json::to_value(action.payload(SAMPLE_FIELD_ID, &[]))
+        .with_context(|| format!("{}: failed to serialize action", label))?;
+    let expected = expected_payload()?;
+    if actual != expected {
+        anyhow::bail!(
+            "{}: payload does not match the expected payload\n  expected: {}\n  actual:   {}",
+            label,
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+fn check_parsed(label: &str, actions: Vec<anyhow::Result<ActionObject>>) -> anyhow::Result<()> {
+    let actions = actions
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .with_context(|| format!("{}: failed to parse sample rows", label))?;
+    if actions.len() != SAMPLE_ROWS {
+        anyhow::bail!(
+            "{}: expected {} rows, parsed {}",
+            label,
+            SAMPLE_ROWS,
+            actions.len()
+        );
+    }
+    check_payload(label, &actions[0])
+}
+
+/// Checks the build without credentials or a server: serializes a known action against
+/// the embedded expected payload, then parses the embedded CSV and Excel samples and
+/// checks their first row serializes to the same payload.
+pub fn run_self_test() -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout();
+
+    let sample = ActionObject::new(
+        2997,
+        NaiveDate::from_ymd_opt(2024, 1, 15).and_then(|d| d.and_hms_opt(9, 30, 0)),
+        None,
+        "Sample imported note",
+        "Sample Agent",
+        ActionId::new("1001"),
+    );
+    check_payload("serializer", &sample)?;
+    writeln!(stdout, "ok: serializer matches the expected payload")?;
+
+    let csv_actions = <Reader as Csv>::csv_action_iter_from_reader(
+        SAMPLE_CSV.as_bytes(),
+        "sample_actions.csv",
+        &ColumnMapping::default(),
+    )
+    .collect();
+    check_parsed("csv", csv_actions)?;
+    writeln!(stdout, "ok: parsed sample CSV ({} rows)", SAMPLE_ROWS)?;
+
+    let excel_actions = <Reader as Excel>::excel_action_iter_from_bytes(
+        SAMPLE_XLSX.to_vec(),
+        "sample_actions.xlsx",
+        &ColumnMapping::default(),
+    )?
+    .collect();
+    check_parsed("excel", excel_actions)?;
+    writeln!(
+        stdout,
+        "ok: parsed sample Excel workbook ({} rows)",
+        SAMPLE_ROWS
+    )?;
+
+    writeln!(stdout, "Self-test passed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_test_passes() {
+        run_self_test().unwrap();
+    }
+}
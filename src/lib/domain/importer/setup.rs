@@ -1,51 +1,159 @@
-use crate::config::Config;
-use crate::inbound::client::ReportClient;
-use crate::outbound::client::{action::ActionClient, auth::AuthClient};
+use crate::config::{Config, FileOrder};
+use crate::domain::importer::agent_map::AgentMap;
+use crate::domain::importer::field_check::custom_field_value;
+use crate::domain::importer::processor::format_number;
+use crate::domain::importer::ticket_map::TicketIdMap;
+use crate::inbound::client::{ReportClient, ReportFetchStats};
+use crate::inbound::file::{InputKind, archive::extract_archive};
+use crate::outbound::client::{
+    action::ActionClient,
+    auth::AuthClient,
+    http::{RetryStats, build_http_client},
+};
 use anyhow::Context;
-use chrono::Utc;
-use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+use flate2::{Compression, write::GzEncoder};
+use reqwest::Client as ReqwestClient;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsStr;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{error, info, warn};
 use tracing_subscriber::{
     Registry, filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
-fn format_number(n: usize) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    let chars: Vec<char> = s.chars().collect();
-    for (i, &ch) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i).is_multiple_of(3) {
-            result.push(',');
-        }
-        result.push(ch);
-    }
-    result
-}
-
-const LOG_DIR: &str = "log";
+/// `--input sqlite://<path>` reads a single SQLite database instead of a directory.
+pub const SQLITE_URL_PREFIX: &str = "sqlite://";
 
 pub struct SetupResult {
     pub existing_ids: HashSet<String>,
     pub action_client: Option<ActionClient>,
     pub files_to_process: Vec<(PathBuf, String)>,
+    /// Archives in the input directory that could not be extracted.
+    pub skipped_files: Vec<String>,
     pub auth_client: Option<Arc<AuthClient>>,
+    /// Shared by every outbound client so they reuse one connection pool.
+    pub http_client: ReqwestClient,
+    /// Retry activity from the report fetch and every POST, for the summary.
+    pub retry_stats: Arc<RetryStats>,
+    /// Loaded from `TICKET_ID_MAP_PATH` when set.
+    pub ticket_id_map: Option<Arc<TicketIdMap>>,
+    /// Loaded from `AGENT_MAP_PATH` when set.
+    pub agent_map: Option<Arc<AgentMap>>,
+    /// Per-report breakdown of the existing-ID fetch; `None` when no report was fetched.
+    pub report_stats: Option<ReportFetchStats>,
+}
+
+/// Identifies one importer run in logs and artifact file names.
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    /// Short random ID carried by every log line of the run.
+    pub run_id: String,
+    pub started_at: DateTime<Utc>,
+    pub output_dir: PathBuf,
+}
+
+impl RunContext {
+    pub fn new(output_dir: PathBuf) -> Self {
+        let mut run_id = uuid::Uuid::new_v4().simple().to_string();
+        run_id.truncate(8);
+        Self {
+            run_id,
+            started_at: Utc::now(),
+            output_dir,
+        }
+    }
+
+    /// Path for a run artifact, e.g. `artifact_path(".log")` gives
+    /// `<output_dir>/2024-01-15_09-30-00_1a2b3c4d.log`.
+    pub fn artifact_path(&self, suffix: &str) -> PathBuf {
+        self.output_dir.join(format!(
+            "{}_{}{}",
+            self.started_at.format("%Y-%m-%d_%H-%M-%S"),
+            self.run_id,
+            suffix
+        ))
+    }
+}
+
+/// Gzips the `.log` files in `output_dir` last written more than `older_than` ago, other
+/// than `current_log`, into `<name>.log.gz`, and removes the originals. A file that fails
+/// is warned about and left as it was. Returns the number compressed.
+pub fn compress_old_logs(
+    output_dir: &Path,
+    current_log: &Path,
+    older_than: Duration,
+) -> anyhow::Result<usize> {
+    let mut compressed = 0;
+    for entry in std::fs::read_dir(output_dir)
+        .with_context(|| format!("Failed to read output directory: {}", output_dir.display()))?
+    {
+        let path = entry
+            .with_context(|| {
+                format!(
+                    "Failed to read entry in output directory: {}",
+                    output_dir.display()
+                )
+            })?
+            .path();
+        let is_old_log = path.extension() == Some(OsStr::new("log"))
+            && path != current_log
+            && std::fs::metadata(&path).is_ok_and(|meta| {
+                meta.is_file()
+                    && meta
+                        .modified()
+                        .is_ok_and(|modified| modified.elapsed().unwrap_or_default() >= older_than)
+            });
+        if !is_old_log {
+            continue;
+        }
+        let mut gz_name = path.file_name().unwrap_or_default().to_os_string();
+        gz_name.push(".gz");
+        let gz_path = path.with_file_name(gz_name);
+        let result = (|| -> anyhow::Result<()> {
+            let mut log = File::open(&path)?;
+            let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+            std::io::copy(&mut log, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::remove_file(&path)?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => compressed += 1,
+            Err(e) => {
+                if path.exists() {
+                    std::fs::remove_file(&gz_path).ok();
+                }
+                warn!("Failed to compress old log {}: {}", path.display(), e);
+            }
+        }
+    }
+    Ok(compressed)
 }
 
-pub fn setup_logging(only_parse: bool, log_level: tracing::Level) -> anyhow::Result<()> {
-    std::fs::create_dir_all(LOG_DIR)
-        .with_context(|| format!("Failed to create log directory: {}", LOG_DIR))?;
-    let timestamp_str = Utc::now().format("%Y-%m-%d_%H-%M-%S");
-    let log_file_path = format!("{}/{}.log", LOG_DIR, timestamp_str);
+pub fn setup_logging(
+    only_parse: bool,
+    log_level: tracing::Level,
+    compress_old_logs_days: Option<u64>,
+    run: &RunContext,
+) -> anyhow::Result<()> {
+    let output_dir = run.output_dir.as_path();
+    std::fs::create_dir_all(output_dir).with_context(|| {
+        format!(
+            "Failed to create output directory: {} (set OUTPUT_DIR or --output-dir to a writable location)",
+            output_dir.display()
+        )
+    })?;
+    let log_file_path = run.artifact_path(".log");
     let log_file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .open(&log_file_path)
-        .with_context(|| format!("Failed to open log file: {}", log_file_path))?;
+        .with_context(|| format!("Failed to open log file: {}", log_file_path.display()))?;
     let level_filter = if only_parse {
         LevelFilter::INFO
     } else {
@@ -65,26 +173,72 @@ pub fn setup_logging(only_parse: bool, log_level: tracing::Level) -> anyhow::Res
                 .with_timer(tracing_subscriber::fmt::time::ChronoUtc::rfc_3339()),
         )
         .init();
-    info!("Starting Halo action importer");
+    info!("Starting Halo action importer (run ID: {})", run.run_id);
     if only_parse {
         info!("Parse-only mode: will skip API calls");
     }
     info!("Configuration loaded successfully");
+    info!("Writing logs and artifacts to: {}", output_dir.display());
+    if let Some(days) = compress_old_logs_days {
+        let older_than = Duration::from_secs(days * 24 * 60 * 60);
+        match compress_old_logs(output_dir, &log_file_path, older_than) {
+            Ok(0) => {}
+            Ok(count) => info!("Compressed {} old log file(s)", count),
+            Err(e) => warn!("Failed to compress old logs: {:#}", e),
+        }
+    }
     Ok(())
 }
 
 pub async fn setup_auth_and_existing_ids(
     config: &Config,
     only_parse: bool,
-) -> anyhow::Result<(Option<Arc<AuthClient>>, HashSet<String>)> {
-    let auth_client = Arc::new(AuthClient::new(config.clone()));
+    http_client: &ReqwestClient,
+    retry_stats: Arc<RetryStats>,
+) -> anyhow::Result<(Option<Arc<AuthClient>>, HashSet<String>, ReportFetchStats)> {
+    let auth_client = Arc::new(AuthClient::new(config.clone(), http_client.clone()));
     let _token = auth_client
         .get_valid_token()
         .await
         .context("Failed to authenticate with Halo API")?;
     info!("Authentication successful");
-    let report_client = ReportClient::new(config.clone(), auth_client.clone());
-    let ids = report_client
+    // Checked before the report fetch, which can take a long time
+    let field_check_value = match config.field_check_action {
+        Some((ticket_id, action_id)) => {
+            let action_client = ActionClient::new(
+                config.clone(),
+                http_client.clone(),
+                auth_client.clone(),
+                retry_stats.clone(),
+            );
+            let action = action_client
+                .get_action(ticket_id, action_id)
+                .await
+                .context("Failed to read the field check action from Halo")?;
+            let value =
+                custom_field_value(&action, config.action_id_custom_field_id).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Custom field check failed for action {} on ticket {}: {}",
+                        action_id,
+                        ticket_id,
+                        e
+                    )
+                })?;
+            info!(
+                "Custom field check passed: action {} holds action ID '{}' in custom field {}",
+                action_id, value, config.action_id_custom_field_id
+            );
+            Some(value)
+        }
+        None => None,
+    };
+    let report_client = ReportClient::new(
+        config.clone(),
+        http_client.clone(),
+        auth_client.clone(),
+        retry_stats,
+    );
+    let (ids, report_stats) = report_client
         .get_existing_action_ids()
         .await
         .context("Failed to fetch existing action IDs from report")?;
@@ -92,21 +246,98 @@ pub async fn setup_auth_and_existing_ids(
         "Found {} existing action IDs to skip",
         format_number(ids.len())
     );
+    if let Some(value) = &field_check_value
+        && !ids.is_empty()
+        && !ids.contains(value)
+    {
+        warn!(
+            "The field check action's ID '{}' is not in the existing ID reports, so actions imported like it may be posted again on the next run. Check that the reports read custom field {}",
+            value, config.action_id_custom_field_id
+        );
+    }
+    if ids.is_empty() {
+        // Zero IDs usually means a misconfigured report, and every row would be re-posted
+        if only_parse || config.allow_empty_report {
+            warn!(
+                "Reports returned no existing action IDs: every row will be treated as new. Check ACTION_IDS_RESOURCE_PATH if this is unexpected"
+            );
+        } else {
+            anyhow::bail!(
+                "Reports returned no existing action IDs, so every row would be posted again. Check ACTION_IDS_RESOURCE_PATH, or pass --allow-empty-report (ALLOW_EMPTY_REPORT=true) if this is a first import"
+            );
+        }
+    }
     if only_parse {
         info!(
             "Parse-only mode: existing IDs fetched successfully, will skip API calls for imports"
         );
-        return Ok((None, ids));
+        return Ok((None, ids, report_stats));
     }
-    Ok((Some(auth_client), ids))
+    // Actions posted from here on aren't in the cached reports
+    report_client.clear_cache();
+    Ok((Some(auth_client), ids, report_stats))
 }
 
-pub fn discover_files(input_path: &str) -> anyhow::Result<Vec<(PathBuf, String)>> {
+/// The database a `sqlite://` input points at, as the only file to process.
+#[cfg(feature = "sqlite")]
+fn sqlite_input(path: &str) -> anyhow::Result<DiscoveredFiles> {
+    let file_path = PathBuf::from(path);
+    if !file_path.is_file() {
+        anyhow::bail!("SQLite database '{}' does not exist", path);
+    }
+    if InputKind::of(&file_path) != Some(InputKind::Sqlite) {
+        anyhow::bail!(
+            "SQLite database '{}' must end in .db, .sqlite, or .sqlite3",
+            path
+        );
+    }
+    let file_name = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    Ok(DiscoveredFiles {
+        files: vec![(file_path, file_name)],
+        skipped: Vec::new(),
+    })
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_input(path: &str) -> anyhow::Result<DiscoveredFiles> {
+    anyhow::bail!(
+        "Can't read SQLite database '{}': this build has no SQLite support. Rebuild with `--features sqlite`",
+        path
+    )
+}
+
+/// The input files found by [`discover_files`].
+#[derive(Debug, Default)]
+pub struct DiscoveredFiles {
+    pub files: Vec<(PathBuf, String)>,
+    /// Archives that could not be extracted, as entries for the summary's skipped-file list.
+    pub skipped: Vec<String>,
+}
+
+/// Lists the CSV and Excel files in `input_path`, sorted by `order`. A `sqlite://` input
+/// names a single SQLite database instead.
+///
+/// A missing directory is an error. An empty directory, or one holding only unsupported
+/// files, is logged as a warning and yields no files so scheduled runs can exit cleanly. A
+/// zip archive that can't be extracted is skipped like any other unreadable file.
+pub fn discover_files(input_path: &str, order: FileOrder) -> anyhow::Result<DiscoveredFiles> {
+    if let Some(database) = input_path.strip_prefix(SQLITE_URL_PREFIX) {
+        return sqlite_input(database);
+    }
     let input_dir = Path::new(input_path);
     if !input_dir.exists() {
         anyhow::bail!("Input directory '{}' does not exist", input_path);
     }
+    if !input_dir.is_dir() {
+        anyhow::bail!("Input path '{}' is not a directory", input_path);
+    }
     let mut files_to_process = Vec::new();
+    let mut skipped = Vec::new();
+    let mut ignored_extensions: BTreeMap<String, usize> = BTreeMap::new();
     for entry in std::fs::read_dir(input_dir)
         .with_context(|| format!("Failed to read input directory: {}", input_path))?
     {
@@ -116,45 +347,289 @@ pub fn discover_files(input_path: &str) -> anyhow::Result<Vec<(PathBuf, String)>
         if !file_path.is_file() {
             continue;
         }
-        if let Some(ext) = file_path.extension().and_then(OsStr::to_str) {
-            let ext_lower = ext.to_lowercase();
-            if ext_lower == "csv" || ext_lower == "xlsx" || ext_lower == "xls" {
-                let file_name = file_path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                files_to_process.push((file_path, file_name));
+        let file_name = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        match InputKind::of(&file_path) {
+            // Each spreadsheet in an archive is processed as its own file
+            Some(InputKind::Archive) => match extract_archive(&file_path) {
+                Ok(extracted) => {
+                    for ext in extracted.ignored_extensions {
+                        *ignored_extensions.entry(ext).or_insert(0) += 1;
+                    }
+                    files_to_process.extend(extracted.files);
+                }
+                Err(e) => {
+                    error!("Failed to read file {:?}: {:#}", file_name, e);
+                    skipped.push(format!("{:?}: {:#}", file_name, e));
+                }
+            },
+            Some(_) => files_to_process.push((file_path, file_name)),
+            None => {
+                let label = file_path
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .map(|ext| format!(".{}", ext.to_lowercase()))
+                    .unwrap_or_else(|| "(no extension)".to_string());
+                *ignored_extensions.entry(label).or_insert(0) += 1;
             }
         }
     }
-    Ok(files_to_process)
+    // read_dir order is filesystem-dependent; sort so sheet numbering is reproducible
+    match order {
+        FileOrder::Name => files_to_process.sort_by(|a, b| a.1.cmp(&b.1)),
+        FileOrder::Modified => files_to_process.sort_by_cached_key(|(path, name)| {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            (modified, name.clone())
+        }),
+    }
+    let ignored_summary = ignored_extensions
+        .iter()
+        .map(|(ext, count)| format!("{} ({})", ext, format_number(*count)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if files_to_process.is_empty() && ignored_extensions.is_empty() && skipped.is_empty() {
+        warn!(
+            "Input directory '{}' is empty. Nothing to process.",
+            input_path
+        );
+    } else if files_to_process.is_empty() && !ignored_extensions.is_empty() {
+        warn!(
+            "Input directory '{}' has no CSV or Excel files. Nothing to process. Ignored files: {}",
+            input_path, ignored_summary
+        );
+    } else if !ignored_extensions.is_empty() {
+        warn!(
+            "Ignoring unsupported files in '{}': {}",
+            input_path, ignored_summary
+        );
+    }
+    Ok(DiscoveredFiles {
+        files: files_to_process,
+        skipped,
+    })
+}
+
+/// Drops the files ordered before `name`, so a batch that stopped partway can resume at the
+/// file it stopped on. A name that isn't among `files` is an error.
+pub fn continue_from_file(
+    files: Vec<(PathBuf, String)>,
+    name: &str,
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let Some(start) = files.iter().position(|(_, file_name)| file_name == name) else {
+        anyhow::bail!(
+            "--continue-from-file '{}' is not one of the {} file(s) found in the input directory",
+            name,
+            files.len()
+        );
+    };
+    if start > 0 {
+        info!(
+            "Resuming at {}: skipping {} file(s) before it",
+            name,
+            format_number(start)
+        );
+        for (_, file_name) in &files[..start] {
+            info!("  Skipped before resume point: {}", file_name);
+        }
+    }
+    Ok(files.into_iter().skip(start).collect())
+}
+
+type LoadedMaps = (Option<Arc<TicketIdMap>>, Option<Arc<AgentMap>>);
+
+/// Loads `TICKET_ID_MAP_PATH` and `AGENT_MAP_PATH`, when set.
+pub fn load_maps(config: &Config) -> anyhow::Result<LoadedMaps> {
+    let ticket_id_map = match &config.ticket_id_map_path {
+        Some(path) => {
+            let map = TicketIdMap::load(path, config.ticket_id_map_pass_through)?;
+            info!(
+                "Loaded {} ticket ID mapping(s) from {}{}",
+                format_number(map.len()),
+                path.display(),
+                if config.ticket_id_map_pass_through {
+                    " (unmapped IDs pass through)"
+                } else {
+                    ""
+                }
+            );
+            Some(Arc::new(map))
+        }
+        None => None,
+    };
+    let agent_map = match &config.agent_map_path {
+        Some(path) => {
+            let map = AgentMap::load(path, config.agent_map_pass_through)?;
+            info!(
+                "Loaded {} agent mapping(s) from {}{}",
+                format_number(map.len()),
+                path.display(),
+                if config.agent_map_pass_through {
+                    " (unmapped names pass through)"
+                } else {
+                    ""
+                }
+            );
+            Some(Arc::new(map))
+        }
+        None => None,
+    };
+    Ok((ticket_id_map, agent_map))
 }
 
 pub async fn setup(
     config: &Config,
     only_parse: bool,
     input_path: &str,
+    continue_from: Option<&str>,
 ) -> anyhow::Result<SetupResult> {
     // Check for files FIRST before doing expensive ID fetching
-    let files_to_process = discover_files(input_path)?;
+    let DiscoveredFiles {
+        files: mut files_to_process,
+        skipped: skipped_files,
+    } = discover_files(input_path, config.file_order)?;
+    if let Some(name) = continue_from {
+        files_to_process = continue_from_file(files_to_process, name)?;
+    }
+    let http_client = build_http_client(config)?;
     if files_to_process.is_empty() {
-        anyhow::bail!(
-            "No CSV or Excel files found in input directory: {}. Nothing to process.",
-            input_path
+        return Ok(SetupResult {
+            existing_ids: HashSet::new(),
+            action_client: None,
+            files_to_process,
+            skipped_files,
+            auth_client: None,
+            http_client,
+            retry_stats: Arc::new(RetryStats::default()),
+            ticket_id_map: None,
+            agent_map: None,
+            report_stats: None,
+        });
+    }
+
+    // Load the maps before fetching IDs so a bad map fails fast
+    let (ticket_id_map, agent_map) = load_maps(config)?;
+
+    let retry_stats = Arc::new(RetryStats::default());
+    if config.offline {
+        info!(
+            "Offline mode: skipping authentication and the existing action ID report, every row is treated as new"
         );
+        return Ok(SetupResult {
+            existing_ids: HashSet::new(),
+            action_client: None,
+            files_to_process,
+            skipped_files,
+            auth_client: None,
+            http_client,
+            retry_stats,
+            ticket_id_map,
+            agent_map,
+            report_stats: None,
+        });
     }
-    
+
     // Now fetch existing IDs (this can take a long time)
-    let (auth_client, existing_ids) = setup_auth_and_existing_ids(config, only_parse).await?;
-    let action_client = auth_client
-        .as_ref()
-        .map(|auth| ActionClient::new(config.clone(), auth.clone()));
-    
+    let (auth_client, existing_ids, report_stats) =
+        setup_auth_and_existing_ids(config, only_parse, &http_client, retry_stats.clone()).await?;
+    let action_client = auth_client.as_ref().map(|auth| {
+        ActionClient::new(
+            config.clone(),
+            http_client.clone(),
+            auth.clone(),
+            retry_stats.clone(),
+        )
+    });
+
     Ok(SetupResult {
         existing_ids,
         action_client,
         files_to_process,
+        skipped_files,
         auth_client,
+        http_client,
+        retry_stats,
+        ticket_id_map,
+        agent_map,
+        report_stats: Some(report_stats),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn discover_files_distinguishes_missing_empty_and_unsupported() {
+        let dir = TempPath::new("discover");
+        let dir_str = dir.to_str().unwrap().to_string();
+        assert!(discover_files(&dir_str, FileOrder::Name).is_err());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(
+            discover_files(&dir_str, FileOrder::Name)
+                .unwrap()
+                .files
+                .is_empty()
+        );
+
+        std::fs::write(dir.join("notes.txt"), "not an import").unwrap();
+        assert!(
+            discover_files(&dir_str, FileOrder::Name)
+                .unwrap()
+                .files
+                .is_empty()
+        );
+
+        std::fs::write(dir.join("b_actions.CSV"), "").unwrap();
+        std::fs::write(dir.join("a_actions.xlsx"), "").unwrap();
+        std::fs::write(dir.join("c_actions.ods"), "").unwrap();
+        // A corrupt archive is skipped, not fatal
+        std::fs::write(dir.join("broken.zip"), "not a zip").unwrap();
+        let discovered = discover_files(&dir_str, FileOrder::Name).unwrap();
+        assert_eq!(discovered.skipped.len(), 1);
+        assert!(discovered.skipped[0].starts_with("\"broken.zip\""));
+        let files = discovered.files;
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].1, "a_actions.xlsx");
+        assert_eq!(files[1].1, "b_actions.CSV");
+        assert_eq!(files[2].1, "c_actions.ods");
+
+        let resumed = continue_from_file(files.clone(), "b_actions.CSV").unwrap();
+        assert_eq!(resumed.len(), 2);
+        assert_eq!(resumed[0].1, "b_actions.CSV");
+        assert!(continue_from_file(files, "missing.csv").is_err());
+    }
+    #[test]
+    fn compresses_earlier_logs_but_not_the_current_one() {
+        use std::io::Read;
+
+        let dir = TempPath::dir("old_logs");
+        let old_log = dir.join("2024-01-14_09-30-00_5e6f7a8b.log");
+        let current_log = dir.join("2024-01-15_09-30-00_1a2b3c4d.log");
+        std::fs::write(&old_log, "earlier run").unwrap();
+        std::fs::write(&current_log, "this run").unwrap();
+        std::fs::write(dir.join("2024-01-14_09-30-00_5e6f7a8b_imported.csv"), "").unwrap();
+
+        let kept = compress_old_logs(&dir, &current_log, Duration::from_secs(3600)).unwrap();
+        let compressed = compress_old_logs(&dir, &current_log, Duration::ZERO).unwrap();
+        let mut contents = String::new();
+        let gz = File::open(dir.join("2024-01-14_09-30-00_5e6f7a8b.log.gz"));
+        if let Ok(gz) = gz {
+            flate2::read::GzDecoder::new(gz)
+                .read_to_string(&mut contents)
+                .unwrap();
+        }
+        let old_log_left = old_log.exists();
+        let current_log_left = current_log.exists();
+
+        assert_eq!(kept, 0);
+        assert_eq!(compressed, 1);
+        assert_eq!(contents, "earlier run");
+        assert!(!old_log_left);
+        assert!(current_log_left);
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+/// Stop reason recorded in the summary and run notification after a SIGTERM.
+pub const SHUTDOWN_REASON: &str = "SIGTERM received";
+
+/// A request to end the run early, such as a SIGTERM from an orchestrator shutting the
+/// pod down.
+///
+/// Once requested, no new rows or files are started. The batch being built is still
+/// posted, and the run ends the way a `MAX_RUN_SECS` stop does: rejects file, summary, and
+/// notification.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once a shutdown is requested, at once if it already was.
+    pub async fn wait(&self) {
+        loop {
+            // Created before the check, so a request in between isn't missed
+            let notified = self.notify.notified();
+            if self.requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Requests a shutdown on the first SIGTERM. A second one exits at once, for when the
+    /// rows in flight take longer than the operator will wait.
+    #[cfg(unix)]
+    pub fn listen_for_sigterm(self: &std::sync::Arc<Self>) -> anyhow::Result<()> {
+        use anyhow::Context;
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut sigterm =
+            signal(SignalKind::terminate()).context("Failed to listen for SIGTERM")?;
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            if sigterm.recv().await.is_none() {
+                return;
+            }
+            tracing::warn!(
+                "SIGTERM received: finishing the rows in flight, then writing the summary. Send it again to exit at once"
+            );
+            shutdown.request();
+            if sigterm.recv().await.is_some() {
+                tracing::error!("Second SIGTERM received: exiting without a summary");
+                std::process::exit(143);
+            }
+        });
+        Ok(())
+    }
+
+    /// SIGTERM only exists on unix; elsewhere the run ends when it is killed.
+    #[cfg(not(unix))]
+    pub fn listen_for_sigterm(self: &std::sync::Arc<Self>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn wait_resolves_once_requested() {
+        let shutdown = Arc::new(Shutdown::default());
+        assert!(!shutdown.requested());
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait().await }
+        });
+        shutdown.request();
+        waiter.await.unwrap();
+        assert!(shutdown.requested());
+        // Already requested, so this doesn't wait
+        shutdown.wait().await;
+    }
+}
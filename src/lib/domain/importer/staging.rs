@@ -0,0 +1,158 @@
+use crate::config::Config;
+use crate::domain::importer::confirm::ask_yes_no;
+use crate::domain::importer::error::FailedActions;
+use crate::domain::importer::processor::{ProcessOptions, format_number, process_input_file};
+use crate::outbound::client::{action::ActionClient, auth::AuthClient, http::RetryStats};
+use anyhow::Context;
+use reqwest::Client as ReqwestClient;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Failures listed when the staging phase ends; the rest are only in the log.
+const FAILURES_SHOWN: usize = 5;
+
+/// Posts the first `STAGING_SAMPLE` rows that would be imported to `STAGING_BASE_URL`, then
+/// asks the operator to check them there before the real import goes ahead.
+///
+/// Rows are picked and skipped exactly as in the real run, against the production
+/// existing-ID reports, so the staged rows are the ones the real import starts with. With
+/// `assume_yes` (`--yes`) there is no prompt, and the real import only goes ahead when
+/// nothing failed in staging. Does nothing when `STAGING_BASE_URL` isn't set.
+pub async fn run_staging_phase(
+    config: &Config,
+    files_to_process: &[(PathBuf, String)],
+    existing_ids: &HashSet<String>,
+    http_client: &ReqwestClient,
+    retry_stats: Arc<RetryStats>,
+    options: &ProcessOptions,
+    assume_yes: bool,
+) -> anyhow::Result<()> {
+    let Some(staging) = config.staging()? else {
+        return Ok(());
+    };
+    info!(
+        "Staging phase: posting the first {} action(s) to {}",
+        format_number(config.staging_sample),
+        staging.base_resource_url
+    );
+    let auth_client = Arc::new(AuthClient::new(staging.clone(), http_client.clone()));
+    auth_client
+        .get_valid_token()
+        .await
+        .context("Failed to authenticate with the staging Halo API")?;
+    let action_client = ActionClient::new(
+        staging.clone(),
+        http_client.clone(),
+        auth_client,
+        retry_stats,
+    );
+
+    let total_sheets = files_to_process.len();
+    let mut sheet_times = Vec::new();
+    let mut rows_left = config.staging_sample;
+    let mut imported = 0;
+    let mut failed = FailedActions::default();
+    for (sheet_num, (file_path, file_name)) in files_to_process.iter().enumerate() {
+        if rows_left == 0 {
+            break;
+        }
+        // Nothing from the staging phase belongs in the real run's manifest or hooks
+        let staging_options = ProcessOptions {
+            max_rows: Some(rows_left),
+            manifest: None,
+            rejects: None,
+            hooks: Default::default(),
+            failure_threshold: None,
+            estimator: None,
+            shutdown: None,
+            ..options.clone()
+        };
+        let Some(result) = process_input_file(
+            file_path,
+            file_name,
+            existing_ids,
+            Some(&action_client),
+            &mut sheet_times,
+            sheet_num + 1,
+            total_sheets,
+            &staging_options,
+        )
+        .await
+        else {
+            continue;
+        };
+        let stats = match result {
+            Ok(result) => result.stats,
+            Err(e) => {
+                // The real run reports unreadable files; staging only needs rows to post
+                warn!(
+                    "Staging phase skipped unreadable file {:?}: {}",
+                    file_name, e
+                );
+                continue;
+            }
+        };
+        imported += stats.imported;
+        failed.append(stats.failed);
+        rows_left = rows_left.saturating_sub(stats.posted);
+        if let Some(reason) = stats.aborted {
+            error!("Staging phase stopped early: {}", reason);
+            break;
+        }
+        if stats.deadline_reached {
+            break;
+        }
+    }
+
+    info!(
+        "Staging phase finished: {} imported, {} failed on {}",
+        format_number(imported),
+        format_number(failed.len()),
+        staging.base_resource_url
+    );
+    for failure in failed.kept().iter().take(FAILURES_SHOWN) {
+        error!(
+            "Staging failure for action ID {}: {}",
+            failure.action_id, failure.error
+        );
+    }
+    if failed.len() > FAILURES_SHOWN {
+        error!(
+            "... and {} more staging failure(s); see the log above",
+            format_number(failed.len() - FAILURES_SHOWN)
+        );
+    }
+
+    if assume_yes {
+        if !failed.is_empty() {
+            anyhow::bail!(
+                "{} action(s) failed in staging, so the import into {} was not started",
+                format_number(failed.len()),
+                config.base_resource_url
+            );
+        }
+        info!("Continuing to the production import (--yes)");
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout();
+    writeln!(stdout)?;
+    writeln!(
+        stdout,
+        "Posted {} action(s) to staging at {} ({} failed).",
+        format_number(imported),
+        staging.base_resource_url,
+        format_number(failed.len())
+    )?;
+    let question = format!(
+        "Check them in staging. Continue with the full import into {}?",
+        config.base_resource_url
+    );
+    if !ask_yes_no(&question)? {
+        anyhow::bail!("Import cancelled by operator after the staging phase");
+    }
+    info!("Production import confirmed by operator after staging");
+    Ok(())
+}
@@ -1,26 +1,174 @@
+use crate::domain::importer::processor::format_number;
+use crate::inbound::client::ReportFetchStats;
+use crate::outbound::client::{http::RetryCounts, webhook::RunNotification};
+use std::collections::BTreeMap;
 use tracing::{info, warn};
 
-fn format_number(n: usize) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    let chars: Vec<char> = s.chars().collect();
-    for (i, &ch) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i).is_multiple_of(3) {
-            result.push(',');
+/// Upper bound on retained per-row latency samples so huge runs stay memory-bounded.
+const MAX_LATENCY_SAMPLES: usize = 100_000;
+
+/// Bounded collection of per-row POST latencies used for percentile reporting.
+///
+/// Once the cap is reached every other retained sample is dropped and the
+/// sampling stride doubles, so the retained set stays evenly spread across the run.
+#[derive(Debug, Clone)]
+pub struct LatencySamples {
+    samples: Vec<f64>,
+    stride: usize,
+    seen: usize,
+}
+
+impl Default for LatencySamples {
+    fn default() -> Self {
+        Self {
+            samples: Vec::new(),
+            stride: 1,
+            seen: 0,
+        }
+    }
+}
+
+impl LatencySamples {
+    pub fn push(&mut self, latency_secs: f64) {
+        self.seen += 1;
+        if !self.seen.is_multiple_of(self.stride) {
+            return;
+        }
+        self.samples.push(latency_secs);
+        if self.samples.len() >= MAX_LATENCY_SAMPLES {
+            let mut idx = 0;
+            self.samples.retain(|_| {
+                idx += 1;
+                idx % 2 == 0
+            });
+            self.stride *= 2;
+        }
+    }
+
+    pub fn extend(&mut self, latencies: &[f64]) {
+        for &latency in latencies {
+            self.push(latency);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn sorted(&self) -> Vec<f64> {
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        sorted
+    }
+}
+
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// How the end-of-run summary is reported (`--summary-format`). The summary is always
+/// logged; the other formats add a machine-readable copy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SummaryFormat {
+    #[default]
+    Text,
+    /// One JSON object, printed as the last line on stdout.
+    Json,
+    /// One row appended to `history.csv` in the output directory.
+    Csv,
+}
+
+impl SummaryFormat {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            other => anyhow::bail!(
+                "invalid value '{}' for --summary-format. must be one of: text, json, csv",
+                other
+            ),
         }
-        result.push(ch);
     }
-    result
 }
 
 pub struct ImportSummary {
     pub total_processed: usize,
     pub total_imported: usize,
     pub total_skipped: usize,
+    /// Rows left out by `SAMPLE_RATE`.
+    pub total_sampled_out: usize,
+    /// Rows left out for an empty note (`SKIP_EMPTY_NOTE`).
+    pub total_skipped_empty_note: usize,
     pub total_failed: usize,
+    pub failures_by_category: BTreeMap<&'static str, usize>,
+    /// Failures past `MAX_FAILURES_KEPT`, counted in `total_failed` but not held in memory.
+    pub failures_not_kept: usize,
     pub skipped_files: Vec<String>,
     pub total_runtime_secs: f64,
     pub sheet_times: Vec<f64>,
+    pub row_latencies: LatencySamples,
+    pub retries: RetryCounts,
+    /// Why the run stopped before processing every file, when it wasn't a failure.
+    pub stopped_early: Option<String>,
+    /// Per-report breakdown of the existing-ID fetch, when reports were fetched.
+    pub report_stats: Option<ReportFetchStats>,
+}
+
+impl ImportSummary {
+    /// Whether the run finished with nothing to do: every row already existed, and nothing
+    /// failed, was unreadable, or was left for later.
+    pub fn nothing_to_import(&self) -> bool {
+        self.total_imported == 0
+            && self.total_failed == 0
+            && self.total_skipped + self.total_skipped_empty_note == self.total_processed
+            && self.skipped_files.is_empty()
+            && self.stopped_early.is_none()
+    }
+
+    /// `completed`, `completed_with_failures`, `stopped_early`, or `aborted`.
+    pub fn status(&self, aborted: Option<&str>) -> &'static str {
+        if aborted.is_some() {
+            "aborted"
+        } else if self.stopped_early.is_some() {
+            "stopped_early"
+        } else if self.total_failed > 0 || !self.skipped_files.is_empty() {
+            "completed_with_failures"
+        } else {
+            "completed"
+        }
+    }
+
+    /// The webhook payload for this run; `aborted` is the failure that stopped it, if any.
+    /// Also the run's `--summary-format` JSON and CSV history row.
+    pub fn notification(
+        &self,
+        run_id: &str,
+        only_parse: bool,
+        aborted: Option<&str>,
+    ) -> RunNotification {
+        RunNotification {
+            run_id: run_id.to_string(),
+            status: self.status(aborted),
+            only_parse,
+            processed: self.total_processed,
+            imported: self.total_imported,
+            skipped: self.total_skipped,
+            sampled_out: self.total_sampled_out,
+            skipped_empty_note: self.total_skipped_empty_note,
+            failed: self.total_failed,
+            failures_by_category: self.failures_by_category.clone(),
+            unreadable_files: self.skipped_files.len(),
+            runtime_secs: self.total_runtime_secs,
+            reason: aborted
+                .map(str::to_string)
+                .or_else(|| self.stopped_early.clone()),
+        }
+    }
 }
 
 pub fn log_summary(summary: ImportSummary, only_parse: bool) {
@@ -33,6 +181,18 @@ pub fn log_summary(summary: ImportSummary, only_parse: bool) {
         "Actions skipped (already exist): {}",
         format_number(summary.total_skipped)
     );
+    if summary.total_skipped_empty_note > 0 {
+        info!(
+            "Actions skipped (empty note): {}",
+            format_number(summary.total_skipped_empty_note)
+        );
+    }
+    if summary.total_sampled_out > 0 {
+        info!(
+            "Actions sampled out (SAMPLE_RATE): {}",
+            format_number(summary.total_sampled_out)
+        );
+    }
     info!(
         "Actions successfully imported: {}",
         format_number(summary.total_imported)
@@ -41,6 +201,28 @@ pub fn log_summary(summary: ImportSummary, only_parse: bool) {
         "Actions failed to import: {}",
         format_number(summary.total_failed)
     );
+    for (category, count) in &summary.failures_by_category {
+        info!("  {} failures: {}", category, format_number(*count));
+    }
+    if summary.failures_not_kept > 0 {
+        warn!(
+            "Failure list truncated at MAX_FAILURES_KEPT: {} failure(s) past it were counted but not held in memory, and went straight to the rejects file when one was written",
+            format_number(summary.failures_not_kept)
+        );
+    }
+    if let Some(reason) = &summary.stopped_early {
+        warn!("Run stopped early: {}", reason);
+    }
+    if summary.nothing_to_import() {
+        if summary.total_processed == 0 {
+            info!("No new actions to import: the input files had no rows");
+        } else {
+            info!(
+                "No new actions to import: all {} rows already existed",
+                format_number(summary.total_processed)
+            );
+        }
+    }
     if !summary.skipped_files.is_empty() {
         warn!(
             "Files that could not be read: {}",
@@ -55,6 +237,9 @@ pub fn log_summary(summary: ImportSummary, only_parse: bool) {
             format_number(summary.total_processed)
         );
     }
+    if let Some(report_stats) = &summary.report_stats {
+        log_report_stats(report_stats);
+    }
     if summary.total_processed > 0 {
         let total_runtime = summary.total_runtime_secs;
         let time_per_entry = total_runtime / summary.total_processed as f64;
@@ -79,5 +264,110 @@ pub fn log_summary(summary: ImportSummary, only_parse: bool) {
         if !summary.sheet_times.is_empty() {
             info!("Average time per sheet: {:.2}s", avg_sheet_time);
         }
+        if !summary.row_latencies.is_empty() {
+            let sorted = summary.row_latencies.sorted();
+            let avg_latency = sorted.iter().sum::<f64>() / sorted.len() as f64;
+            info!(
+                "Row latency: avg {:.3}s | p50 {:.3}s | p95 {:.3}s | p99 {:.3}s ({} samples)",
+                avg_latency,
+                percentile(&sorted, 50.0),
+                percentile(&sorted, 95.0),
+                percentile(&sorted, 99.0),
+                format_number(sorted.len())
+            );
+        }
+        let retries = summary.retries;
+        info!(
+            "Retries: {} total ({} after 401 token refresh, {} after 504 wait, {:.0}s spent waiting on 504s)",
+            format_number(retries.total_retries()),
+            format_number(retries.auth_refreshes),
+            format_number(retries.gateway_timeouts),
+            retries.gateway_wait_secs
+        );
+    }
+}
+
+fn log_report_stats(stats: &ReportFetchStats) {
+    info!(
+        "Existing ID reports: {} distinct IDs from {} report(s) in {:.1}s",
+        format_number(stats.total_ids),
+        stats.reports.len(),
+        stats.duration_secs
+    );
+    for (idx, report) in stats.reports.iter().enumerate() {
+        let chunks = if report.from_cache {
+            " from cache".to_string()
+        } else if report.chunks > 1 {
+            format!(" over {} chunks", report.chunks)
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "  Report {}: {} IDs ({} new, {} already seen) in {:.1}s{} | {}",
+            idx + 1,
+            format_number(report.ids_listed),
+            format_number(report.new_ids),
+            format_number(report.duplicate_ids()),
+            report.duration_secs,
+            chunks,
+            report.url
+        );
+        // A report adding nothing is usually misconfigured or fully overlapped by another
+        if report.new_ids == 0 {
+            warn!("{}", line);
+        } else {
+            info!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let mut samples = LatencySamples::default();
+        samples.extend(&(1..=100).map(|n| n as f64).collect::<Vec<_>>());
+        let sorted = samples.sorted();
+        assert_eq!(percentile(&sorted, 50.0), 50.0);
+        assert_eq!(percentile(&sorted, 95.0), 95.0);
+        assert_eq!(percentile(&sorted, 99.0), 99.0);
+    }
+
+    #[test]
+    fn nothing_to_import_only_when_every_row_already_existed() {
+        let summary = |processed, imported, skipped, failed| ImportSummary {
+            total_processed: processed,
+            total_imported: imported,
+            total_skipped: skipped,
+            total_sampled_out: 0,
+            total_skipped_empty_note: 0,
+            total_failed: failed,
+            failures_by_category: BTreeMap::new(),
+            failures_not_kept: 0,
+            skipped_files: Vec::new(),
+            total_runtime_secs: 1.0,
+            sheet_times: Vec::new(),
+            row_latencies: LatencySamples::default(),
+            retries: RetryCounts::default(),
+            stopped_early: None,
+            report_stats: None,
+        };
+        assert!(summary(5, 0, 5, 0).nothing_to_import());
+        assert!(!summary(5, 1, 4, 0).nothing_to_import());
+        assert!(!summary(5, 0, 4, 1).nothing_to_import());
+        let mut unreadable = summary(5, 0, 5, 0);
+        unreadable.skipped_files.push("bad.xlsx".to_string());
+        assert!(!unreadable.nothing_to_import());
+    }
+
+    #[test]
+    fn latency_samples_stay_bounded() {
+        let mut samples = LatencySamples::default();
+        for n in 0..(MAX_LATENCY_SAMPLES * 3) {
+            samples.push(n as f64);
+        }
+        assert!(samples.samples.len() < MAX_LATENCY_SAMPLES);
     }
 }
@@ -0,0 +1,116 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Source ticket reference to Halo request ID mapping loaded from `TICKET_ID_MAP_PATH`.
+///
+/// The file is a two-column CSV of `source_id,halo_id`. Source IDs may be alphanumeric
+/// (e.g. `INC-1042`); a first row whose `halo_id` isn't numeric is treated as a header.
+/// With `pass_through` set, unmapped numeric IDs are kept as-is instead of failing the row.
+#[derive(Debug, Clone)]
+pub struct TicketIdMap {
+    ids: HashMap<String, u32>,
+    pass_through: bool,
+}
+
+/// Numeric references compare as numbers, so `0100` in the map matches `100` in a file.
+fn map_key(source_id: &str) -> String {
+    match source_id.parse::<u32>() {
+        Ok(number) => number.to_string(),
+        Err(_) => source_id.to_string(),
+    }
+}
+
+impl TicketIdMap {
+    pub fn load(path: &Path, pass_through: bool) -> anyhow::Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .with_context(|| format!("Failed to open ticket ID map: {}", path.display()))?;
+        let mut ids = HashMap::new();
+        for (idx, record) in rdr.records().enumerate() {
+            let line = idx + 1;
+            let record = record.with_context(|| {
+                format!(
+                    "Failed to read line {} of ticket ID map: {}",
+                    line,
+                    path.display()
+                )
+            })?;
+            let source = record.get(0).map(str::trim).unwrap_or_default();
+            let halo = record.get(1).map(str::trim).unwrap_or_default();
+            if source.is_empty() && halo.is_empty() {
+                continue;
+            }
+            let halo_id = match halo.parse::<u32>() {
+                Ok(halo_id) if !source.is_empty() => halo_id,
+                _ if line == 1 => continue,
+                _ => anyhow::bail!(
+                    "invalid ticket ID map entry on line {} of {}: expected source_id,halo_id but got '{},{}'",
+                    line,
+                    path.display(),
+                    source,
+                    halo
+                ),
+            };
+            if let Some(previous) = ids.insert(map_key(source), halo_id)
+                && previous != halo_id
+            {
+                anyhow::bail!(
+                    "conflicting ticket ID map entries for source ID {} in {}: {} and {}",
+                    source,
+                    path.display(),
+                    previous,
+                    halo_id
+                );
+            }
+        }
+        Ok(Self { ids, pass_through })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The Halo ID for `source_id`, or `None` when it is unmapped and pass-through is off
+    /// or can't apply because the reference isn't numeric.
+    pub fn lookup(&self, source_id: &str) -> Option<u32> {
+        let source_id = source_id.trim();
+        match self.ids.get(&map_key(source_id)) {
+            Some(halo_id) => Some(*halo_id),
+            None if self.pass_through => source_id.parse().ok(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn loads_map_with_header_and_honours_pass_through() {
+        let path = TempPath::new("ticket_map.csv");
+        std::fs::write(
+            &path,
+            "source_id,halo_id\n100,2997\n101,2998\nINC-1042,2999\n",
+        )
+        .unwrap();
+        let strict = TicketIdMap::load(&path, false).unwrap();
+        let lenient = TicketIdMap::load(&path, true).unwrap();
+
+        assert_eq!(strict.len(), 3);
+        assert_eq!(strict.lookup("100"), Some(2997));
+        assert_eq!(strict.lookup("0101"), Some(2998));
+        assert_eq!(strict.lookup("INC-1042"), Some(2999));
+        assert_eq!(strict.lookup("555"), None);
+        assert_eq!(lenient.lookup("555"), Some(555));
+        assert_eq!(lenient.lookup("INC-9"), None);
+    }
+}
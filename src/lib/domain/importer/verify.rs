@@ -0,0 +1,247 @@
+use crate::domain::models::action_object::{ActionObject, REQUIRED_FIELDS};
+#[cfg(feature = "sqlite")]
+use crate::inbound::file::sqlite::Sqlite;
+use crate::inbound::file::{ColumnMapping, InputKind, Reader, csv::Csv, excel::Excel};
+use anyhow::Context;
+use std::io::Write;
+use std::path::Path;
+
+/// How a source header is read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderStatus {
+    /// Matches a field name exactly.
+    Detected(&'static str),
+    /// Read into a field under a different name.
+    Aliased(&'static str),
+    /// Not read into any field.
+    Ignored,
+}
+
+/// How a file's header row maps onto the action fields.
+#[derive(Debug, Default)]
+pub struct HeaderCheck {
+    pub file_name: String,
+    pub headers: Vec<(String, HeaderStatus)>,
+    /// Required fields no header maps to.
+    pub missing_fields: Vec<&'static str>,
+}
+
+impl HeaderCheck {
+    fn new(file_name: String, headers: &[String]) -> Self {
+        let headers: Vec<(String, HeaderStatus)> = headers
+            .iter()
+            .map(|header| {
+                let status = match ActionObject::field_for_header(header) {
+                    Some(field) if header.trim() == field => HeaderStatus::Detected(field),
+                    Some(field) => HeaderStatus::Aliased(field),
+                    None => HeaderStatus::Ignored,
+                };
+                (header.clone(), status)
+            })
+            .collect();
+        let missing_fields = REQUIRED_FIELDS
+            .into_iter()
+            .filter(|field| {
+                !headers.iter().any(|(_, status)| {
+                    matches!(status, HeaderStatus::Detected(f) | HeaderStatus::Aliased(f) if f == field)
+                })
+            })
+            .collect();
+        Self {
+            file_name,
+            headers,
+            missing_fields,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.missing_fields.is_empty()
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "File: {}", self.file_name)?;
+        writeln!(out, "Headers:")?;
+        for (header, status) in &self.headers {
+            match status {
+                HeaderStatus::Detected(_) => writeln!(out, "  {} (detected)", header)?,
+                HeaderStatus::Aliased(field) => {
+                    writeln!(out, "  {} (alias for {})", header, field)?
+                }
+                HeaderStatus::Ignored => writeln!(out, "  {} (ignored)", header)?,
+            }
+        }
+        if !self.missing_fields.is_empty() {
+            writeln!(out, "Missing columns: {}", self.missing_fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of parsing a single file without importing it.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub header_check: HeaderCheck,
+    pub total_rows: usize,
+    pub parsed: usize,
+    /// Error message of every row that failed to parse, in file order.
+    pub failures: Vec<String>,
+}
+
+impl VerifyReport {
+    fn new(file_name: String, headers: &[String]) -> Self {
+        Self {
+            header_check: HeaderCheck::new(file_name, headers),
+            ..Self::default()
+        }
+    }
+
+    fn record(&mut self, result: anyhow::Result<ActionObject>) {
+        self.total_rows += 1;
+        match result {
+            // Listed whatever `BAD_DATE_POLICY` says, so bad dates are found before a run
+            Ok(action) => match action.date_error() {
+                Some(e) => self.failures.push(format!(
+                    "row {} (action ID {}): {}",
+                    self.total_rows,
+                    action.action_id(),
+                    e
+                )),
+                None => self.parsed += 1,
+            },
+            Err(e) => self.failures.push(format!("{:#}", e)),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        self.header_check.write_to(out)?;
+        writeln!(out, "Total rows: {}", self.total_rows)?;
+        writeln!(out, "Parsed OK: {}", self.parsed)?;
+        writeln!(out, "Parse failures: {}", self.failures.len())?;
+        for failure in &self.failures {
+            writeln!(out, "  - {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+fn file_name_and_kind(path: &Path) -> anyhow::Result<(String, Option<InputKind>)> {
+    if !path.is_file() {
+        anyhow::bail!("File does not exist: {}", path.display());
+    }
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown file")
+        .to_string();
+    Ok((file_name, InputKind::of(path)))
+}
+
+fn unsupported(path: &Path) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Unsupported file type: {} (expected .csv, .xlsx, .xls, or .ods)",
+        path.display()
+    )
+}
+
+/// Reads only the header row of a CSV or Excel file and checks it covers every required
+/// field, without parsing any data rows.
+pub fn check_headers(path: &Path, mapping: &ColumnMapping) -> anyhow::Result<HeaderCheck> {
+    let (file_name, kind) = file_name_and_kind(path)?;
+    match kind {
+        Some(InputKind::Csv) => {
+            // Skips the row-count pass `csv_action_iter` would make
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("failed to open csv file: {}", file_name))?;
+            let iter = <Reader as Csv>::csv_action_iter_from_reader(file, &file_name, mapping);
+            Ok(HeaderCheck::new(file_name, iter.headers()))
+        }
+        Some(InputKind::Spreadsheet) => {
+            let iter = <Reader as Excel>::excel_action_iter(path, mapping)?;
+            Ok(HeaderCheck::new(file_name, iter.headers()))
+        }
+        #[cfg(feature = "sqlite")]
+        Some(InputKind::Sqlite) => {
+            let iter = <Reader as Sqlite>::sqlite_action_iter(path, mapping)?;
+            Ok(HeaderCheck::new(file_name, iter.headers()))
+        }
+        Some(InputKind::Archive) | None => Err(unsupported(path)),
+    }
+}
+
+/// Parses every row of a CSV or Excel file with the same readers as an import, without
+/// authenticating, fetching the report, or posting anything.
+pub fn verify_file(path: &Path, mapping: &ColumnMapping) -> anyhow::Result<VerifyReport> {
+    let (file_name, kind) = file_name_and_kind(path)?;
+    let report = match kind {
+        Some(InputKind::Csv) => {
+            let iter = <Reader as Csv>::csv_action_iter(path, mapping)?;
+            let mut report = VerifyReport::new(file_name, iter.headers());
+            iter.for_each(|result| report.record(result));
+            report
+        }
+        Some(InputKind::Spreadsheet) => {
+            let iter = <Reader as Excel>::excel_action_iter(path, mapping)?;
+            let mut report = VerifyReport::new(file_name, iter.headers());
+            iter.for_each(|result| report.record(result));
+            report
+        }
+        #[cfg(feature = "sqlite")]
+        Some(InputKind::Sqlite) => {
+            let iter = <Reader as Sqlite>::sqlite_action_iter(path, mapping)?;
+            let mut report = VerifyReport::new(file_name, iter.headers());
+            iter.for_each(|result| report.record(result));
+            report
+        }
+        Some(InputKind::Archive) | None => return Err(unsupported(path)),
+    };
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn reports_aliases_and_failed_rows() {
+        let path = TempPath::new("verify.csv");
+        std::fs::write(
+            &path,
+            "requestId,note,actionwho,cfactionid,actionDate,extra\n\
+             1,first,tester,456,2024-01-01 10:00:00,x\n\
+             ,second,tester,457,,y\n",
+        )
+        .unwrap();
+        let report = verify_file(&path, &ColumnMapping::default()).unwrap();
+
+        assert_eq!(report.total_rows, 2);
+        assert_eq!(report.parsed, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].contains("row 2"));
+        let headers = &report.header_check.headers;
+        assert!(report.header_check.is_ok());
+        assert_eq!(
+            headers[0],
+            ("requestId".to_string(), HeaderStatus::Aliased("ticket_id"))
+        );
+        assert_eq!(
+            headers[1],
+            ("note".to_string(), HeaderStatus::Detected("note"))
+        );
+        assert_eq!(headers[5], ("extra".to_string(), HeaderStatus::Ignored));
+    }
+
+    #[test]
+    fn header_check_lists_missing_required_fields() {
+        let path = TempPath::new("headers.csv");
+        std::fs::write(&path, "requestId,actionDate,note\nnot-a-ticket,,x\n").unwrap();
+        let check = check_headers(&path, &ColumnMapping::default()).unwrap();
+
+        assert!(!check.is_ok());
+        assert_eq!(check.missing_fields, vec!["actionwho", "action_id"]);
+    }
+}
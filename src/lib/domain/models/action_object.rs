@@ -1,5 +1,9 @@
-use chrono::{FixedOffset, NaiveDateTime, TimeZone};
-use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::SerializeSeq};
+use crate::config::{LeadingZeros, PayloadField};
+use crate::domain::models::timezone::SourceTimezone;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct ActionId(String);
@@ -12,20 +16,26 @@ impl ActionId {
     pub fn new(id: impl Into<String>) -> Self {
         Self(id.into())
     }
-}
 
-impl Serialize for ActionId {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let object = serde_json::json!({
-            "name": "cfactionid",
-            "value": self.value()
-        });
-        let mut seq = serializer.serialize_seq(Some(1))?;
-        seq.serialize_element(&object)?;
-        seq.end()
+    /// Drops the leading zeros of an all-digit ID under `LeadingZeros::Strip`.
+    pub fn apply_leading_zeros(&mut self, leading_zeros: LeadingZeros) {
+        if leading_zeros == LeadingZeros::Strip {
+            let stripped = strip_leading_zeros(&self.0);
+            if stripped.len() != self.0.len() {
+                self.0 = stripped.to_string();
+            }
+        }
+    }
+
+    /// The ID as the number posted in `cfactionid`, or why it can't be one.
+    pub fn cfactionid(&self) -> Result<u32, String> {
+        self.0.parse().map_err(|_| {
+            format!(
+                "Action ID: '{}' is not a whole number from 0 to {}, so it can't be posted as cfactionid",
+                self.0,
+                u32::MAX
+            )
+        })
     }
 }
 
@@ -39,68 +49,266 @@ impl<'de> Deserialize<'de> for ActionId {
     }
 }
 
+/// An all-digit action ID without its leading zeros, so `00456` and `456` compare equal;
+/// `000` becomes `0`. Any other ID is returned as is.
+pub fn strip_leading_zeros(id: &str) -> &str {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return id;
+    }
+    match id.trim_start_matches('0') {
+        "" => &id[id.len() - 1..],
+        stripped => stripped,
+    }
+}
+
+/// Header name the `TIMEZONE_COLUMN` source column is deserialized under.
+pub const SOURCE_TIMEZONE_FIELD: &str = "__source_timezone";
+
+/// Outcome used when neither the row nor `DEFAULT_OUTCOME` gives one.
+pub const DEFAULT_OUTCOME: &str = "Imported Note";
+
+/// Fields every row needs a column for; `outcome` has a default.
+pub const REQUIRED_FIELDS: [&str; 5] =
+    ["ticket_id", "actiondate", "note", "actionwho", "action_id"];
+
+/// Each field and the header names read into it, lowercase and without spaces or
+/// underscores.
+const FIELD_HEADERS: [(&str, &[&str]); 7] = [
+    ("ticket_id", &["ticketid", "requestid"]),
+    ("actiondate", &["actiondate"]),
+    ("outcome", &["outcome"]),
+    ("result", &["result", "actionresult"]),
+    ("note", &["note"]),
+    ("actionwho", &["actionwho"]),
+    ("action_id", &["actionid", "cfactionid", "cdactionid"]),
+];
+
+/// A parsed action date, remembering whether the source value was already UTC.
+///
+/// Values with a `Z` suffix or an explicit offset are normalised to UTC at parse time and
+/// sent as-is. Naive values are read in their source timezone (Arizona time unless one is
+/// set) and converted on serialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionDate {
+    datetime: NaiveDateTime,
+    is_utc: bool,
+    timezone: Option<SourceTimezone>,
+}
+
+impl ActionDate {
+    pub fn local(datetime: NaiveDateTime) -> Self {
+        Self {
+            datetime,
+            is_utc: false,
+            timezone: None,
+        }
+    }
+
+    pub fn utc(datetime: NaiveDateTime) -> Self {
+        Self {
+            datetime,
+            is_utc: true,
+            timezone: None,
+        }
+    }
+
+    /// Sets the timezone a naive value is read in; no effect on values that were zoned.
+    pub fn set_timezone(&mut self, timezone: SourceTimezone) {
+        self.timezone = Some(timezone);
+    }
+
+    /// The wall-clock value as parsed (already shifted to UTC if the source was zoned).
+    pub fn naive(&self) -> NaiveDateTime {
+        self.datetime
+    }
+
+    pub fn is_utc(&self) -> bool {
+        self.is_utc
+    }
+
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        if self.is_utc {
+            return Utc.from_utc_datetime(&self.datetime);
+        }
+        self.timezone
+            .unwrap_or_else(SourceTimezone::arizona)
+            .to_utc(&self.datetime)
+    }
+
+    /// Parses the date formats accepted in source files. Blank input is `None`.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            return Ok(None);
+        }
+        if let Ok(zoned) = DateTime::parse_from_rfc3339(trimmed) {
+            return Ok(Some(Self::utc(zoned.naive_utc())));
+        }
+        let is_utc = trimmed.ends_with(['Z', 'z']);
+        let cleaned = trimmed.trim_end_matches(['Z', 'z']);
+        NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%d %H:%M:%S%.f"))
+            .map(|datetime| {
+                Some(Self {
+                    datetime,
+                    is_utc,
+                    timezone: None,
+                })
+            })
+            .map_err(|e| format!("failed to parse date '{}': {}", value, e))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ActionObject {
-    #[serde(
-        alias = "requestId",
-        alias = "requestID",
-        alias = "RequestId",
-        alias = "RequestID",
-        alias = "requestid",
-        alias = "REQUESTID"
-    )]
+    /// Ticket reference as the source had it, which may be alphanumeric. A numeric one is
+    /// read into `ticket_id` as the row is read; others are resolved by the processor
+    /// through `TICKET_ID_MAP_PATH`.
+    #[serde(rename = "ticket_id", deserialize_with = "deserialize_ticket_ref")]
+    pub source_ticket_id: String,
+    /// Halo ticket ID the action is posted to; `0` until a non-numeric reference is resolved.
+    #[serde(skip)]
     pub ticket_id: u32,
-    #[serde(
-        alias = "actionDate",
-        alias = "ActionDate",
-        alias = "ACTIONDATE",
-        deserialize_with = "deserialize_action_date",
-        skip_serializing_if = "Option::is_none"
-    )]
-    pub actiondate: Option<NaiveDateTime>,
+    /// The source's date text. It is read into `actiondate` as the row is read; text no
+    /// accepted format matches stays here for the processor to apply `BAD_DATE_POLICY`.
+    #[serde(rename = "actiondate", deserialize_with = "deserialize_date_text")]
+    pub unparsed_date: Option<String>,
+    #[serde(skip)]
+    pub actiondate: Option<ActionDate>,
     #[serde(default = "default_outcome")]
     pub outcome: String,
-    #[serde(alias = "Note", alias = "NOTE")]
+    /// Posted as the action's `result`; `null` when neither the row nor `DEFAULT_RESULT`
+    /// gives one.
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    pub result: Option<String>,
     pub note: String,
-    #[serde(alias = "actionWho", alias = "ActionWho")]
     pub actionwho: String,
-    #[serde(
-        alias = "cfactionid",
-        alias = "CFACTIONID",
-        alias = "CfActionId",
-        alias = "cfActionId",
-        alias = "CFactionId",
-        alias = "CFActionID",
-        alias = "CFactionId",
-        alias = "cfactionId",
-        alias = "cfActionID",
-        alias = "cfactionID",
-        alias = "cdactionId"
-    )]
     pub action_id: ActionId,
     #[serde(default = "default_is_import", rename = "_isimport")]
     pub _isimport: bool,
+    /// Halo agent ID resolved from `actionwho` through `AGENT_MAP_PATH`.
+    #[serde(skip)]
+    pub agent_id: Option<u32>,
+    /// Raw value of the row's `TIMEZONE_COLUMN`, resolved onto `actiondate` by the processor.
+    #[serde(default, rename = "__source_timezone")]
+    pub source_timezone: Option<String>,
+    /// Where the row came from, set by the processor when `SOURCE_FILE_CUSTOM_FIELD_ID` is.
+    #[serde(skip)]
+    pub source_location: Option<String>,
 }
 
 fn default_outcome() -> String {
-    "Imported Note".to_string()
+    DEFAULT_OUTCOME.to_string()
 }
 
 fn default_is_import() -> bool {
     true
 }
 
-fn deserialize_action_date<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+/// Reads an optional text cell, treating a blank one as absent.
+fn deserialize_optional_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty()))
+}
+
+/// Reads a ticket reference from a text or numeric cell, rejecting a blank one.
+fn deserialize_ticket_ref<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::{self, Visitor};
+    use std::fmt;
+
+    struct TicketRefVisitor;
+
+    impl Visitor<'_> for TicketRefVisitor {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a ticket ID or reference")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            let trimmed = value.trim();
+            if trimmed.is_empty() {
+                return Err(E::custom("ticket ID is blank"));
+            }
+            Ok(trimmed.to_string())
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            // Spreadsheet cells hold whole numbers as floats
+            if value.fract() == 0.0 && value >= 0.0 && value <= u64::MAX as f64 {
+                Ok((value as u64).to_string())
+            } else {
+                Ok(value.to_string())
+            }
+        }
+
+        fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_string())
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Err(E::custom("ticket ID is blank"))
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            self.visit_unit()
+        }
+    }
+
+    deserializer.deserialize_any(TicketRefVisitor)
+}
+
+/// Reads a date cell as text, leaving the parsing to [`ActionObject::read_date`] so an
+/// unreadable date doesn't fail the whole row. Blank input is `None`.
+fn deserialize_date_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
     use serde::de::{self, Visitor};
     use std::fmt;
 
-    struct ActionDateVisitor;
+    struct DateTextVisitor;
 
-    impl<'de> Visitor<'de> for ActionDateVisitor {
-        type Value = Option<NaiveDateTime>;
+    impl<'de> Visitor<'de> for DateTextVisitor {
+        type Value = Option<String>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
             formatter.write_str("an optional date string in ISO 8601 format")
@@ -124,69 +332,190 @@ where
         where
             E: de::Error,
         {
-            if value.trim().is_empty() {
-                return Ok(None);
-            }
-            let cleaned = value.trim().trim_end_matches('Z').trim_end_matches('z');
-            NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%S%.f")
-                .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%S"))
-                .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%d %H:%M:%S"))
-                .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%S%.fZ"))
-                .or_else(|_| NaiveDateTime::parse_from_str(cleaned, "%Y-%m-%dT%H:%M:%SZ"))
-                .map(Some)
-                .map_err(|e| de::Error::custom(format!("failed to parse date '{}': {}", value, e)))
+            Ok(Some(value.trim().to_string()).filter(|text| !text.is_empty()))
         }
     }
 
-    deserializer.deserialize_option(ActionDateVisitor)
+    deserializer.deserialize_option(DateTextVisitor)
+}
+
+/// The POST body for an action, with the given internal fields left out.
+pub struct ActionPayload<'a> {
+    action: &'a ActionObject,
+    omitted: &'a [PayloadField],
+    /// Custom field the action ID is written into (`ACTION_ID_CUSTOM_FIELD_ID`), the field
+    /// the existing ID reports read.
+    action_id_field_id: u32,
+    source_field_id: Option<u32>,
+    leading_zeros: LeadingZeros,
+}
+
+impl ActionPayload<'_> {
+    /// Writes the action's source location into custom field `field_id`, if it has one.
+    pub fn with_source_field(mut self, field_id: Option<u32>) -> Self {
+        self.source_field_id = field_id;
+        self
+    }
+
+    /// Under `LeadingZeros::Keep`, posts `cfactionid` as the ID's text so its zeros survive.
+    pub fn with_leading_zeros(mut self, leading_zeros: LeadingZeros) -> Self {
+        self.leading_zeros = leading_zeros;
+        self
+    }
 }
 
-impl Serialize for ActionObject {
+impl Serialize for ActionPayload<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        use serde::ser::SerializeMap;
+        use serde::ser::{Error, SerializeMap};
+        let action = self.action;
+        // Fails the serialization rather than post a cfactionid the report can't match
+        let cfactionid = match (
+            action.action_id.cfactionid().map_err(S::Error::custom)?,
+            self.leading_zeros,
+        ) {
+            (_, LeadingZeros::Keep) => serde_json::json!(action.action_id()),
+            (number, LeadingZeros::Strip) => serde_json::json!(number),
+        };
+        let include = |field: PayloadField| !self.omitted.contains(&field);
         let mut map = serializer.serialize_map(None)?;
 
-        map.serialize_entry("__rowNum__", &Option::<u32>::None)?;
-        map.serialize_entry("_isimport", &self._isimport)?;
-
-        if let Some(date) = &self.actiondate {
-            let arizona_offset = FixedOffset::west_opt(7 * 3600).unwrap();
-            let arizona_dt = arizona_offset
-                .from_local_datetime(date)
-                .earliest()
-                .unwrap_or_else(|| arizona_offset.from_utc_datetime(date));
-            let utc_dt = arizona_dt.with_timezone(&chrono::Utc);
-            let date_str = utc_dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        if include(PayloadField::RowNum) {
+            map.serialize_entry("__rowNum__", &Option::<u32>::None)?;
+        }
+        map.serialize_entry("_isimport", &action._isimport)?;
+
+        if let Some(date) = &action.actiondate {
+            let date_str = date.to_utc().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
             map.serialize_entry("datetime", &date_str)?;
         }
 
-        map.serialize_entry("actionwho", &self.actionwho)?;
+        map.serialize_entry("actionwho", &action.actionwho)?;
+        if let Some(agent_id) = action.agent_id {
+            map.serialize_entry("who_agentid", &agent_id)?;
+        }
 
-        let cfactionid: u32 = self.action_id.value().parse().unwrap_or(0);
         map.serialize_entry("cfactionid", &cfactionid)?;
 
-        let customfields = vec![serde_json::json!({
-            "name": "cfactionid",
+        let mut customfields = vec![serde_json::json!({
+            "id": self.action_id_field_id,
             "value": cfactionid
         })];
+        if let (Some(field_id), Some(location)) = (self.source_field_id, &action.source_location) {
+            customfields.push(serde_json::json!({
+                "id": field_id,
+                "value": location
+            }));
+        }
         map.serialize_entry("customfields", &customfields)?;
 
-        map.serialize_entry("note", &self.note)?;
-        map.serialize_entry("note_html", &self.note)?;
-        map.serialize_entry("outcome", &self.outcome)?;
-        map.serialize_entry("requestid", &self.ticket_id)?;
-        map.serialize_entry("result", &Option::<String>::None)?;
-        map.serialize_entry("ticket_id", &self.ticket_id)?;
-        map.serialize_entry("who", &self.actionwho)?;
+        map.serialize_entry("note", &action.note)?;
+        map.serialize_entry("note_html", &action.note)?;
+        map.serialize_entry("outcome", &action.outcome)?;
+        if include(PayloadField::RequestId) {
+            map.serialize_entry("requestid", &action.ticket_id)?;
+        }
+        if include(PayloadField::Result) {
+            map.serialize_entry("result", &action.result)?;
+        }
+        map.serialize_entry("ticket_id", &action.ticket_id)?;
+        if include(PayloadField::Who) {
+            map.serialize_entry("who", &action.actionwho)?;
+        }
 
         map.end()
     }
 }
 
+/// Maps a source header onto its field name, ignoring case, spaces, and underscores, so
+/// `requestId`, `Request ID`, and `request_id` all resolve to `ticket_id`.
+fn canonical_field(header: &str) -> Option<&'static str> {
+    let normalized: String = header
+        .chars()
+        .filter(|c| *c != '_' && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    FIELD_HEADERS
+        .into_iter()
+        .find(|(_, names)| names.contains(&normalized.as_str()))
+        .map(|(field, _)| field)
+}
+
 impl ActionObject {
+    /// The field a source header is read into, or `None` if the header is ignored.
+    pub fn field_for_header(header: &str) -> Option<&'static str> {
+        canonical_field(header)
+    }
+
+    /// The header names read into `field`, lowercase and without spaces or underscores.
+    pub fn header_names_for(field: &str) -> &'static [&'static str] {
+        FIELD_HEADERS
+            .into_iter()
+            .find(|(name, _)| *name == field)
+            .map_or(&[], |(_, names)| names)
+    }
+
+    /// The `REQUIRED_FIELDS` that none of `headers` is read into.
+    pub fn missing_required_fields(headers: &[String]) -> Vec<&'static str> {
+        REQUIRED_FIELDS
+            .into_iter()
+            .filter(|field| {
+                !headers
+                    .iter()
+                    .any(|header| canonical_field(header) == Some(*field))
+            })
+            .collect()
+    }
+
+    /// Builds an action from a header-to-value record from any source.
+    ///
+    /// Headers are normalized the same way as by the file readers, the date goes through
+    /// the same parser, and missing optional fields get the same defaults. Unknown headers
+    /// are ignored.
+    pub fn from_record(fields: HashMap<String, String>) -> anyhow::Result<ActionObject> {
+        let mut record = serde_json::Map::new();
+        for (header, value) in fields {
+            let Some(field) = canonical_field(&header) else {
+                continue;
+            };
+            record.insert(field.to_string(), serde_json::Value::String(value));
+        }
+        record
+            .entry("actiondate")
+            .or_insert(serde_json::Value::Null);
+        let mut action: ActionObject = serde_json::from_value(serde_json::Value::Object(record))
+            .context("failed to build action from record")?;
+        action.read_numeric_ticket_id();
+        action.read_date();
+        Ok(action)
+    }
+
+    /// Sets `ticket_id` from `source_ticket_id` when the reference is a Halo ID as is.
+    pub fn read_numeric_ticket_id(&mut self) {
+        if let Ok(ticket_id) = self.source_ticket_id.parse() {
+            self.ticket_id = ticket_id;
+        }
+    }
+
+    /// Parses `unparsed_date` into `actiondate`, keeping the text only when no accepted
+    /// format matches it.
+    pub fn read_date(&mut self) {
+        if let Some(text) = self.unparsed_date.take() {
+            match ActionDate::parse(&text) {
+                Ok(date) => self.actiondate = date,
+                Err(_) => self.unparsed_date = Some(text),
+            }
+        }
+    }
+
+    /// Why the row's date couldn't be read, if it couldn't.
+    pub fn date_error(&self) -> Option<String> {
+        let text = self.unparsed_date.as_deref()?;
+        ActionDate::parse(text).err()
+    }
+
     pub fn new(
         ticket_id: u32,
         actiondate: Option<NaiveDateTime>,
@@ -197,46 +526,224 @@ impl ActionObject {
     ) -> Self {
         let outcome = outcome.unwrap_or_else(default_outcome);
         Self {
+            source_ticket_id: ticket_id.to_string(),
             ticket_id,
-            actiondate,
+            unparsed_date: None,
+            actiondate: actiondate.map(ActionDate::local),
             outcome,
+            result: None,
             note: note.into(),
             actionwho: actionwho.into(),
             action_id,
             _isimport: true,
+            agent_id: None,
+            source_timezone: None,
+            source_location: None,
         }
     }
 
     pub fn action_id(&self) -> &str {
         self.action_id.value()
     }
+
+    /// The action's POST body, writing its ID into custom field `action_id_field_id`.
+    pub fn payload<'a>(
+        &'a self,
+        action_id_field_id: u32,
+        omitted: &'a [PayloadField],
+    ) -> ActionPayload<'a> {
+        ActionPayload {
+            action: self,
+            omitted,
+            action_id_field_id,
+            source_field_id: None,
+            leading_zeros: LeadingZeros::Strip,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::Config;
-
     use super::*;
 
     #[test]
     fn serialize_action_object() {
-        let config = Config::from_env().unwrap();
         let action_object =
             ActionObject::new(123, None, None, "testing..", "tester", ActionId::new("456"));
-        let serialized: serde_json::Value = serde_json::to_value(&action_object).unwrap();
+        let serialized = serde_json::to_value(action_object.payload(42, &[])).unwrap();
         assert_eq!(
             serialized,
             serde_json::json!({
-                "ticket_id": 123,
-                "actiondate": null,
-                "note": "testing..",
-                "outcome": "Imported Note",
+                "__rowNum__": null,
+                "_isimport": true,
                 "actionwho": "tester",
+                "cfactionid": 456,
                 "customfields": [
-                    { "id": config.action_id_custom_field_id,"value": "456" }
+                    { "id": 42, "value": 456 }
                 ],
-                "_isimport": true,
+                "note": "testing..",
+                "note_html": "testing..",
+                "outcome": "Imported Note",
+                "requestid": 123,
+                "result": null,
+                "ticket_id": 123,
+                "who": "tester",
             })
         );
+        // The field the payload writes is the one the field check and reports read
+        assert_eq!(
+            crate::domain::importer::field_check::custom_field_value(&serialized, 42),
+            Ok("456".to_string())
+        );
+    }
+
+    #[test]
+    fn payload_omits_internal_fields() {
+        let action_object =
+            ActionObject::new(123, None, None, "testing..", "tester", ActionId::new("456"));
+        let full = serde_json::to_value(action_object.payload(42, &[])).unwrap();
+        assert!(full.get("__rowNum__").is_some());
+        let minimal = serde_json::to_value(action_object.payload(42, &PayloadField::ALL)).unwrap();
+        for field in PayloadField::ALL {
+            assert!(minimal.get(field.name()).is_none());
+        }
+        assert_eq!(minimal["ticket_id"], 123);
+        assert_eq!(minimal["actionwho"], "tester");
+    }
+
+    #[test]
+    fn non_numeric_action_id_fails_serialization() {
+        let action_object = ActionObject::new(
+            123,
+            None,
+            None,
+            "testing..",
+            "tester",
+            ActionId::new("A-456"),
+        );
+        let err = serde_json::to_value(action_object.payload(42, &[])).unwrap_err();
+        assert!(err.to_string().contains("'A-456'"), "{}", err);
+        assert_eq!(ActionId::new("456").cfactionid(), Ok(456));
+    }
+
+    #[test]
+    fn leading_zeros_are_stripped_or_posted_as_text() {
+        assert_eq!(strip_leading_zeros("00456"), "456");
+        assert_eq!(strip_leading_zeros("456"), "456");
+        assert_eq!(strip_leading_zeros("000"), "0");
+        assert_eq!(strip_leading_zeros("0A-456"), "0A-456");
+
+        let mut stripped = ActionId::new("00456");
+        stripped.apply_leading_zeros(LeadingZeros::Strip);
+        assert_eq!(stripped.value(), "456");
+        let mut kept = ActionId::new("00456");
+        kept.apply_leading_zeros(LeadingZeros::Keep);
+        assert_eq!(kept.value(), "00456");
+
+        let action_object = ActionObject::new(123, None, None, "testing..", "tester", kept.clone());
+        let numeric = serde_json::to_value(action_object.payload(42, &[])).unwrap();
+        assert_eq!(numeric["cfactionid"], 456);
+        let text = serde_json::to_value(
+            action_object
+                .payload(42, &[])
+                .with_leading_zeros(LeadingZeros::Keep),
+        )
+        .unwrap();
+        assert_eq!(text["cfactionid"], "00456");
+        assert_eq!(text["customfields"][0]["value"], "00456");
+    }
+
+    #[test]
+    fn source_field_is_added_when_configured() {
+        let mut action_object =
+            ActionObject::new(123, None, None, "testing..", "tester", ActionId::new("456"));
+        action_object.source_location = Some("actions.xlsx / Sheet1 / row 12".to_string());
+
+        let plain = serde_json::to_value(action_object.payload(42, &[])).unwrap();
+        assert_eq!(plain["customfields"].as_array().unwrap().len(), 1);
+
+        let tagged =
+            serde_json::to_value(action_object.payload(42, &[]).with_source_field(Some(77)))
+                .unwrap();
+        assert_eq!(
+            tagged["customfields"][1],
+            serde_json::json!({ "id": 77, "value": "actions.xlsx / Sheet1 / row 12" })
+        );
+    }
+
+    #[test]
+    fn from_record_resolves_aliases_and_defaults() {
+        let record = HashMap::from([
+            ("RequestID".to_string(), " 2997 ".to_string()),
+            (
+                "action_date".to_string(),
+                "2024-01-15T09:30:00Z".to_string(),
+            ),
+            ("Note".to_string(), "from a record".to_string()),
+            ("actionWho".to_string(), "tester".to_string()),
+            ("CFactionId".to_string(), "1001".to_string()),
+            ("ignored".to_string(), "x".to_string()),
+        ]);
+        let action = ActionObject::from_record(record).unwrap();
+        assert_eq!(action.ticket_id, 2997);
+        assert!(action.actiondate.unwrap().is_utc());
+        assert_eq!(action.outcome, "Imported Note");
+        assert_eq!(action.action_id(), "1001");
+
+        let missing_note = HashMap::from([
+            ("requestId".to_string(), "2997".to_string()),
+            ("actionWho".to_string(), "tester".to_string()),
+            ("cfactionid".to_string(), "1001".to_string()),
+        ]);
+        let err = ActionObject::from_record(missing_note).unwrap_err();
+        assert!(format!("{:#}", err).contains("missing field `note`"));
+
+        let bad_date = HashMap::from([
+            ("requestId".to_string(), "2997".to_string()),
+            ("actionDate".to_string(), "01/15/2024".to_string()),
+            ("note".to_string(), "from a record".to_string()),
+            ("actionWho".to_string(), "tester".to_string()),
+            ("cfactionid".to_string(), "1001".to_string()),
+        ]);
+        let action = ActionObject::from_record(bad_date).unwrap();
+        assert!(action.actiondate.is_none());
+        assert!(action.date_error().unwrap().contains("'01/15/2024'"));
+    }
+
+    #[test]
+    fn result_column_is_posted_when_present() {
+        let record = |result: &str| {
+            HashMap::from([
+                ("requestId".to_string(), "2997".to_string()),
+                ("Action Result".to_string(), result.to_string()),
+                ("note".to_string(), "from a record".to_string()),
+                ("actionWho".to_string(), "tester".to_string()),
+                ("cfactionid".to_string(), "1001".to_string()),
+            ])
+        };
+        let action = ActionObject::from_record(record(" Resolved ")).unwrap();
+        let payload = serde_json::to_value(action.payload(42, &[])).unwrap();
+        assert_eq!(payload["result"], "Resolved");
+
+        let blank = ActionObject::from_record(record("  ")).unwrap();
+        assert_eq!(blank.result, None);
+        let payload = serde_json::to_value(blank.payload(42, &[])).unwrap();
+        assert!(payload["result"].is_null());
+    }
+
+    #[test]
+    fn zoned_dates_are_not_shifted_twice() {
+        let utc = ActionDate::parse("2024-01-15T09:30:00Z").unwrap().unwrap();
+        assert!(utc.is_utc());
+        assert_eq!(utc.to_utc().to_rfc3339(), "2024-01-15T09:30:00+00:00");
+
+        let offset = ActionDate::parse("2024-01-15T09:30:00+02:00")
+            .unwrap()
+            .unwrap();
+        assert_eq!(offset.to_utc().to_rfc3339(), "2024-01-15T07:30:00+00:00");
+
+        let local = ActionDate::parse("2024-01-15 09:30:00").unwrap().unwrap();
+        assert!(!local.is_utc());
+        assert_eq!(local.to_utc().to_rfc3339(), "2024-01-15T16:30:00+00:00");
     }
 }
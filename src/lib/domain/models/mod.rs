@@ -1 +1,2 @@
 pub mod action_object;
+pub mod timezone;
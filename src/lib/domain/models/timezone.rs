@@ -0,0 +1,121 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Timezone naive source dates are read in: a fixed offset or an IANA zone.
+///
+/// IANA zones follow daylight saving, so the same zone can map two rows to different
+/// offsets depending on their date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceTimezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl SourceTimezone {
+    /// Arizona time (UTC-7, no daylight saving), the importer's historical default.
+    pub fn arizona() -> Self {
+        Self::Fixed(FixedOffset::west_opt(7 * 3600).expect("UTC-7 is a valid offset"))
+    }
+
+    /// Parses an IANA name (`America/Phoenix`), `UTC`/`Z`, or an offset such as
+    /// `+02:00`, `-0700`, or `+5`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let trimmed = value.trim();
+        if trimmed.eq_ignore_ascii_case("utc") || trimmed.eq_ignore_ascii_case("z") {
+            return Ok(Self::Fixed(Utc.fix()));
+        }
+        if trimmed.starts_with(['+', '-']) {
+            return parse_offset(trimmed).map(Self::Fixed).ok_or_else(|| {
+                format!(
+                    "invalid timezone offset '{}': expected e.g. +02:00 or -0700",
+                    value
+                )
+            });
+        }
+        trimmed
+            .parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| format!("unknown timezone '{}'", value))
+    }
+
+    /// Converts a wall-clock time in this zone to UTC.
+    ///
+    /// Ambiguous times (clocks going back) resolve to the earlier instant; times skipped by
+    /// a daylight saving jump are read with the offset in effect just before the jump.
+    pub fn to_utc(&self, datetime: &NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Self::Fixed(offset) => offset
+                .from_local_datetime(datetime)
+                .earliest()
+                .unwrap_or_else(|| offset.from_utc_datetime(datetime))
+                .with_timezone(&Utc),
+            Self::Named(tz) => match tz.from_local_datetime(datetime).earliest() {
+                Some(local) => local.with_timezone(&Utc),
+                None => {
+                    let before = *datetime - chrono::Duration::hours(1);
+                    let offset = tz.offset_from_utc_datetime(&before).fix();
+                    Utc.from_utc_datetime(&(*datetime - offset))
+                }
+            },
+        }
+    }
+}
+
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, rest) = value.split_at(1);
+    let sign = if sign == "-" { -1 } else { 1 };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parses_names_and_offsets_and_follows_dst() {
+        let winter = NaiveDate::from_ymd_opt(2024, 1, 15)
+            .and_then(|d| d.and_hms_opt(9, 30, 0))
+            .unwrap();
+        let summer = NaiveDate::from_ymd_opt(2024, 7, 15)
+            .and_then(|d| d.and_hms_opt(9, 30, 0))
+            .unwrap();
+        let london = SourceTimezone::parse("Europe/London").unwrap();
+        assert_eq!(
+            london.to_utc(&winter).to_rfc3339(),
+            "2024-01-15T09:30:00+00:00"
+        );
+        assert_eq!(
+            london.to_utc(&summer).to_rfc3339(),
+            "2024-07-15T08:30:00+00:00"
+        );
+
+        let offset = SourceTimezone::parse("-07:00").unwrap();
+        assert_eq!(offset, SourceTimezone::arizona());
+        assert_eq!(SourceTimezone::parse("-0700").unwrap(), offset);
+        assert_eq!(
+            SourceTimezone::parse("utc")
+                .unwrap()
+                .to_utc(&winter)
+                .to_rfc3339(),
+            "2024-01-15T09:30:00+00:00"
+        );
+        assert!(SourceTimezone::parse("Mars/Olympus").is_err());
+        assert!(SourceTimezone::parse("+25:00").is_err());
+    }
+}
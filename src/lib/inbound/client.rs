@@ -1,23 +1,32 @@
-use crate::{config::Config, outbound::client::auth::AuthClient};
+use crate::{
+    config::{
+        Config, LeadingZeros, REPORT_FROM_PLACEHOLDER, REPORT_TO_PLACEHOLDER, encode_braces,
+        report_url_is_chunked,
+    },
+    domain::{importer::processor::format_number, models::action_object::strip_leading_zeros},
+    inbound::report_cache::ReportCache,
+    outbound::client::{
+        auth::AuthClient,
+        http::{self, REQUEST_ID_HEADER, RetryStats, new_request_id},
+    },
+};
 use anyhow::Context;
-use reqwest::Client as ReqwestClient;
+use chrono::{Days, NaiveDate, Utc};
+use futures::StreamExt;
+use reqwest::{Client as ReqwestClient, Url};
 use serde::Deserialize;
+use serde_json::error::Category;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use tracing::{error, warn};
 
-fn format_number(n: usize) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    let chars: Vec<char> = s.chars().collect();
-    for (i, &ch) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i).is_multiple_of(3) {
-            result.push(',');
-        }
-        result.push(ch);
-    }
-    result
-}
+/// Wait before the first retry of a report body that failed to parse; doubled each retry.
+const REPORT_PARSE_RETRY_BASE_DELAY: Duration = Duration::from_secs(5);
+/// Longest wait between report parse retries, however many `REPORT_PARSE_RETRIES` allows.
+const REPORT_PARSE_RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+/// Characters of an unparseable report body quoted in the error.
+const REPORT_BODY_PREFIX_CHARS: usize = 500;
 
 #[derive(Debug, Deserialize)]
 struct ReportResponse {
@@ -27,151 +36,667 @@ struct ReportResponse {
     action_ids: String,
 }
 
+/// What one report contributed to the existing-ID set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportStats {
+    /// Report URL as configured, placeholders included.
+    pub url: String,
+    /// Requests made for the report: its chunk count, or 1 when not chunked.
+    pub chunks: usize,
+    /// IDs the report returned, counting repeats.
+    pub ids_listed: usize,
+    /// IDs no other report (or earlier row of this one) had returned first; with
+    /// `REPORT_CONCURRENCY` that depends on which report finished first.
+    pub new_ids: usize,
+    pub duration_secs: f64,
+    /// Loaded from `REPORT_CACHE_SECS` instead of fetched.
+    pub from_cache: bool,
+}
+
+impl ReportStats {
+    /// IDs already known when this report returned them.
+    pub fn duplicate_ids(&self) -> usize {
+        self.ids_listed.saturating_sub(self.new_ids)
+    }
+}
+
+/// Per-report breakdown of an existing-ID fetch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReportFetchStats {
+    pub reports: Vec<ReportStats>,
+    /// Distinct IDs across every report.
+    pub total_ids: usize,
+    pub duration_secs: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct ReportClient {
     config: Config,
     http_client: ReqwestClient,
     auth_client: Arc<AuthClient>,
+    retry_stats: Arc<RetryStats>,
+    cache: Option<ReportCache>,
 }
 
 impl ReportClient {
-    pub fn new(config: Config, auth_client: Arc<AuthClient>) -> Self {
+    pub fn new(
+        config: Config,
+        http_client: ReqwestClient,
+        auth_client: Arc<AuthClient>,
+        retry_stats: Arc<RetryStats>,
+    ) -> Self {
+        let cache = config
+            .report_cache_secs
+            .map(|secs| ReportCache::new(&config.output_dir, Duration::from_secs(secs)));
         Self {
             config,
-            http_client: ReqwestClient::new(),
+            http_client,
             auth_client,
+            retry_stats,
+            cache,
         }
     }
 
-    pub async fn get_existing_action_ids(&self) -> anyhow::Result<HashSet<String>> {
-        let mut all_existing_ids = HashSet::new();
+    /// Deletes the cached reports, once posting is about to make them out of date.
+    pub fn clear_cache(&self) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        match cache.clear() {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!(
+                "Cleared {} cached report(s) from {} before posting",
+                removed,
+                cache.dir().display()
+            ),
+            Err(e) => warn!("Failed to clear the report cache: {:#}", e),
+        }
+    }
+
+    pub async fn get_existing_action_ids(
+        &self,
+    ) -> anyhow::Result<(HashSet<String>, ReportFetchStats)> {
+        let fetch_start = Instant::now();
+        let all_existing_ids = Mutex::new(HashSet::new());
+        let mut stats = ReportFetchStats::default();
         let total_reports = self.config.action_ids_resources.len();
+        let concurrency = self.config.report_concurrency.min(total_reports.max(1));
+        if concurrency > 1 {
+            tracing::info!(
+                "Fetching existing action IDs from {} report(s), {} at a time",
+                total_reports,
+                concurrency
+            );
+        } else {
+            tracing::info!(
+                "Fetching existing action IDs from {} report(s)",
+                total_reports
+            );
+        }
+
+        let mut fetches =
+            futures::stream::iter(self.config.action_ids_resources.iter().enumerate())
+                .map(|(idx, report_url)| {
+                    let report_label = format!("{}/{}", idx + 1, total_reports);
+                    self.fetch_one_report(report_url, report_label, &all_existing_ids)
+                })
+                .buffer_unordered(concurrency);
+        while let Some(report_stats) = fetches.next().await {
+            stats.reports.push(report_stats?);
+        }
+        // Releases the stream's borrow of the shared set
+        drop(fetches);
+        // Reports finish in any order; the summary lists them in configured order
+        stats.reports.sort_by_key(|report| {
+            self.config
+                .action_ids_resources
+                .iter()
+                .position(|url| url.as_str() == report.url)
+        });
+
+        let all_existing_ids = all_existing_ids
+            .into_inner()
+            .map_err(|_| anyhow::anyhow!("existing action ID set lock poisoned"))?;
+        stats.total_ids = all_existing_ids.len();
+        stats.duration_secs = fetch_start.elapsed().as_secs_f64();
         tracing::info!(
-            "Fetching existing action IDs from {} report(s)",
-            total_reports
+            "Completed fetching all reports: {} total existing action IDs in {:.1}s",
+            format_number(all_existing_ids.len()),
+            stats.duration_secs
         );
+        Ok((all_existing_ids, stats))
+    }
 
-        for (idx, report_url) in self.config.action_ids_resources.iter().enumerate() {
+    /// Fetches one configured report, from the cache or in chunks when set up, and merges
+    /// its IDs into `all_existing_ids`. Reports fetched concurrently share the set.
+    async fn fetch_one_report(
+        &self,
+        report_url: &Url,
+        report_label: String,
+        all_existing_ids: &Mutex<HashSet<String>>,
+    ) -> anyhow::Result<ReportStats> {
+        let report_start = Instant::now();
+        let (chunks, cache_key) = match (
+            self.config.report_chunk_days,
+            self.config.report_chunk_start,
+        ) {
+            (Some(days), Some(start)) if report_url_is_chunked(report_url) => (
+                report_chunks(start, Utc::now().date_naive(), days),
+                format!("{} from {} in {}-day chunks", report_url, start, days),
+            ),
+            _ => (Vec::new(), report_url.to_string()),
+        };
+        let mut report_stats = ReportStats {
+            url: report_url.to_string(),
+            chunks: chunks.len().max(1),
+            ids_listed: 0,
+            new_ids: 0,
+            duration_secs: 0.0,
+            from_cache: false,
+        };
+        // Only collected when caching, as it holds a second copy of the report's IDs
+        let mut report_ids = self.cache.as_ref().map(|_| HashSet::new());
+        let cached = self.cache.as_ref().and_then(|cache| cache.load(&cache_key));
+        if let Some(cached) = cached {
             tracing::info!(
-                "Fetching report {}/{}: {}",
-                idx + 1,
-                total_reports,
+                "Report {} loaded from cache fetched {}s ago: {} IDs in this report: {}",
+                report_label,
+                cached.age().as_secs(),
+                format_number(cached.ids.len()),
                 report_url
             );
+            report_stats.ids_listed = cached.ids_listed;
+            report_stats.from_cache = true;
+            let mut ids = lock_ids(all_existing_ids)?;
+            report_stats.new_ids = add_cached_ids(cached.ids, &mut ids, self.config.leading_zeros);
+        } else if chunks.is_empty() {
+            tracing::info!("Fetching report {}: {}", report_label, report_url);
+            let report_data = self.fetch_report(report_url, &report_label, false).await?;
+            let mut ids = lock_ids(all_existing_ids)?;
+            let (report_id_count, new_ids) = add_report_ids(
+                &report_data,
+                &mut ids,
+                report_ids.as_mut(),
+                self.config.leading_zeros,
+            );
+            report_stats.ids_listed = report_id_count;
+            report_stats.new_ids = new_ids;
+            if report_id_count == 0 {
+                warn!(
+                    "Report {} returned {} row(s) but no action IDs. Check that the report resource is configured correctly",
+                    report_label,
+                    report_data.len()
+                );
+            }
+            tracing::info!(
+                "Report {} complete: {} IDs in this report, {} total IDs so far",
+                report_label,
+                report_id_count,
+                format_number(ids.len())
+            );
+        } else {
+            tracing::info!(
+                "Fetching report {} in {} chunk(s) of {} day(s): {}",
+                report_label,
+                chunks.len(),
+                self.config.report_chunk_days.unwrap_or_default(),
+                report_url
+            );
+            for (chunk_idx, (from, to)) in chunks.iter().enumerate() {
+                let chunk_label = format!(
+                    "{} chunk {}/{} ({} to {})",
+                    report_label,
+                    chunk_idx + 1,
+                    chunks.len(),
+                    from,
+                    to
+                );
+                let chunk_url = report_chunk_url(report_url, *from, *to)?;
+                // A date range with no actions is expected, so an empty chunk isn't an error
+                let report_data = self.fetch_report(&chunk_url, &chunk_label, true).await?;
+                let mut ids = lock_ids(all_existing_ids)?;
+                let (chunk_id_count, new_ids) = add_report_ids(
+                    &report_data,
+                    &mut ids,
+                    report_ids.as_mut(),
+                    self.config.leading_zeros,
+                );
+                report_stats.ids_listed += chunk_id_count;
+                report_stats.new_ids += new_ids;
+                tracing::info!(
+                    "Report {} complete: {} IDs in this chunk, {} total IDs so far",
+                    chunk_label,
+                    chunk_id_count,
+                    format_number(ids.len())
+                );
+            }
+            if report_stats.ids_listed == 0 {
+                warn!(
+                    "Report {} returned no action IDs across {} chunk(s). Check REPORT_CHUNK_START and that the report resource is configured correctly",
+                    report_label,
+                    chunks.len()
+                );
+            }
+        }
+        if let (Some(cache), Some(report_ids)) = (&self.cache, &report_ids)
+            && !report_stats.from_cache
+            && let Err(e) = cache.store(&cache_key, report_ids, report_stats.ids_listed)
+        {
+            warn!("Failed to cache report {}: {:#}", report_label, e);
+        }
+        report_stats.duration_secs = report_start.elapsed().as_secs_f64();
+        Ok(report_stats)
+    }
+
+    /// Fetches one report URL, retrying 504s after `REPORT_RETRY_DELAY_SECS` until it
+    /// succeeds, refreshing the token once on 401, and retrying a body that was cut short
+    /// or garbled up to `REPORT_PARSE_RETRIES` times with backoff.
+    async fn fetch_report(
+        &self,
+        report_url: &Url,
+        label: &str,
+        allow_empty: bool,
+    ) -> anyhow::Result<Vec<ReportResponse>> {
+        let mut auth_token = self
+            .auth_client
+            .get_valid_token()
+            .await
+            .context("Failed to get valid authentication token")?;
+        let mut parse_failures = 0;
+
+        // Outer loop for 504 timeout and parse retries
+        loop {
+            // Inner loop for 401 auth retries (max 2 attempts)
+            for attempt in 0..2 {
+                let request_id = new_request_id();
+                let request = self
+                    .http_client
+                    .get(report_url.as_str())
+                    .header("Authorization", &auth_token)
+                    .header("Content-Type", &self.config.request_content_type)
+                    .header(REQUEST_ID_HEADER, &request_id);
+                let response = http::send(request, self.config.http_trace)
+                    .await
+                    .with_context(|| {
+                        format!("failed to send report request (request ID: {})", request_id)
+                    })?;
+
+                let status = response.status();
 
-            let mut auth_token = self
-                .auth_client
-                .get_valid_token()
-                .await
-                .context("Failed to get valid authentication token")?;
-
-            // Outer loop for 504 timeout retries (infinite until success)
-            'outer: loop {
-                // Inner loop for 401 auth retries (max 2 attempts)
-                for attempt in 0..2 {
-                    let response = self
-                        .http_client
-                        .get(report_url.as_str())
-                        .header("Authorization", &auth_token)
-                        .header("Content-Type", "application/json; charset=utf-8")
-                        .send()
+                // Handle 504 Gateway Timeout - wait REPORT_RETRY_DELAY_SECS and retry from outer loop
+                if status == reqwest::StatusCode::GATEWAY_TIMEOUT {
+                    let wait = std::time::Duration::from_secs(self.config.report_retry_delay_secs);
+                    warn!(
+                        "Received 504 Gateway Timeout for report {} (request ID: {}), waiting {}s before retrying",
+                        label,
+                        request_id,
+                        wait.as_secs()
+                    );
+                    tokio::time::sleep(wait).await;
+                    self.retry_stats.record_gateway_timeout(wait);
+                    auth_token = self
+                        .auth_client
+                        .get_valid_token()
                         .await
-                        .context("failed to send report request")?;
+                        .context("Failed to refresh authentication token after 504")?;
+                    break; // Back to the outer loop to retry
+                }
 
-                    let status = response.status();
-                    
-                    // Handle 504 Gateway Timeout - wait 5 minutes and retry from outer loop
-                    if status == reqwest::StatusCode::GATEWAY_TIMEOUT {
-                        warn!(
-                            "Received 504 Gateway Timeout for report {}/{}, waiting 1 minute before retrying",
-                            idx + 1,
-                            total_reports
-                        );
-                        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-                        auth_token = self
-                            .auth_client
-                            .get_valid_token()
-                            .await
-                            .context("Failed to refresh authentication token after 504")?;
-                        continue 'outer; // Continue outer loop to retry
-                    }
-                    
-                    if status == reqwest::StatusCode::UNAUTHORIZED && attempt == 0 {
+                if status == reqwest::StatusCode::UNAUTHORIZED && attempt == 0 {
+                    warn!(
+                        "Received 401 Unauthorized for report request, refreshing token and retrying"
+                    );
+                    auth_token = self
+                        .auth_client
+                        .refresh_token()
+                        .await
+                        .context("Failed to refresh authentication token after 401")?;
+                    self.retry_stats.record_auth_refresh();
+                    continue;
+                }
+
+                if !status.is_success() {
+                    let error_text = response
+                        .text()
+                        .await
+                        .unwrap_or_else(|_| "failed to get error response".to_string());
+                    error!(
+                        "Report request failed: status {}, request ID: {}, error: {}",
+                        status, request_id, error_text
+                    );
+                    anyhow::bail!(
+                        "Report request failed: status {}, request ID: {}, error: {}",
+                        status,
+                        request_id,
+                        error_text
+                    );
+                }
+
+                // A body that stops partway fails to read the same way a truncated one
+                // fails to parse, so both are retried
+                let parsed = match response.text().await {
+                    Ok(body) => parse_report_body(&body),
+                    Err(e) => Err(ReportParseError {
+                        message: format!("failed to read report response: {}", e),
+                        body_prefix: String::new(),
+                        transient: true,
+                    }),
+                };
+                let report_data = match parsed {
+                    Ok(data) => data,
+                    Err(e) if e.transient && parse_failures < self.config.report_parse_retries => {
+                        parse_failures += 1;
+                        let wait = parse_retry_delay(parse_failures);
                         warn!(
-                            "Received 401 Unauthorized for report request, refreshing token and retrying"
+                            "Report {} (request ID: {}): {}. Retry {} of {} in {}s",
+                            label,
+                            request_id,
+                            e.message,
+                            parse_failures,
+                            self.config.report_parse_retries,
+                            wait.as_secs()
                         );
-                        auth_token = self
-                            .auth_client
-                            .get_valid_token()
-                            .await
-                            .context("Failed to refresh authentication token after 401")?;
-                        continue;
+                        tokio::time::sleep(wait).await;
+                        auth_token = self.auth_client.get_valid_token().await.context(
+                            "Failed to refresh authentication token after a parse retry wait",
+                        )?;
+                        break; // Back to the outer loop to retry
                     }
-
-                    if !status.is_success() {
-                        let error_text = response
-                            .text()
-                            .await
-                            .unwrap_or_else(|_| "failed to get error response".to_string());
+                    Err(e) => {
+                        let cause = if e.transient {
+                            format!("after {} retries", parse_failures)
+                        } else {
+                            "the response is JSON of the wrong shape, so it wasn't retried"
+                                .to_string()
+                        };
                         error!(
-                            "Report request failed: status {}, error: {}",
-                            status, error_text
+                            "Report {} (request ID: {}): {} ({}). Response starts: {}",
+                            label, request_id, e.message, cause, e.body_prefix
                         );
                         anyhow::bail!(
-                            "Report request failed: status {}, error: {}",
-                            status,
-                            error_text
+                            "Report {} (request ID: {}): {} ({}). Response starts: {}",
+                            label,
+                            request_id,
+                            e.message,
+                            cause,
+                            e.body_prefix
                         );
                     }
+                };
 
-                    let report_data: Vec<ReportResponse> = match response
-                        .json()
-                        .await
-                        .context("failed to parse report response")
-                    {
-                        Ok(data) => data,
-                        Err(e) => {
-                            error!("Failed to parse report response: {}", e);
-                            return Err(e);
-                        }
-                    };
-
-                    if report_data.is_empty() {
-                        error!("Report response is empty");
-                        anyhow::bail!("Report response is empty");
-                    }
+                if report_data.is_empty() && !allow_empty {
+                    error!("Report response is empty");
+                    anyhow::bail!("Report response is empty");
+                }
+                return Ok(report_data);
+            }
+        }
+    }
+}
 
-                    for row in &report_data {
-                        for id_str in row.action_ids.split(',') {
-                            let id_str = id_str.trim();
-                            if !id_str.is_empty() {
-                                all_existing_ids.insert(id_str.to_string());
-                            }
-                        }
-                    }
+/// A report body that couldn't be read as report rows.
+#[derive(Debug)]
+struct ReportParseError {
+    message: String,
+    /// The start of the body, for diagnosis.
+    body_prefix: String,
+    /// Cut short or garbled, so a retry may succeed; valid JSON of the wrong shape isn't.
+    transient: bool,
+}
 
-                    tracing::info!(
-                        "Report {}/{} complete: {} IDs in this report, {} total IDs so far",
-                        idx + 1,
-                        total_reports,
-                        report_data.iter().fold(0, |acc, row| {
-                            acc + row
-                                .action_ids
-                                .split(',')
-                                .filter(|s| !s.trim().is_empty())
-                                .count()
-                        }),
-                        format_number(all_existing_ids.len())
-                    );
-                    break 'outer; // Success - break outer loop and move to next report
+fn parse_report_body(body: &str) -> Result<Vec<ReportResponse>, ReportParseError> {
+    serde_json::from_str(body).map_err(|e| {
+        let mut body_prefix: String = body.chars().take(REPORT_BODY_PREFIX_CHARS).collect();
+        if body_prefix.len() < body.len() {
+            body_prefix.push_str("...");
+        }
+        ReportParseError {
+            message: format!("failed to parse report response: {}", e),
+            body_prefix,
+            transient: matches!(
+                e.classify(),
+                Category::Eof | Category::Syntax | Category::Io
+            ),
+        }
+    })
+}
+
+fn lock_ids(ids: &Mutex<HashSet<String>>) -> anyhow::Result<MutexGuard<'_, HashSet<String>>> {
+    ids.lock()
+        .map_err(|_| anyhow::anyhow!("existing action ID set lock poisoned"))
+}
+
+/// Adds a report's IDs to `ids`, and to `report_ids` when the report is being cached,
+/// returning how many the report listed and how many were new to `ids`.
+fn add_report_ids(
+    report_data: &[ReportResponse],
+    ids: &mut HashSet<String>,
+    mut report_ids: Option<&mut HashSet<String>>,
+    leading_zeros: LeadingZeros,
+) -> (usize, usize) {
+    let mut count = 0;
+    let mut new_ids = 0;
+    for row in report_data {
+        for id_str in row.action_ids.split(',') {
+            let raw_id = id_str.trim();
+            // Matched against rows whose IDs had the same treatment
+            let id_str = match leading_zeros {
+                LeadingZeros::Strip => strip_leading_zeros(raw_id),
+                LeadingZeros::Keep => raw_id,
+            };
+            if !id_str.is_empty() {
+                // Cached as listed, so a later run can apply its own LEADING_ZEROS mode
+                if let Some(report_ids) = report_ids.as_deref_mut() {
+                    report_ids.insert(raw_id.to_string());
+                }
+                if ids.insert(id_str.to_string()) {
+                    new_ids += 1;
                 }
+                count += 1;
             }
         }
+    }
+    (count, new_ids)
+}
 
-        tracing::info!(
-            "Completed fetching all reports: {} total existing action IDs",
-            format_number(all_existing_ids.len())
+/// Adds a cached report's IDs, which are stored as the report listed them, under this run's
+/// LEADING_ZEROS mode. Returns how many were new to `ids`.
+fn add_cached_ids(
+    cached_ids: Vec<String>,
+    ids: &mut HashSet<String>,
+    leading_zeros: LeadingZeros,
+) -> usize {
+    let mut new_ids = 0;
+    for id in cached_ids {
+        let id = match leading_zeros {
+            LeadingZeros::Strip => strip_leading_zeros(&id).to_string(),
+            LeadingZeros::Keep => id,
+        };
+        if ids.insert(id) {
+            new_ids += 1;
+        }
+    }
+    new_ids
+}
+
+/// Splits `start..=today` into windows of `days` days as `(from, to)` pairs, where `to` is
+/// the day after the window's last day so reports can filter `>= {from}` and `< {to}`.
+fn report_chunks(start: NaiveDate, today: NaiveDate, days: u32) -> Vec<(NaiveDate, NaiveDate)> {
+    let end = today + Days::new(1);
+    let mut chunks = Vec::new();
+    let mut from = start;
+    while from < end {
+        let to = (from + Days::new(u64::from(days))).min(end);
+        chunks.push((from, to));
+        from = to;
+    }
+    chunks
+}
+
+/// Substitutes a chunk's dates into the `{from}`/`{to}` placeholders of a report URL.
+fn report_chunk_url(report_url: &Url, from: NaiveDate, to: NaiveDate) -> anyhow::Result<Url> {
+    let from = from.format("%Y-%m-%d").to_string();
+    let to = to.format("%Y-%m-%d").to_string();
+    let mut url = report_url.as_str().to_string();
+    for (placeholder, date) in [
+        (REPORT_FROM_PLACEHOLDER, &from),
+        (REPORT_TO_PLACEHOLDER, &to),
+    ] {
+        url = url
+            .replace(placeholder, date)
+            .replace(&encode_braces(placeholder), date);
+    }
+    Url::parse(&url)
+        .with_context(|| format!("invalid report URL after substituting dates: {}", url))
+}
+
+/// Wait before parse retry number `retry`, counting from 1: doubled each retry up to
+/// `REPORT_PARSE_RETRY_MAX_DELAY`.
+fn parse_retry_delay(retry: usize) -> Duration {
+    u32::try_from(retry.saturating_sub(1))
+        .ok()
+        .and_then(|doublings| 2u32.checked_pow(doublings))
+        .and_then(|factor| REPORT_PARSE_RETRY_BASE_DELAY.checked_mul(factor))
+        .map_or(REPORT_PARSE_RETRY_MAX_DELAY, |wait| {
+            wait.min(REPORT_PARSE_RETRY_MAX_DELAY)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn parse_retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(parse_retry_delay(1), Duration::from_secs(5));
+        assert_eq!(parse_retry_delay(3), Duration::from_secs(20));
+        assert_eq!(parse_retry_delay(7), REPORT_PARSE_RETRY_MAX_DELAY);
+        assert_eq!(parse_retry_delay(40), REPORT_PARSE_RETRY_MAX_DELAY);
+        assert_eq!(parse_retry_delay(usize::MAX), REPORT_PARSE_RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn only_cut_short_or_garbled_bodies_are_retried() {
+        let rows = parse_report_body(r#"[{"group_num": "1", "action_ids": "1001,1002"}]"#).unwrap();
+        assert_eq!(rows[0].action_ids, "1001,1002");
+
+        let truncated = parse_report_body(r#"[{"group_num": "1", "action_ids": "10"#).unwrap_err();
+        assert!(truncated.transient);
+        assert!(
+            parse_report_body("<html>502 Bad Gateway</html>")
+                .unwrap_err()
+                .transient
         );
-        Ok(all_existing_ids)
+
+        let wrong_shape = parse_report_body(r#"{"error": "no such report"}"#).unwrap_err();
+        assert!(!wrong_shape.transient);
+        assert_eq!(wrong_shape.body_prefix, r#"{"error": "no such report"}"#);
+        let long = format!("[{}", "x".repeat(REPORT_BODY_PREFIX_CHARS * 2));
+        assert!(
+            parse_report_body(&long)
+                .unwrap_err()
+                .body_prefix
+                .ends_with("...")
+        );
+    }
+
+    #[test]
+    fn cached_ids_follow_the_leading_zeros_mode_of_the_run_loading_them() {
+        let output_dir = TempPath::new("cached_zeros");
+        let cache = ReportCache::new(&output_dir, Duration::from_secs(60));
+        let rows =
+            parse_report_body(r#"[{"group_num": "1", "action_ids": "00456, 789"}]"#).unwrap();
+        let (mut stripped, mut report_ids) = (HashSet::new(), HashSet::new());
+        add_report_ids(
+            &rows,
+            &mut stripped,
+            Some(&mut report_ids),
+            LeadingZeros::Strip,
+        );
+        assert_eq!(
+            stripped,
+            HashSet::from(["456".to_string(), "789".to_string()])
+        );
+        cache
+            .store("https://halo/report/a", &report_ids, 2)
+            .unwrap();
+
+        let cached = cache.load("https://halo/report/a").unwrap();
+        let mut kept = HashSet::new();
+        assert_eq!(add_cached_ids(cached.ids, &mut kept, LeadingZeros::Keep), 2);
+        assert_eq!(
+            kept,
+            HashSet::from(["00456".to_string(), "789".to_string()])
+        );
+    }
+
+    #[test]
+    fn chunks_cover_range_and_fill_placeholders() {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let chunks = report_chunks(date("2024-01-01"), date("2024-01-20"), 7);
+        assert_eq!(
+            chunks,
+            vec![
+                (date("2024-01-01"), date("2024-01-08")),
+                (date("2024-01-08"), date("2024-01-15")),
+                (date("2024-01-15"), date("2024-01-21")),
+            ]
+        );
+
+        let mut url = Url::parse("https://halo.example/").unwrap();
+        url.set_path("/api/ReportData/uuid-1/{from}");
+        url.set_query(Some("to={to}"));
+        assert!(report_url_is_chunked(&url));
+        let chunk_url = report_chunk_url(&url, chunks[0].0, chunks[0].1).unwrap();
+        assert_eq!(
+            chunk_url.as_str(),
+            "https://halo.example/api/ReportData/uuid-1/2024-01-01?to=2024-01-08"
+        );
+    }
+
+    #[test]
+    fn report_ids_count_listed_and_new() {
+        let rows = |ids: &str| {
+            vec![ReportResponse {
+                _group_num: "1".to_string(),
+                action_ids: ids.to_string(),
+            }]
+        };
+        let mut ids = HashSet::new();
+        let mut report_ids = HashSet::new();
+        assert_eq!(
+            add_report_ids(
+                &rows("1, 2,2,"),
+                &mut ids,
+                Some(&mut report_ids),
+                LeadingZeros::Strip
+            ),
+            (3, 2)
+        );
+        // A second report sharing the same set only counts IDs it added
+        assert_eq!(
+            add_report_ids(&rows("2,3"), &mut ids, None, LeadingZeros::Strip),
+            (2, 1)
+        );
+        assert_eq!(ids.len(), 3);
+        assert_eq!(report_ids.len(), 2);
+    }
+
+    #[test]
+    fn report_ids_with_leading_zeros_match_by_mode() {
+        let rows = vec![ReportResponse {
+            _group_num: "1".to_string(),
+            action_ids: "00456,457".to_string(),
+        }];
+        let mut stripped = HashSet::new();
+        add_report_ids(&rows, &mut stripped, None, LeadingZeros::Strip);
+        assert!(stripped.contains("456"));
+        assert!(!stripped.contains("00456"));
+
+        let mut kept = HashSet::new();
+        add_report_ids(&rows, &mut kept, None, LeadingZeros::Keep);
+        assert!(kept.contains("00456"));
+        assert!(!kept.contains("456"));
     }
 }
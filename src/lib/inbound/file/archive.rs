@@ -0,0 +1,158 @@
+use crate::inbound::file::InputKind;
+use anyhow::Context;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Spreadsheets extracted from one zip archive in the input directory.
+#[derive(Debug, Default)]
+pub struct ExtractedArchive {
+    /// Each extracted file, with its name for logs: the archive name and the entry's path
+    /// inside it, e.g. `clients.zip/march/actions.csv`.
+    pub files: Vec<(PathBuf, String)>,
+    /// Extensions of the entries that were left out, such as `.pdf`.
+    pub ignored_extensions: Vec<String>,
+}
+
+/// Where this process extracts archives. Each run gets its own directory, so concurrent
+/// runs on the same machine don't overwrite each other's files.
+pub fn extraction_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("halo_action_importer_zip_{}", std::process::id()))
+}
+
+/// Deletes everything this process extracted. Does nothing if no archive was read.
+pub fn remove_extracted_archives() {
+    let dir = extraction_dir();
+    if dir.exists()
+        && let Err(e) = std::fs::remove_dir_all(&dir)
+    {
+        tracing::warn!(
+            "Failed to remove extracted archive files in {}: {}",
+            dir.display(),
+            e
+        );
+    }
+}
+
+/// Whether a zip entry is macOS metadata or a hidden or Office lock file, not a real file.
+fn is_junk_entry(entry_path: &Path) -> bool {
+    entry_path.components().any(|part| {
+        let part = part.as_os_str().to_string_lossy();
+        part == "__MACOSX" || part.starts_with('.') || part.starts_with("~$")
+    })
+}
+
+/// Extracts the CSV and Excel entries of a zip archive into [`extraction_dir`], from any
+/// folder inside it.
+///
+/// Entries are written under flat, numbered names, so an entry path can never point
+/// outside the extraction directory and two entries with the same file name in different
+/// folders don't collide.
+pub fn extract_archive(zip_path: &Path) -> anyhow::Result<ExtractedArchive> {
+    let zip_name = zip_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown.zip")
+        .to_string();
+    let file = File::open(zip_path)
+        .with_context(|| format!("Failed to open zip archive: {}", zip_name))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive: {}", zip_name))?;
+    let dest = extraction_dir().join(&zip_name);
+    std::fs::create_dir_all(&dest).with_context(|| {
+        format!(
+            "Failed to create directory for extracted files: {}",
+            dest.display()
+        )
+    })?;
+
+    let mut extracted = ExtractedArchive::default();
+    for idx in 0..archive.len() {
+        let mut entry = archive
+            .by_index(idx)
+            .with_context(|| format!("Failed to read entry {} of {}", idx + 1, zip_name))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let entry_name = entry.name().to_string();
+        let Some(entry_path) = entry.enclosed_name() else {
+            tracing::warn!("Skipping unsafe path {:?} in {}", entry_name, zip_name);
+            continue;
+        };
+        if is_junk_entry(&entry_path) {
+            continue;
+        }
+        match InputKind::of(&entry_path) {
+            Some(InputKind::Csv | InputKind::Spreadsheet) => {
+                let file_name = entry_path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or("entry");
+                let out_path = dest.join(format!("{}_{}", idx + 1, file_name));
+                let mut out = File::create(&out_path).with_context(|| {
+                    format!("Failed to create extracted file: {}", out_path.display())
+                })?;
+                std::io::copy(&mut entry, &mut out).with_context(|| {
+                    format!("Failed to extract {} from {}", entry_name, zip_name)
+                })?;
+                extracted
+                    .files
+                    .push((out_path, format!("{}/{}", zip_name, entry_name)));
+            }
+            _ => extracted.ignored_extensions.push(
+                entry_path
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .map(|ext| format!(".{}", ext.to_lowercase()))
+                    .unwrap_or_else(|| "(no extension)".to_string()),
+            ),
+        }
+    }
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn extracts_spreadsheets_from_every_folder() {
+        let zip_path = TempPath::new("archive.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        for (name, contents) in [
+            ("actions.csv", "top"),
+            ("march/actions.csv", "nested"),
+            ("march/notes.pdf", "not a sheet"),
+            ("__MACOSX/march/._actions.csv", "resource fork"),
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer
+            .add_directory("empty/", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let extracted = extract_archive(&zip_path).unwrap();
+        let names: Vec<&str> = extracted.files.iter().map(|(_, n)| n.as_str()).collect();
+        let nested = std::fs::read_to_string(&extracted.files[1].0).unwrap();
+        remove_extracted_archives();
+
+        let zip_name = zip_path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(
+            names,
+            vec![
+                format!("{}/actions.csv", zip_name),
+                format!("{}/march/actions.csv", zip_name)
+            ]
+        );
+        assert_eq!(nested, "nested");
+        assert_eq!(extracted.ignored_extensions, vec![".pdf"]);
+        assert!(!extraction_dir().exists());
+    }
+}
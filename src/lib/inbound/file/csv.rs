@@ -1,49 +1,128 @@
-use crate::{domain::models::action_object::ActionObject, inbound::file::Reader};
+use crate::{
+    domain::models::action_object::ActionObject,
+    inbound::file::{Reader, mapping::ColumnMapping},
+};
 use anyhow::Context;
-use csv::ReaderBuilder;
+use csv::{ReaderBuilder, Terminator};
+use std::io::Read;
 use std::path::Path;
 
+/// Builder shared by the row-count pass and the deserialization pass so both see the
+/// same records. Quoted fields may contain `\r\n`/`\n` and commas; any of `\r\n`,
+/// `\n`, or `\r` ends a record; `flexible` tolerates rows with a differing field count
+/// instead of failing the whole record.
+fn csv_reader_builder() -> ReaderBuilder {
+    let mut builder = ReaderBuilder::new();
+    builder
+        .has_headers(true)
+        .flexible(true)
+        .terminator(Terminator::CRLF);
+    builder
+}
+
 pub trait Csv {
     fn try_csv_to_action_objects(path: &Path) -> anyhow::Result<Vec<ActionObject>>;
-    fn csv_action_iter(path: &Path) -> anyhow::Result<CsvActionIterator>;
+    fn csv_action_iter(path: &Path, mapping: &ColumnMapping) -> anyhow::Result<CsvActionIterator>;
+    /// Parses CSV from any reader (e.g. an in-memory buffer); `source_name` is used in
+    /// error messages. No row-count pass is possible, so `total_rows` is `None`.
+    fn csv_action_iter_from_reader<R: Read>(
+        reader: R,
+        source_name: &str,
+        mapping: &ColumnMapping,
+    ) -> CsvActionIterator<R>;
 }
 
-pub struct CsvActionIterator {
-    rdr: csv::DeserializeRecordsIntoIter<std::fs::File, ActionObject>,
+pub struct CsvActionIterator<R: Read = std::fs::File> {
+    rdr: csv::StringRecordsIntoIter<R>,
     file_name: String,
+    headers: Vec<String>,
+    /// Mapped header row records are deserialized against; `None` if it was unreadable.
+    header_record: Option<csv::StringRecord>,
+    extra_note_indices: Vec<usize>,
     row_num: usize,
     total_rows: Option<usize>,
+    mapping: ColumnMapping,
+    has_outcome_column: bool,
 }
 
-impl CsvActionIterator {
+impl<R: Read> CsvActionIterator<R> {
     pub fn total_rows(&self) -> Option<usize> {
         self.total_rows
     }
+
+    /// Source headers after column mapping, empty if the header row was unreadable.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// The row's error with the column and value it came from when a single field failed
+    /// to parse, followed by the file's headers so a missing or misnamed column stands out.
+    fn describe_error(&self, e: &csv::Error, record: &csv::StringRecord) -> String {
+        let headers = self.headers.join(", ");
+        if let csv::ErrorKind::Deserialize { err, .. } = e.kind()
+            && let Some(idx) = err.field()
+        {
+            let idx = idx as usize;
+            return format!(
+                "column {} ('{}') has value '{}': {}. Headers: [{}]",
+                idx + 1,
+                self.headers
+                    .get(idx)
+                    .map(String::as_str)
+                    .unwrap_or("unnamed"),
+                record.get(idx).unwrap_or_default(),
+                err.kind(),
+                headers
+            );
+        }
+        format!("{}. Headers: [{}]", e, headers)
+    }
 }
 
-impl Iterator for CsvActionIterator {
+impl<R: Read> Iterator for CsvActionIterator<R> {
     type Item = anyhow::Result<ActionObject>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.rdr.next() {
-            Some(Ok(action)) => {
-                self.row_num += 1;
+        let record = self.rdr.next()?;
+        // Counted whether or not the row parses, so each error names its own row
+        self.row_num += 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                return Some(Err(anyhow::anyhow!(
+                    "failed to read row {} in csv file: {}: {}",
+                    self.row_num,
+                    self.file_name,
+                    e
+                )));
+            }
+        };
+        match record.deserialize::<ActionObject>(self.header_record.as_ref()) {
+            Ok(mut action) => {
+                let extras = self
+                    .extra_note_indices
+                    .iter()
+                    .filter_map(|&idx| record.get(idx));
+                self.mapping.append_extra_notes(&mut action, extras);
+                self.mapping
+                    .apply_defaults(&mut action, self.has_outcome_column);
+                action.read_numeric_ticket_id();
+                action.read_date();
                 Some(Ok(action))
             }
-            Some(Err(e)) => Some(Err(anyhow::anyhow!(
+            Err(e) => Some(Err(anyhow::anyhow!(
                 "failed to deserialize row {} in csv file: {}: {}",
-                self.row_num + 1,
+                self.row_num,
                 self.file_name,
-                e
+                self.describe_error(&e, &record)
             ))),
-            None => None,
         }
     }
 }
 
 impl Csv for Reader {
     fn try_csv_to_action_objects(path: &Path) -> anyhow::Result<Vec<ActionObject>> {
-        let iter = Self::csv_action_iter(path)?;
+        let iter = Self::csv_action_iter(path, &ColumnMapping::default())?;
         let mut output = Vec::new();
         for result in iter {
             output.push(result?);
@@ -51,7 +130,7 @@ impl Csv for Reader {
         Ok(output)
     }
 
-    fn csv_action_iter(path: &Path) -> anyhow::Result<CsvActionIterator> {
+    fn csv_action_iter(path: &Path, mapping: &ColumnMapping) -> anyhow::Result<CsvActionIterator> {
         let file_name = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -60,7 +139,7 @@ impl Csv for Reader {
         let total_rows = {
             let file = std::fs::File::open(path)
                 .with_context(|| format!("failed to open csv file: {}", file_name))?;
-            let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
+            let mut rdr = csv_reader_builder().from_reader(file);
             let mut count = 0;
             let mut records = rdr.records();
             while records.next().is_some() {
@@ -70,12 +149,153 @@ impl Csv for Reader {
         };
         let file = std::fs::File::open(path)
             .with_context(|| format!("failed to open csv file: {}", file_name))?;
-        let rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
-        Ok(CsvActionIterator {
-            rdr: rdr.into_deserialize(),
-            file_name,
+        let mut iter = Self::csv_action_iter_from_reader(file, &file_name, mapping);
+        iter.total_rows = total_rows;
+        Ok(iter)
+    }
+
+    fn csv_action_iter_from_reader<R: Read>(
+        reader: R,
+        source_name: &str,
+        mapping: &ColumnMapping,
+    ) -> CsvActionIterator<R> {
+        let mut rdr = csv_reader_builder().from_reader(reader);
+        // An unreadable header row surfaces as an error on the first record instead
+        let mut has_outcome_column = true;
+        let mut headers = Vec::new();
+        let mut header_record = None;
+        if let Ok(source_headers) = rdr.headers() {
+            let mapped: csv::StringRecord = source_headers
+                .iter()
+                .map(|h| mapping.map_header(h))
+                .collect();
+            has_outcome_column = ColumnMapping::has_outcome_column(mapped.iter());
+            headers = mapped.iter().map(str::to_string).collect();
+            let keys: csv::StringRecord = mapped.iter().map(ColumnMapping::field_key).collect();
+            rdr.set_headers(keys.clone());
+            header_record = Some(keys);
+        }
+        CsvActionIterator {
+            rdr: rdr.into_records(),
+            file_name: source_name.to_string(),
+            extra_note_indices: mapping.extra_note_indices(&headers, source_name),
+            headers,
+            header_record,
             row_num: 0,
-            total_rows,
-        })
+            total_rows: None,
+            mapping: mapping.clone(),
+            has_outcome_column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn crlf_and_embedded_newlines_in_quoted_notes() {
+        let data = "ticket_id,note,actionwho,cfactionid,actionDate\r\n\
+                    1,\"line one\r\nline two, with a comma\",tester,456,2024-01-01 10:00:00\r\n\
+                    2,plain note,tester,457,\r\n";
+        let actions: Vec<ActionObject> = <Reader as Csv>::csv_action_iter_from_reader(
+            data.as_bytes(),
+            "crlf.csv",
+            &ColumnMapping::default(),
+        )
+        .map(|r| r.unwrap())
+        .collect();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].note, "line one\r\nline two, with a comma");
+        assert_eq!(actions[1].action_id(), "457");
+
+        // Only a file can be counted ahead of reading
+        let path = TempPath::file("crlf.csv", data);
+        let iter = <Reader as Csv>::csv_action_iter(&path, &ColumnMapping::default()).unwrap();
+        assert_eq!(iter.total_rows(), Some(2));
+    }
+
+    #[test]
+    fn errors_name_the_failing_column_and_row() {
+        let data = "ticket_id,note,actionwho,cfactionid,actionDate,_isimport\n\
+                    1,first,tester,1001,,maybe\n\
+                    1,second,tester,1002,,true\n\
+                    ,third,tester,1003,,true\n";
+        let results: Vec<_> = <Reader as Csv>::csv_action_iter_from_reader(
+            data.as_bytes(),
+            "bad.csv",
+            &ColumnMapping::default(),
+        )
+        .collect();
+        let first = results[0].as_ref().unwrap_err().to_string();
+        assert!(first.contains("row 1 "), "{}", first);
+        assert!(
+            first.contains("column 6 ('_isimport') has value 'maybe'"),
+            "{}",
+            first
+        );
+        assert!(
+            first.contains(
+                "Headers: [ticket_id, note, actionwho, cfactionid, actionDate, _isimport]"
+            ),
+            "{}",
+            first
+        );
+        assert!(results[1].is_ok());
+        let third = results[2].as_ref().unwrap_err().to_string();
+        assert!(third.contains("row 3 "), "{}", third);
+        assert!(third.contains("ticket ID is blank"), "{}", third);
+    }
+
+    #[test]
+    fn parses_from_in_memory_buffer() {
+        let data = "requestId,note,actionWho,CFactionId,actionDate\n42,hello,tester,1001,\n";
+        let mut iter = <Reader as Csv>::csv_action_iter_from_reader(
+            data.as_bytes(),
+            "buffer",
+            &ColumnMapping::default(),
+        );
+        assert_eq!(iter.total_rows(), None);
+        let action = iter.next().unwrap().unwrap();
+        assert_eq!(action.ticket_id, 42);
+        assert_eq!(action.action_id(), "1001");
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn headers_match_ignoring_case_spaces_and_underscores() {
+        let data = " Request ID ,ACTION_DATE,Note,Action Who,CF Action Id\n\
+                    42,2024-01-15 09:30:00,hello,tester,1001\n";
+        let action = <Reader as Csv>::csv_action_iter_from_reader(
+            data.as_bytes(),
+            "buffer",
+            &ColumnMapping::default(),
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        assert_eq!(action.ticket_id, 42);
+        assert!(action.actiondate.is_some());
+        assert_eq!(action.note, "hello");
+        assert_eq!(action.actionwho, "tester");
+        assert_eq!(action.action_id(), "1001");
+    }
+
+    #[test]
+    fn extra_note_columns_are_appended() {
+        let data = "ticket_id,Summary,note,actionwho,cfactionid,actionDate,Detail\n\
+                    1,Printer down,,tester,1001,,Replaced toner\n\
+                    2,,Called back,tester,1002,,\n";
+        let mapping = ColumnMapping {
+            extra_note_columns: vec!["summary".to_string(), "detail".to_string()],
+            extra_note_separator: "\n".to_string(),
+            ..ColumnMapping::default()
+        };
+        let notes: Vec<String> =
+            <Reader as Csv>::csv_action_iter_from_reader(data.as_bytes(), "buffer", &mapping)
+                .map(|r| r.unwrap().note)
+                .collect();
+        assert_eq!(notes, vec!["Printer down\nReplaced toner", "Called back"]);
     }
 }
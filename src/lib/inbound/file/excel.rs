@@ -1,8 +1,52 @@
-use crate::{domain::models::action_object::ActionObject, inbound::file::Reader};
-use anyhow::Context;
-use calamine::{Data, Reader as CalamineReader, open_workbook_auto};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use crate::{
+    config::EmptyNumericCell,
+    domain::models::action_object::ActionObject,
+    inbound::file::{Reader, mapping::ColumnMapping},
+};
+use calamine::{
+    Data, OdsError, Reader as CalamineReader, Sheets, XlsError, Xlsx, open_workbook_auto,
+    open_workbook_auto_from_rs,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use std::io::{Cursor, Read, Seek};
 use std::path::Path;
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+
+/// First bytes of an OLE compound file, the container of `.xls` files and of encrypted
+/// `.xlsx` files.
+const OLE_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Whether the bytes are an encrypted `.xlsx`: an OLE file holding an `EncryptionInfo`
+/// stream in place of the usual zip archive, which calamine only reports as a zip error.
+fn is_encrypted_ooxml(bytes: &[u8]) -> bool {
+    if !bytes.starts_with(&OLE_SIGNATURE) {
+        return false;
+    }
+    // Stream names in the OLE directory are UTF-16LE
+    let name: Vec<u8> = "EncryptionInfo"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    bytes.windows(name.len()).any(|window| window == name)
+}
+
+/// The error for a workbook that failed to open, saying so plainly when it is
+/// password-protected rather than passing on calamine's parse error.
+fn open_error(error: calamine::Error, encrypted_ooxml: bool, file_name: &str) -> anyhow::Error {
+    let password_protected = encrypted_ooxml
+        || matches!(
+            error,
+            calamine::Error::Xls(XlsError::Password) | calamine::Error::Ods(OdsError::Password)
+        );
+    if password_protected {
+        anyhow::anyhow!(
+            "Excel file {} is password-protected; decrypt it before importing",
+            file_name
+        )
+    } else {
+        anyhow::Error::new(error).context(format!("failed to open excel file: {}", file_name))
+    }
+}
 
 fn excel_serial_to_datetime(serial: f64) -> Option<NaiveDateTime> {
     let excel_epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?;
@@ -15,76 +59,223 @@ fn excel_serial_to_datetime(serial: f64) -> Option<NaiveDateTime> {
 
 pub trait Excel {
     fn try_excel_to_action_objects(path: &Path) -> anyhow::Result<Vec<ActionObject>>;
-    fn excel_action_iter(path: &Path) -> anyhow::Result<ExcelActionIterator>;
+    fn excel_action_iter(
+        path: &Path,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<ExcelActionIterator>;
+    /// Parses a workbook held in memory (xlsx, xls, xlsb, or ods, detected from the
+    /// content); `source_name` is used in log and error messages.
+    fn excel_action_iter_from_bytes(
+        bytes: Vec<u8>,
+        source_name: &str,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<ExcelActionIterator>;
+}
+
+/// Rows buffered between the reader thread and the iterator.
+const STREAM_BUFFER_ROWS: usize = 1_000;
+
+/// What the reader thread sends: the header row first, then data rows numbered from 1,
+/// then `End` once the whole sheet was read. A channel that closes without `End` means the
+/// thread died (a panic in calamine), so the sheet was cut short.
+enum SheetEvent {
+    Header {
+        cells: Vec<Data>,
+        total_rows: Option<usize>,
+    },
+    Row(usize, Vec<Data>),
+    End,
 }
 
+/// Streams rows of the first worksheet from a background reader thread.
+///
+/// xlsx sheets are read cell by cell, so only the buffered rows are held in memory.
+/// Other formats are loaded as one range on the reader thread and handed over row by row.
 pub struct ExcelActionIterator {
-    rows: Vec<Vec<Data>>,
+    rows: Receiver<anyhow::Result<SheetEvent>>,
+    /// Set once `End`, an error, or a dead reader thread has been seen.
+    finished: bool,
     headers: Vec<String>,
+    /// Key each column is deserialized under (`ColumnMapping::field_key`).
+    keys: Vec<String>,
     file_name: String,
     sheet_name: String,
-    row_num: usize,
+    total_rows: Option<usize>,
+    mapping: ColumnMapping,
+    has_outcome_column: bool,
+    extra_note_indices: Vec<usize>,
 }
 
 impl ExcelActionIterator {
-    pub fn total_rows(&self) -> usize {
-        self.rows.len()
+    /// Data rows in the sheet, from the sheet's declared dimensions when streaming.
+    /// `None` when the workbook doesn't record them.
+    pub fn total_rows(&self) -> Option<usize> {
+        self.total_rows
     }
 
     pub fn sheet_name(&self) -> &str {
         &self.sheet_name
     }
+
+    /// Header row of the sheet after column mapping.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
 }
 
 impl Iterator for ExcelActionIterator {
     type Item = anyhow::Result<ActionObject>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.row_num >= self.rows.len() {
-            return None;
-        }
-        let row = &self.rows[self.row_num];
-        let row_num_for_error = self.row_num + 1;
-        let mut record = serde_json::Map::new();
-        let mut has_any_data = false;
-        for (idx, header) in self.headers.iter().enumerate() {
-            let header_lower = header.to_lowercase();
-            let is_date_field = header_lower == "actiondate"
-                || header_lower == "action_date"
-                || header == "actionDate"
-                || header == "ActionDate"
-                || header == "ACTIONDATE";
-            let is_numeric_field = header_lower == "requestid"
-                || header_lower == "request_id"
-                || header == "requestId"
-                || header == "RequestId"
-                || header == "REQUESTID";
-            let cell_value = if idx < row.len() {
-                match &row[idx] {
-                    Data::Empty => {
-                        if is_date_field {
-                            serde_json::Value::Null
-                        } else {
-                            serde_json::Value::String(String::new())
+        // Blank rows are skipped iteratively so long runs of them can't overflow the stack
+        loop {
+            if self.finished {
+                return None;
+            }
+            let (row_num_for_error, row) = match self.rows.recv() {
+                Ok(Ok(SheetEvent::Row(row_num, row))) => (row_num, row),
+                Ok(Ok(SheetEvent::Header { .. })) => continue,
+                Ok(Ok(SheetEvent::End)) => {
+                    self.finished = true;
+                    return None;
+                }
+                Ok(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                Err(_) => {
+                    self.finished = true;
+                    return Some(Err(reader_died(&self.sheet_name, &self.file_name)));
+                }
+            };
+            let mut record = serde_json::Map::new();
+            let mut has_any_data = false;
+            let mut empty_numeric_field = None;
+            for (idx, (header, key)) in self.headers.iter().zip(&self.keys).enumerate() {
+                let is_date_field = key == "actiondate";
+                let is_numeric_field = key == "ticket_id";
+                if matches!(key.as_str(), "ticket_id" | "action_id")
+                    && empty_numeric_field.is_none()
+                    && row.get(idx).is_none_or(|cell| match cell {
+                        Data::Empty => true,
+                        Data::String(s) => s.trim().is_empty(),
+                        _ => false,
+                    })
+                {
+                    empty_numeric_field = Some((key.as_str(), header.as_str()));
+                }
+                let cell_value = if idx < row.len() {
+                    match &row[idx] {
+                        Data::Empty => {
+                            if is_date_field {
+                                serde_json::Value::Null
+                            } else {
+                                serde_json::Value::String(String::new())
+                            }
                         }
-                    }
-                    Data::String(s) => {
-                        let trimmed = s.trim();
-                        if !trimmed.is_empty() {
-                            has_any_data = true;
+                        Data::String(s) => {
+                            let trimmed = s.trim();
+                            if !trimmed.is_empty() {
+                                has_any_data = true;
+                            }
+                            if is_date_field {
+                                if trimmed.is_empty() {
+                                    serde_json::Value::Null
+                                } else if let Ok(serial) = trimmed.parse::<f64>() {
+                                    match excel_serial_to_datetime(serial) {
+                                        Some(dt) => serde_json::Value::String(
+                                            dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                                        ),
+                                        None => serde_json::Value::String(s.clone()),
+                                    }
+                                } else if DateTime::parse_from_rfc3339(trimmed).is_ok() {
+                                    // Zoned values keep their offset so they aren't re-read as local time
+                                    serde_json::Value::String(trimmed.to_string())
+                                } else {
+                                    let dt_clean =
+                                        trimmed.trim_end_matches('Z').trim_end_matches('z');
+                                    match NaiveDateTime::parse_from_str(
+                                        dt_clean,
+                                        "%Y-%m-%dT%H:%M:%S%.f",
+                                    ) {
+                                        Ok(ndt) => serde_json::Value::String(
+                                            ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                                        ),
+                                        Err(_) => {
+                                            match NaiveDateTime::parse_from_str(
+                                                dt_clean,
+                                                "%Y-%m-%dT%H:%M:%S",
+                                            ) {
+                                                Ok(ndt) => serde_json::Value::String(
+                                                    ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                                                ),
+                                                Err(_) => serde_json::Value::String(s.clone()),
+                                            }
+                                        }
+                                    }
+                                }
+                            } else if is_numeric_field {
+                                if let Some(num) = self.mapping.number_locale.parse_u32(trimmed) {
+                                    serde_json::Value::Number(num.into())
+                                } else {
+                                    serde_json::Value::String(s.clone())
+                                }
+                            } else {
+                                serde_json::Value::String(s.clone())
+                            }
                         }
-                        if is_date_field {
-                            if trimmed.is_empty() {
-                                serde_json::Value::Null
-                            } else if let Ok(serial) = trimmed.parse::<f64>() {
-                                match excel_serial_to_datetime(serial) {
+                        Data::Float(f) => {
+                            has_any_data = true;
+                            if is_date_field {
+                                match excel_serial_to_datetime(*f) {
                                     Some(dt) => serde_json::Value::String(
                                         dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
                                     ),
-                                    None => serde_json::Value::String(s.clone()),
+                                    None => serde_json::Value::Null,
                                 }
                             } else {
-                                let dt_clean = trimmed.trim_end_matches('Z').trim_end_matches('z');
+                                // A ticket ID is kept as written, so the processor can reject
+                                // one that is negative, fractional, or too large
+                                serde_json::Value::String(f.to_string())
+                            }
+                        }
+                        Data::Int(i) => {
+                            has_any_data = true;
+                            serde_json::Value::String(i.to_string())
+                        }
+                        Data::Bool(b) => {
+                            has_any_data = true;
+                            serde_json::Value::String(
+                                self.mapping.bool_format.format(*b).to_string(),
+                            )
+                        }
+                        Data::DateTime(dt) => {
+                            has_any_data = true;
+                            if is_date_field {
+                                let serial = dt.as_f64();
+                                match excel_serial_to_datetime(serial) {
+                                    Some(ndt) => {
+                                        let date_str = ndt.format("%Y-%m-%dT%H:%M:%S").to_string();
+                                        serde_json::Value::String(date_str)
+                                    }
+                                    None => {
+                                        tracing::warn!(
+                                            "Failed to convert Excel DateTime serial {} to NaiveDateTime",
+                                            serial
+                                        );
+                                        serde_json::Value::Null
+                                    }
+                                }
+                            } else {
+                                serde_json::Value::String(dt.to_string())
+                            }
+                        }
+                        Data::DateTimeIso(dt) => {
+                            has_any_data = true;
+                            if is_date_field && DateTime::parse_from_rfc3339(dt).is_ok() {
+                                serde_json::Value::String(dt.clone())
+                            } else if is_date_field {
+                                let dt_clean = dt.trim_end_matches('Z').trim_end_matches('z');
                                 match NaiveDateTime::parse_from_str(
                                     dt_clean,
                                     "%Y-%m-%dT%H:%M:%S%.f",
@@ -100,170 +291,131 @@ impl Iterator for ExcelActionIterator {
                                             Ok(ndt) => serde_json::Value::String(
                                                 ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
                                             ),
-                                            Err(_) => serde_json::Value::String(s.clone()),
+                                            Err(_) => {
+                                                match NaiveDateTime::parse_from_str(
+                                                    dt_clean,
+                                                    "%Y-%m-%d %H:%M:%S",
+                                                ) {
+                                                    Ok(ndt) => serde_json::Value::String(
+                                                        ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                                                    ),
+                                                    // ODS date cells without a time are plain dates
+                                                    Err(_) => match NaiveDate::parse_from_str(
+                                                        dt_clean, "%Y-%m-%d",
+                                                    ) {
+                                                        Ok(date) => serde_json::Value::String(
+                                                            date.and_time(NaiveTime::MIN)
+                                                                .format("%Y-%m-%dT%H:%M:%S")
+                                                                .to_string(),
+                                                        ),
+                                                        Err(_) => serde_json::Value::String(
+                                                            dt_clean.to_string(),
+                                                        ),
+                                                    },
+                                                }
+                                            }
                                         }
                                     }
                                 }
-                            }
-                        } else if is_numeric_field {
-                            if let Ok(num) = trimmed.parse::<u32>() {
-                                serde_json::Value::Number(num.into())
                             } else {
-                                serde_json::Value::String(s.clone())
+                                serde_json::Value::String(dt.clone())
                             }
-                        } else {
-                            serde_json::Value::String(s.clone())
                         }
-                    }
-                    Data::Float(f) => {
-                        has_any_data = true;
-                        if is_date_field {
-                            match excel_serial_to_datetime(*f) {
-                                Some(dt) => serde_json::Value::String(
-                                    dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
-                                ),
-                                None => serde_json::Value::Null,
-                            }
-                        } else if is_numeric_field {
-                            serde_json::Value::Number((*f as u32).into())
-                        } else {
-                            serde_json::Value::String(f.to_string())
+                        Data::DurationIso(d) => {
+                            has_any_data = true;
+                            serde_json::Value::String(d.clone())
                         }
-                    }
-                    Data::Int(i) => {
-                        has_any_data = true;
-                        if is_numeric_field {
-                            serde_json::Value::Number((*i as u32).into())
-                        } else {
-                            serde_json::Value::String(i.to_string())
+                        Data::Error(e) => {
+                            has_any_data = true;
+                            serde_json::Value::String(format!("{:?}", e))
                         }
                     }
-                    Data::Bool(b) => {
-                        has_any_data = true;
-                        serde_json::Value::String(b.to_string())
+                } else if is_date_field {
+                    serde_json::Value::Null
+                } else {
+                    serde_json::Value::String(String::new())
+                };
+                record.insert(key.clone(), cell_value);
+            }
+            if !has_any_data {
+                continue;
+            }
+            if let Some((field, header)) = empty_numeric_field {
+                match self.mapping.empty_numeric_cell {
+                    EmptyNumericCell::Skip => {
+                        tracing::warn!(
+                            "Skipping row {} in worksheet '{}' of excel file '{}': the {} cell ('{}') is empty",
+                            row_num_for_error,
+                            self.sheet_name,
+                            self.file_name,
+                            field,
+                            header
+                        );
+                        continue;
                     }
-                    Data::DateTime(dt) => {
-                        has_any_data = true;
-                        if is_date_field {
-                            let serial = dt.as_f64();
-                            match excel_serial_to_datetime(serial) {
-                                Some(ndt) => {
-                                    let date_str = ndt.format("%Y-%m-%dT%H:%M:%S").to_string();
-                                    serde_json::Value::String(date_str)
-                                }
-                                None => {
-                                    tracing::warn!(
-                                        "Failed to convert Excel DateTime serial {} to NaiveDateTime",
-                                        serial
-                                    );
-                                    serde_json::Value::Null
-                                }
-                            }
-                        } else {
-                            serde_json::Value::String(dt.to_string())
-                        }
+                    EmptyNumericCell::Fail => {
+                        return Some(Err(anyhow::anyhow!(
+                            "missing required numeric field {} on row {} in worksheet '{}' of excel file '{}': the '{}' cell is empty. Set EMPTY_NUMERIC_CELL=skip to leave such rows out",
+                            field,
+                            row_num_for_error,
+                            self.sheet_name,
+                            self.file_name,
+                            header
+                        )));
                     }
-                    Data::DateTimeIso(dt) => {
-                        has_any_data = true;
-                        if is_date_field {
-                            let dt_clean = dt.trim_end_matches('Z').trim_end_matches('z');
-                            match NaiveDateTime::parse_from_str(dt_clean, "%Y-%m-%dT%H:%M:%S%.f") {
-                                Ok(ndt) => serde_json::Value::String(
-                                    ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
-                                ),
-                                Err(_) => {
-                                    match NaiveDateTime::parse_from_str(
-                                        dt_clean,
-                                        "%Y-%m-%dT%H:%M:%S",
-                                    ) {
-                                        Ok(ndt) => serde_json::Value::String(
-                                            ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
-                                        ),
-                                        Err(_) => {
-                                            match NaiveDateTime::parse_from_str(
-                                                dt_clean,
-                                                "%Y-%m-%d %H:%M:%S",
-                                            ) {
-                                                Ok(ndt) => serde_json::Value::String(
-                                                    ndt.format("%Y-%m-%dT%H:%M:%S").to_string(),
-                                                ),
-                                                Err(_) => {
-                                                    serde_json::Value::String(dt_clean.to_string())
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            serde_json::Value::String(dt.clone())
+                }
+            }
+            let json_value = serde_json::Value::Object(record.clone());
+            let available_fields: Vec<String> = record.keys().cloned().collect();
+            return match serde_json::from_value::<ActionObject>(json_value.clone()) {
+                Ok(mut action_object) => {
+                    let extras = self
+                        .extra_note_indices
+                        .iter()
+                        .filter_map(|&idx| record.get(&self.keys[idx]))
+                        .filter_map(serde_json::Value::as_str);
+                    self.mapping.append_extra_notes(&mut action_object, extras);
+                    self.mapping
+                        .apply_defaults(&mut action_object, self.has_outcome_column);
+                    action_object.read_numeric_ticket_id();
+                    action_object.read_date();
+                    if action_object.actiondate.is_none() {
+                        tracing::warn!(
+                            "Date field is None after deserialization. Available fields: {:?}",
+                            available_fields
+                        );
+                        if let Some(date_val) = record.get("actiondate") {
+                            tracing::warn!("Date value in JSON was: {:?}", date_val);
                         }
                     }
-                    Data::DurationIso(d) => {
-                        has_any_data = true;
-                        serde_json::Value::String(d.clone())
-                    }
-                    Data::Error(e) => {
-                        has_any_data = true;
-                        serde_json::Value::String(format!("{:?}", e))
-                    }
+                    Some(Ok(action_object))
                 }
-            } else if is_date_field {
-                serde_json::Value::Null
-            } else {
-                serde_json::Value::String(String::new())
-            };
-            let normalized_header = if is_date_field {
-                "actionDate".to_string()
-            } else {
-                header.clone()
-            };
-            record.insert(normalized_header, cell_value);
-        }
-        self.row_num += 1;
-        if !has_any_data {
-            return self.next();
-        }
-        let json_value = serde_json::Value::Object(record.clone());
-        let available_fields: Vec<String> = record.keys().cloned().collect();
-        match serde_json::from_value::<ActionObject>(json_value.clone()) {
-            Ok(action_object) => {
-                if action_object.actiondate.is_none() {
-                    tracing::warn!(
-                        "Date field is None after deserialization. Available fields: {:?}",
-                        available_fields
+                Err(e) => {
+                    let json_str = serde_json::to_string(&json_value)
+                        .unwrap_or_else(|_| "failed to serialize".to_string());
+                    let error_str = e.to_string();
+                    let field_info = format!(
+                        "Available fields: [{}]. Error: {}",
+                        available_fields.join(", "),
+                        error_str
                     );
-                    if let Some(date_val) = record.get("actionDate") {
-                        tracing::warn!("Date value in JSON was: {:?}", date_val);
-                    }
+                    Some(Err(anyhow::anyhow!(
+                        "failed to deserialize row {} in worksheet '{}' of excel file '{}': {} (data: {})",
+                        row_num_for_error,
+                        self.sheet_name,
+                        self.file_name,
+                        field_info,
+                        json_str
+                    )))
                 }
-                Some(Ok(action_object))
-            }
-            Err(e) => {
-                let json_str = serde_json::to_string(&json_value)
-                    .unwrap_or_else(|_| "failed to serialize".to_string());
-                let error_str = e.to_string();
-                let field_info = format!(
-                    "Available fields: [{}]. Error: {}",
-                    available_fields.join(", "),
-                    error_str
-                );
-                Some(Err(anyhow::anyhow!(
-                    "failed to deserialize row {} in worksheet '{}' of excel file '{}': {} (data: {})",
-                    row_num_for_error,
-                    self.sheet_name,
-                    self.file_name,
-                    field_info,
-                    json_str
-                )))
-            }
+            };
         }
     }
 }
 
 impl Excel for Reader {
     fn try_excel_to_action_objects(path: &Path) -> anyhow::Result<Vec<ActionObject>> {
-        let iter = Self::excel_action_iter(path)?;
+        let iter = Self::excel_action_iter(path, &ColumnMapping::default())?;
         let mut output = Vec::new();
         for result in iter {
             output.push(result?);
@@ -271,53 +423,456 @@ impl Excel for Reader {
         Ok(output)
     }
 
-    fn excel_action_iter(path: &Path) -> anyhow::Result<ExcelActionIterator> {
+    fn excel_action_iter(
+        path: &Path,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<ExcelActionIterator> {
         let file_name = path
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown file")
             .to_string();
-        let mut workbook = open_workbook_auto(path)
-            .with_context(|| format!("failed to open excel file: {}", file_name))?;
-        let sheet_names = workbook.sheet_names().to_owned();
-        let first_sheet_name = sheet_names
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("excel file '{}' has no worksheets", file_name))?
-            .clone();
-        let range = workbook
-            .worksheet_range(&first_sheet_name)
-            .with_context(|| {
-                format!(
-                    "failed to read worksheet '{}' from excel file: {}",
-                    first_sheet_name, file_name
-                )
-            })?;
-        let mut rows_iter = range.rows();
-        let headers: Vec<String> = match rows_iter.next() {
-            Some(header_row) => header_row.iter().map(|cell| cell.to_string()).collect(),
-            None => {
-                return Err(anyhow::anyhow!(
-                    "first worksheet '{}' of excel file '{}' has no header row",
-                    first_sheet_name,
-                    file_name
-                ));
-            }
+        let workbook = open_workbook_auto(path).map_err(|e| {
+            // Only read back on failure, to tell an encrypted .xlsx from a corrupt one
+            let encrypted = std::fs::read(path).is_ok_and(|bytes| is_encrypted_ooxml(&bytes));
+            open_error(e, encrypted, &file_name)
+        })?;
+        action_iter_from_workbook(workbook, file_name, mapping)
+    }
+
+    fn excel_action_iter_from_bytes(
+        bytes: Vec<u8>,
+        source_name: &str,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<ExcelActionIterator> {
+        let encrypted = is_encrypted_ooxml(&bytes);
+        let workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+            .map_err(|e| open_error(e, encrypted, source_name))?;
+        action_iter_from_workbook(workbook, source_name.to_string(), mapping)
+    }
+}
+
+fn action_iter_from_workbook<RS: Read + Seek + Send + 'static>(
+    workbook: Sheets<RS>,
+    file_name: String,
+    mapping: &ColumnMapping,
+) -> anyhow::Result<ExcelActionIterator> {
+    let sheet_names = workbook.sheet_names().to_owned();
+    let first_sheet_name = sheet_names
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("excel file '{}' has no worksheets", file_name))?
+        .clone();
+    let rows = spawn_sheet_reader(workbook, first_sheet_name.clone(), file_name.clone());
+    let (header_cells, total_rows) = match rows.recv() {
+        Ok(Ok(SheetEvent::Header { cells, total_rows })) => (cells, total_rows),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => return Err(reader_died(&first_sheet_name, &file_name)),
+        Ok(Ok(SheetEvent::Row(..) | SheetEvent::End)) => {
+            return Err(anyhow::anyhow!(
+                "first worksheet '{}' of excel file '{}' has no header row",
+                first_sheet_name,
+                file_name
+            ));
+        }
+    };
+    let headers: Vec<String> = header_cells
+        .iter()
+        .map(|cell| mapping.map_header(&cell.to_string()))
+        .collect();
+    let has_outcome_column = ColumnMapping::has_outcome_column(headers.iter().map(String::as_str));
+    let extra_note_indices = mapping.extra_note_indices(&headers, &file_name);
+    let keys = headers
+        .iter()
+        .map(|header| ColumnMapping::field_key(header))
+        .collect();
+    Ok(ExcelActionIterator {
+        rows,
+        finished: false,
+        headers,
+        keys,
+        file_name,
+        sheet_name: first_sheet_name,
+        total_rows,
+        mapping: mapping.clone(),
+        has_outcome_column,
+        extra_note_indices,
+    })
+}
+
+/// Reads the sheet on its own thread so rows are pulled on demand. The thread stops as
+/// soon as the iterator is dropped.
+fn spawn_sheet_reader<RS: Read + Seek + Send + 'static>(
+    mut workbook: Sheets<RS>,
+    sheet_name: String,
+    file_name: String,
+) -> Receiver<anyhow::Result<SheetEvent>> {
+    let (tx, rx) = sync_channel(STREAM_BUFFER_ROWS);
+    std::thread::spawn(move || {
+        let result = match &mut workbook {
+            Sheets::Xlsx(xlsx) => stream_xlsx_sheet(xlsx, &sheet_name, &tx),
+            other => stream_sheet_range(other, &sheet_name, &tx),
         };
-        let rows: Vec<Vec<Data>> = rows_iter
-            .map(|row| {
-                let mut row_vec = row.to_vec();
-                while row_vec.len() < headers.len() {
-                    row_vec.push(Data::Empty);
+        let event = result.map(|()| SheetEvent::End).map_err(|e| {
+            e.context(format!(
+                "failed to read worksheet '{}' from excel file: {}",
+                sheet_name, file_name
+            ))
+        });
+        let _ = tx.send(event);
+    });
+    rx
+}
+
+/// The error for a reader thread that stopped without finishing the sheet.
+fn reader_died(sheet_name: &str, file_name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "reading worksheet '{}' of excel file '{}' stopped unexpectedly (the reader thread panicked), so the rest of the sheet was not read",
+        sheet_name,
+        file_name
+    )
+}
+
+/// Sends a row, treating the first one as the header. Returns `false` once the
+/// iterator has been dropped.
+fn send_row(
+    tx: &SyncSender<anyhow::Result<SheetEvent>>,
+    header_row: &mut Option<u32>,
+    last_row: u32,
+    row: u32,
+    cells: Vec<Data>,
+) -> bool {
+    let event = match *header_row {
+        None => {
+            *header_row = Some(row);
+            SheetEvent::Header {
+                cells,
+                total_rows: (last_row > row).then(|| (last_row - row) as usize),
             }
-                row_vec
-            })
+        }
+        Some(header) => SheetEvent::Row((row - header) as usize, cells),
+    };
+    tx.send(Ok(event)).is_ok()
+}
+
+fn stream_xlsx_sheet<RS: Read + Seek>(
+    xlsx: &mut Xlsx<RS>,
+    sheet_name: &str,
+    tx: &SyncSender<anyhow::Result<SheetEvent>>,
+) -> anyhow::Result<()> {
+    let mut reader = xlsx.worksheet_cells_reader(sheet_name)?;
+    let dimensions = reader.dimensions();
+    let first_col = dimensions.start.1;
+    let mut header_row = None;
+    let mut current: Option<(u32, Vec<Data>)> = None;
+    while let Some(cell) = reader.next_cell()? {
+        let (row, col) = cell.get_position();
+        if let Some((current_row, cells)) = current.take_if(|(current_row, _)| *current_row != row)
+            && !send_row(tx, &mut header_row, dimensions.end.0, current_row, cells)
+        {
+            return Ok(());
+        }
+        let (_, cells) = current.get_or_insert_with(|| (row, Vec::new()));
+        let idx = col.saturating_sub(first_col) as usize;
+        if cells.len() <= idx {
+            cells.resize(idx + 1, Data::Empty);
+        }
+        cells[idx] = Data::from(cell.get_value().clone());
+    }
+    if let Some((current_row, cells)) = current {
+        send_row(tx, &mut header_row, dimensions.end.0, current_row, cells);
+    }
+    Ok(())
+}
+
+fn stream_sheet_range<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    sheet_name: &str,
+    tx: &SyncSender<anyhow::Result<SheetEvent>>,
+) -> anyhow::Result<()> {
+    let range = workbook.worksheet_range(sheet_name)?;
+    let mut rows = range.rows();
+    let Some(header) = rows.next() else {
+        return Ok(());
+    };
+    let header_event = SheetEvent::Header {
+        cells: header.to_vec(),
+        total_rows: Some(range.height() - 1),
+    };
+    if tx.send(Ok(header_event)).is_err() {
+        return Ok(());
+    }
+    for (idx, row) in rows.enumerate() {
+        if tx.send(Ok(SheetEvent::Row(idx + 1, row.to_vec()))).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BoolFormat, NumberLocale};
+    use crate::test_support::TempPath;
+
+    const TEST_HEADERS: [&str; 5] = ["requestId", "actionDate", "note", "actionWho", "CFactionId"];
+
+    /// A cell written by [`write_test_workbook`]; `Blank` leaves the cell unwritten.
+    #[derive(Clone, Copy)]
+    enum TestCell<'a> {
+        Blank,
+        Number(f64),
+        Text(&'a str),
+        Bool(bool),
+    }
+
+    /// An xlsx workbook with `headers` as its first row, then `rows`.
+    fn write_test_workbook(headers: &[&str], rows: &[Vec<TestCell>]) -> Vec<u8> {
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let sheet = workbook.add_worksheet();
+        for (col, header) in headers.iter().enumerate() {
+            sheet.write_string(0, col as u16, *header).unwrap();
+        }
+        for (row, cells) in rows.iter().enumerate() {
+            let row = row as u32 + 1;
+            for (col, cell) in cells.iter().enumerate() {
+                let col = col as u16;
+                match cell {
+                    TestCell::Blank => continue,
+                    TestCell::Number(n) => sheet.write_number(row, col, *n),
+                    TestCell::Text(text) => sheet.write_string(row, col, *text),
+                    TestCell::Bool(b) => sheet.write_boolean(row, col, *b),
+                }
+                .unwrap();
+            }
+        }
+        workbook.save_to_buffer().unwrap()
+    }
+
+    #[test]
+    fn parses_workbook_from_bytes() {
+        let bytes = include_bytes!("../../../../fixtures/sample_actions.xlsx").to_vec();
+        let iter = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes,
+            "sample",
+            &ColumnMapping::default(),
+        )
+        .unwrap();
+        assert_eq!(iter.sheet_name(), "Actions");
+        assert_eq!(iter.total_rows(), Some(2));
+        let actions: Vec<ActionObject> = iter.map(|r| r.unwrap()).collect();
+        assert_eq!(actions[0].ticket_id, 2997);
+        assert_eq!(actions[0].action_id(), "1001");
+        assert_eq!(
+            actions[1].actiondate.map(|d| d.naive()),
+            NaiveDate::from_ymd_opt(2024, 1, 15).and_then(|d| d.and_hms_opt(12, 0, 0))
+        );
+    }
+
+    #[test]
+    fn password_protected_workbook_gets_a_clear_error() {
+        let mut bytes = OLE_SIGNATURE.to_vec();
+        bytes.resize(512, 0);
+        bytes.extend("EncryptionInfo".encode_utf16().flat_map(u16::to_le_bytes));
+        // The path entry point only reads the file back once opening it fails
+        let path = TempPath::file("encrypted.xlsx", &bytes);
+        let Err(e) = <Reader as Excel>::excel_action_iter(&path, &ColumnMapping::default()) else {
+            panic!("an encrypted workbook should not open");
+        };
+        assert!(e.to_string().contains("is password-protected"), "{}", e);
+
+        bytes.truncate(512);
+        let Err(e) = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes,
+            "plain_ole.xlsx",
+            &ColumnMapping::default(),
+        ) else {
+            panic!("an empty OLE file should not open");
+        };
+        assert!(e.to_string().contains("failed to open"), "{}", e);
+    }
+
+    #[test]
+    fn reader_thread_dying_mid_sheet_is_an_error() {
+        let bytes = include_bytes!("../../../../fixtures/sample_actions.xlsx").to_vec();
+        let mut iter = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes,
+            "sample",
+            &ColumnMapping::default(),
+        )
+        .unwrap();
+        // Stands in for a reader thread that panicked after the header: no `End` is sent
+        let (tx, rows) = sync_channel(1);
+        drop(tx);
+        iter.rows = rows;
+        let e = iter.next().unwrap().unwrap_err();
+        assert!(e.to_string().contains("stopped unexpectedly"), "{}", e);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn parses_ods_cell_types() {
+        let bytes = include_bytes!("../../../../fixtures/sample_actions.ods").to_vec();
+        let iter = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes,
+            "sample.ods",
+            &ColumnMapping::default(),
+        )
+        .unwrap();
+        assert_eq!(iter.sheet_name(), "Actions");
+        let actions: Vec<ActionObject> = iter.map(|r| r.unwrap()).collect();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].ticket_id, 2997);
+        assert_eq!(actions[0].action_id(), "1001");
+        assert_eq!(actions[0].note, "Sample imported note");
+        assert_eq!(
+            actions[0].actiondate.map(|d| d.naive()),
+            NaiveDate::from_ymd_opt(2024, 1, 15).and_then(|d| d.and_hms_opt(9, 30, 0))
+        );
+        // A date-only cell is read as midnight
+        assert_eq!(
+            actions[1].actiondate.map(|d| d.naive()),
+            NaiveDate::from_ymd_opt(2024, 1, 15).and_then(|d| d.and_hms_opt(0, 0, 0))
+        );
+        assert_eq!(actions[1].action_id(), "1002");
+    }
+
+    #[test]
+    fn empty_numeric_cells_fail_or_skip_the_row() {
+        use TestCell::{Blank, Number, Text};
+        let note = Text("imported note");
+        // Row 2 has no ticket ID, row 3 no action ID
+        let bytes = write_test_workbook(
+            &TEST_HEADERS,
+            &[
+                vec![Number(2997.0), Blank, note, Blank, Number(1001.0)],
+                vec![Blank, Blank, note, Blank, Number(1002.0)],
+                vec![Number(2997.0), Blank, note, Blank, Text(" ")],
+            ],
+        );
+
+        let failed: Vec<_> = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes.clone(),
+            "empty.xlsx",
+            &ColumnMapping::default(),
+        )
+        .unwrap()
+        .collect();
+        assert_eq!(failed.len(), 3);
+        assert_eq!(failed[0].as_ref().unwrap().action_id(), "1001");
+        let missing_ticket = failed[1].as_ref().unwrap_err().to_string();
+        assert!(
+            missing_ticket.starts_with("missing required numeric field ticket_id on row 2"),
+            "{}",
+            missing_ticket
+        );
+        assert!(missing_ticket.contains("'requestId' cell is empty"));
+        let missing_action = failed[2].as_ref().unwrap_err().to_string();
+        assert!(
+            missing_action.starts_with("missing required numeric field action_id on row 3"),
+            "{}",
+            missing_action
+        );
+
+        let mapping = ColumnMapping {
+            empty_numeric_cell: EmptyNumericCell::Skip,
+            ..ColumnMapping::default()
+        };
+        let skipped: Vec<ActionObject> =
+            <Reader as Excel>::excel_action_iter_from_bytes(bytes, "empty.xlsx", &mapping)
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].action_id(), "1001");
+    }
+
+    #[test]
+    fn text_ticket_ids_are_read_with_the_number_locale() {
+        use TestCell::{Blank, Number, Text};
+        let note = Text("imported note");
+        let bytes = write_test_workbook(
+            &TEST_HEADERS,
+            &[
+                vec![Text("1.234"), Blank, note, Blank, Number(1001.0)],
+                vec![Text("1.5"), Blank, note, Blank, Number(1002.0)],
+                vec![Text("2997"), Blank, note, Blank, Number(1003.0)],
+            ],
+        );
+
+        let ticket_ids = |number_locale| {
+            let mapping = ColumnMapping {
+                number_locale,
+                ..ColumnMapping::default()
+            };
+            <Reader as Excel>::excel_action_iter_from_bytes(bytes.clone(), "de.xlsx", &mapping)
+                .unwrap()
+                .map(|r| r.unwrap().ticket_id)
+                .collect::<Vec<_>>()
+        };
+        // A ticket ID that isn't a whole number is left for the processor to reject
+        assert_eq!(ticket_ids(NumberLocale::Plain), vec![0, 0, 2997]);
+        assert_eq!(ticket_ids(NumberLocale::De), vec![1234, 0, 2997]);
+        assert_eq!(NumberLocale::Fr.parse_u32("1\u{202f}234"), Some(1234));
+        assert_eq!(NumberLocale::En.parse_u32("12,34"), None);
+        assert_eq!(NumberLocale::En.parse_u32("1234,567"), None);
+    }
+
+    #[test]
+    fn numeric_ticket_ids_are_kept_as_written() {
+        use TestCell::{Blank, Number, Text};
+        let note = Text("imported note");
+        let bytes = write_test_workbook(
+            &TEST_HEADERS,
+            &[
+                vec![Number(-5.0), Blank, note, Blank, Number(1001.0)],
+                vec![Number(12.7), Blank, note, Blank, Number(1002.0)],
+                vec![Number(5e9), Blank, note, Blank, Number(1003.0)],
+                vec![Number(2997.0), Blank, note, Blank, Number(1004.0)],
+            ],
+        );
+
+        let actions: Vec<ActionObject> = <Reader as Excel>::excel_action_iter_from_bytes(
+            bytes,
+            "tickets.xlsx",
+            &ColumnMapping::default(),
+        )
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+        let references: Vec<&str> = actions
+            .iter()
+            .map(|action| action.source_ticket_id.as_str())
             .collect();
-        Ok(ExcelActionIterator {
-            rows,
-            headers,
-            file_name,
-            sheet_name: first_sheet_name,
-            row_num: 0,
-        })
+        assert_eq!(references, vec!["-5", "12.7", "5000000000", "2997"]);
+        // Only a whole number in range is read as a Halo ticket ID
+        let ticket_ids: Vec<u32> = actions.iter().map(|action| action.ticket_id).collect();
+        assert_eq!(ticket_ids, vec![0, 0, 0, 2997]);
+    }
+
+    #[test]
+    fn boolean_cells_are_written_in_the_bool_format() {
+        use TestCell::{Blank, Bool, Number};
+        let bytes = write_test_workbook(
+            &TEST_HEADERS,
+            &[
+                vec![Number(2997.0), Blank, Bool(true), Blank, Number(1001.0)],
+                vec![Number(2997.0), Blank, Bool(false), Blank, Number(1002.0)],
+            ],
+        );
+
+        let notes = |bool_format| {
+            let mapping = ColumnMapping {
+                bool_format,
+                ..ColumnMapping::default()
+            };
+            <Reader as Excel>::excel_action_iter_from_bytes(bytes.clone(), "flags.xlsx", &mapping)
+                .unwrap()
+                .map(|r| r.unwrap().note)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(notes(BoolFormat::TrueFalse), vec!["true", "false"]);
+        assert_eq!(notes(BoolFormat::YesNo), vec!["Yes", "No"]);
+        assert_eq!(notes(BoolFormat::OneZero), vec!["1", "0"]);
     }
 }
@@ -0,0 +1,234 @@
+use crate::{
+    config::{BoolFormat, Config, EmptyNumericCell, LeadingZeros, NumberLocale},
+    domain::models::action_object::{ActionObject, SOURCE_TIMEZONE_FIELD},
+};
+use tracing::warn;
+
+/// Header name a displaced source `outcome` column is renamed to so it is ignored.
+const DISPLACED_OUTCOME_HEADER: &str = "source_outcome";
+
+/// Run-specific column mapping applied by the file readers before deserialization.
+///
+/// Lets a run source fields from differently named columns without new serde aliases.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMapping {
+    /// Source header to read `outcome` from (`OUTCOME_COLUMN`).
+    pub outcome_column: Option<String>,
+    /// Outcome for rows from files without an outcome column, or with a blank outcome
+    /// (`DEFAULT_OUTCOME`).
+    pub default_outcome: Option<String>,
+    /// Result for rows without a result column, or with a blank result (`DEFAULT_RESULT`).
+    pub default_result: Option<String>,
+    /// Source header holding each row's timezone (`TIMEZONE_COLUMN`).
+    pub timezone_column: Option<String>,
+    /// Source headers whose values are appended to `note`, in order (`EXTRA_NOTE_COLUMNS`).
+    pub extra_note_columns: Vec<String>,
+    /// Put between the note and each appended value (`EXTRA_NOTE_SEPARATOR`).
+    pub extra_note_separator: String,
+    /// Table or view SQLite databases are read from (`SQLITE_TABLE`).
+    pub sqlite_table: Option<String>,
+    /// Whether leading zeros in all-digit action IDs are dropped (`LEADING_ZEROS`).
+    pub leading_zeros: LeadingZeros,
+    /// Whether Excel rows with an empty ticket ID or action ID cell fail or are skipped
+    /// (`EMPTY_NUMERIC_CELL`).
+    pub empty_numeric_cell: EmptyNumericCell,
+    /// Digit grouping of ticket IDs typed as text in Excel files (`NUMBER_LOCALE`).
+    pub number_locale: NumberLocale,
+    /// How boolean Excel cells read as text (`BOOL_FORMAT`).
+    pub bool_format: BoolFormat,
+}
+
+impl ColumnMapping {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            outcome_column: config.outcome_column.clone(),
+            default_outcome: config.default_outcome.clone(),
+            default_result: config.default_result.clone(),
+            timezone_column: config.timezone_column.clone(),
+            extra_note_columns: config.extra_note_columns.clone(),
+            extra_note_separator: config.extra_note_separator.clone(),
+            sqlite_table: config.sqlite_table.clone(),
+            leading_zeros: config.leading_zeros,
+            empty_numeric_cell: config.empty_numeric_cell,
+            number_locale: config.number_locale,
+            bool_format: config.bool_format,
+        }
+    }
+
+    /// The name a source header should be deserialized under.
+    pub fn map_header(&self, header: &str) -> String {
+        let trimmed = header.trim();
+        if let Some(timezone_column) = &self.timezone_column
+            && trimmed.eq_ignore_ascii_case(timezone_column)
+        {
+            return SOURCE_TIMEZONE_FIELD.to_string();
+        }
+        let Some(outcome_column) = &self.outcome_column else {
+            return header.to_string();
+        };
+        if trimmed.eq_ignore_ascii_case(outcome_column) {
+            "outcome".to_string()
+        } else if ActionObject::field_for_header(trimmed) == Some("outcome") {
+            // The configured column wins over a column that would be read as `outcome`
+            DISPLACED_OUTCOME_HEADER.to_string()
+        } else {
+            header.to_string()
+        }
+    }
+
+    /// The key a mapped header is deserialized under: the name of the field it is read
+    /// into, whatever its case, spaces, or underscores, or the header as is when it isn't
+    /// read into one. The readers key rows by this, so every spelling of a header needs no
+    /// alias of its own.
+    pub fn field_key(header: &str) -> String {
+        ActionObject::field_for_header(header).map_or_else(|| header.to_string(), str::to_string)
+    }
+
+    /// Fails a file whose mapped headers have no column for a required field, once, before
+    /// any row is read. The error names each missing field with the header names it is
+    /// read from, and the headers the file has. A file with no header row is left to the
+    /// reader.
+    pub fn check_required_columns(headers: &[String], source: &str) -> anyhow::Result<()> {
+        let missing = ActionObject::missing_required_fields(headers);
+        if headers.is_empty() || missing.is_empty() {
+            return Ok(());
+        }
+        let missing: Vec<String> = missing
+            .into_iter()
+            .map(|field| {
+                format!(
+                    "{} (looked for {})",
+                    field,
+                    ActionObject::header_names_for(field).join(", ")
+                )
+            })
+            .collect();
+        anyhow::bail!(
+            "{} is missing required column(s): {}. Headers found: [{}]. Header names are matched ignoring case, spaces, and underscores; rename the column to one of the names looked for",
+            source,
+            missing.join(", "),
+            headers.join(", ")
+        )
+    }
+
+    /// Whether the already-mapped headers include an outcome column.
+    pub fn has_outcome_column<'a>(mut headers: impl Iterator<Item = &'a str>) -> bool {
+        headers.any(|header| ActionObject::field_for_header(header.trim()) == Some("outcome"))
+    }
+
+    /// Positions in the mapped `headers` of the `EXTRA_NOTE_COLUMNS`, in the configured order.
+    /// Columns the file doesn't have are logged once and contribute nothing.
+    pub fn extra_note_indices(&self, headers: &[String], source: &str) -> Vec<usize> {
+        self.extra_note_columns
+            .iter()
+            .filter_map(|column| {
+                let index = headers
+                    .iter()
+                    .position(|header| header.trim().eq_ignore_ascii_case(column));
+                if index.is_none() {
+                    warn!("Extra note column '{}' not found in {}", column, source);
+                }
+                index
+            })
+            .collect()
+    }
+
+    /// Appends the non-blank `extras` to the row's note, separated by `EXTRA_NOTE_SEPARATOR`.
+    pub fn append_extra_notes<'a>(
+        &self,
+        action: &mut ActionObject,
+        extras: impl Iterator<Item = &'a str>,
+    ) {
+        for extra in extras.map(str::trim).filter(|extra| !extra.is_empty()) {
+            if !action.note.trim().is_empty() {
+                action.note.push_str(&self.extra_note_separator);
+            }
+            action.note.push_str(extra);
+        }
+    }
+
+    /// Applies `DEFAULT_OUTCOME` to a row from a file with no outcome column,
+    /// `DEFAULT_RESULT` to a row without a result, and `LEADING_ZEROS` to its action ID.
+    pub fn apply_defaults(&self, action: &mut ActionObject, has_outcome_column: bool) {
+        if !has_outcome_column && let Some(default_outcome) = &self.default_outcome {
+            action.outcome = default_outcome.clone();
+        }
+        if action.result.is_none() {
+            action.result = self.default_result.clone();
+        }
+        action.action_id.apply_leading_zeros(self.leading_zeros);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::action_object::ActionId;
+
+    #[test]
+    fn configured_outcome_column_replaces_literal_outcome() {
+        let mapping = ColumnMapping {
+            outcome_column: Some("Action Type".to_string()),
+            default_outcome: None,
+            timezone_column: Some("Timezone".to_string()),
+            ..ColumnMapping::default()
+        };
+        assert_eq!(mapping.map_header(" action type "), "outcome");
+        assert_eq!(mapping.map_header("Outcome"), DISPLACED_OUTCOME_HEADER);
+        assert_eq!(mapping.map_header("note"), "note");
+        assert_eq!(mapping.map_header("TIMEZONE"), SOURCE_TIMEZONE_FIELD);
+        assert_eq!(ColumnMapping::default().map_header("Outcome"), "Outcome");
+    }
+
+    #[test]
+    fn outcome_header_spelled_differently_is_still_the_outcome_column() {
+        let mut action = ActionObject::new(1, None, None, "n", "tester", ActionId::new("1"));
+        action.outcome = "Phone Call".to_string();
+        let defaulted = ColumnMapping {
+            default_outcome: Some("Imported Note".to_string()),
+            ..ColumnMapping::default()
+        };
+        let headers = ["note", "Out Come"].map(|header| defaulted.map_header(header));
+        assert!(ColumnMapping::has_outcome_column(
+            headers.iter().map(String::as_str)
+        ));
+        defaulted.apply_defaults(&mut action, true);
+        assert_eq!(action.outcome, "Phone Call");
+
+        let configured = ColumnMapping {
+            outcome_column: Some("Action Type".to_string()),
+            ..defaulted
+        };
+        let headers = ["out_come", "Action Type"].map(|header| configured.map_header(header));
+        assert_eq!(headers[0], DISPLACED_OUTCOME_HEADER);
+        let outcome_keys = headers
+            .iter()
+            .filter(|header| ColumnMapping::field_key(header) == "outcome")
+            .count();
+        assert_eq!(outcome_keys, 1);
+        assert!(ColumnMapping::has_outcome_column(
+            headers.iter().map(String::as_str)
+        ));
+    }
+
+    #[test]
+    fn extra_note_columns_append_in_order_skipping_blanks() {
+        let mapping = ColumnMapping {
+            extra_note_columns: vec!["Detail".to_string(), "Summary".to_string()],
+            extra_note_separator: " | ".to_string(),
+            ..ColumnMapping::default()
+        };
+        let headers = ["summary", "note", "detail"].map(String::from);
+        assert_eq!(mapping.extra_note_indices(&headers, "test.csv"), vec![2, 0]);
+        let partial = ["note", "Summary"].map(String::from);
+        assert_eq!(mapping.extra_note_indices(&partial, "test.csv"), vec![1]);
+
+        let mut action = ActionObject::new(1, None, None, "main", "tester", ActionId::new("1"));
+        mapping.append_extra_notes(&mut action, ["more", "  ", "last"].into_iter());
+        assert_eq!(action.note, "main | more | last");
+
+        let mut empty = ActionObject::new(1, None, None, "", "tester", ActionId::new("2"));
+        mapping.append_extra_notes(&mut empty, ["only"].into_iter());
+        assert_eq!(empty.note, "only");
+    }
+}
@@ -1,9 +1,22 @@
+pub mod archive;
 pub mod csv;
 pub mod excel;
+pub mod mapping;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod watch;
 
 pub use csv::{Csv, CsvActionIterator};
 pub use excel::{Excel, ExcelActionIterator};
-use std::{ffi::OsString, fs::FileType, path::Path};
+pub use mapping::ColumnMapping;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{Sqlite, SqliteActionIterator};
+pub use watch::{InputWatcher, WatchEvent};
+use std::{
+    ffi::{OsStr, OsString},
+    fs::FileType,
+    path::Path,
+};
 
 pub struct Reader;
 
@@ -19,3 +32,32 @@ impl Reader {
         Ok(output)
     }
 }
+
+/// The kinds of input file the importer reads, by extension. Every place that picks files
+/// or a reader goes through [`InputKind::of`], so a new format only has to be added here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Csv,
+    /// `.xlsx`, `.xls`, or `.ods`, read with calamine.
+    Spreadsheet,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// A `.zip` whose CSV and spreadsheet entries are extracted and imported.
+    Archive,
+}
+
+impl InputKind {
+    /// The kind of `path` by its extension, in any case. `None` for a file the importer
+    /// doesn't read.
+    pub fn of(path: &Path) -> Option<Self> {
+        let ext_lower = path.extension().and_then(OsStr::to_str)?.to_lowercase();
+        match ext_lower.as_str() {
+            "csv" => Some(Self::Csv),
+            "xlsx" | "xls" | "ods" => Some(Self::Spreadsheet),
+            #[cfg(feature = "sqlite")]
+            "db" | "sqlite" | "sqlite3" => Some(Self::Sqlite),
+            "zip" => Some(Self::Archive),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    domain::models::action_object::ActionObject,
+    inbound::file::{Reader, mapping::ColumnMapping},
+};
+use anyhow::Context;
+use rusqlite::{Connection, OpenFlags, types::ValueRef};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, sync_channel};
+
+/// Table or view read when `SQLITE_TABLE` isn't set.
+pub const DEFAULT_SQLITE_TABLE: &str = "actions";
+
+/// Rows buffered between the reader thread and the iterator.
+const STREAM_BUFFER_ROWS: usize = 1_000;
+
+pub trait Sqlite {
+    /// Reads every row of the `SQLITE_TABLE` table or view of a SQLite database.
+    fn sqlite_action_iter(
+        path: &Path,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<SqliteActionIterator>;
+}
+
+/// Streams rows of one table or view from a background reader thread.
+///
+/// Columns are read as text and deserialized exactly like CSV fields, so column names go
+/// through the same aliases and mapping as CSV and Excel headers.
+pub struct SqliteActionIterator {
+    rows: Receiver<anyhow::Result<Vec<String>>>,
+    file_name: String,
+    table: String,
+    headers: Vec<String>,
+    header_record: csv::StringRecord,
+    extra_note_indices: Vec<usize>,
+    row_num: usize,
+    total_rows: usize,
+    mapping: ColumnMapping,
+    has_outcome_column: bool,
+}
+
+impl SqliteActionIterator {
+    pub fn total_rows(&self) -> Option<usize> {
+        Some(self.total_rows)
+    }
+
+    /// The table or view the rows come from.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Column names after column mapping.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+}
+
+impl Iterator for SqliteActionIterator {
+    type Item = anyhow::Result<ActionObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let values = self.rows.recv().ok()?;
+        self.row_num += 1;
+        let values = match values {
+            Ok(values) => values,
+            Err(e) => {
+                return Some(Err(e.context(format!(
+                    "failed to read row {} of table '{}' in sqlite database: {}",
+                    self.row_num, self.table, self.file_name
+                ))));
+            }
+        };
+        let record = csv::StringRecord::from(values);
+        match record.deserialize::<ActionObject>(Some(&self.header_record)) {
+            Ok(mut action) => {
+                let extras = self
+                    .extra_note_indices
+                    .iter()
+                    .filter_map(|&idx| record.get(idx));
+                self.mapping.append_extra_notes(&mut action, extras);
+                self.mapping
+                    .apply_defaults(&mut action, self.has_outcome_column);
+                action.read_numeric_ticket_id();
+                action.read_date();
+                Some(Ok(action))
+            }
+            Err(e) => Some(Err(anyhow::anyhow!(
+                "failed to deserialize row {} of table '{}' in sqlite database: {}: {}. Columns: [{}]",
+                self.row_num,
+                self.table,
+                self.file_name,
+                e,
+                self.headers.join(", ")
+            ))),
+        }
+    }
+}
+
+/// A column value as the text a CSV field would hold. NULL reads as an empty field.
+fn value_text(value: ValueRef<'_>, column: &str) -> anyhow::Result<String> {
+    Ok(match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        // Whole numbers stored as REAL (e.g. ticket IDs) read like integers
+        ValueRef::Real(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{:.0}", f),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        ValueRef::Blob(_) => anyhow::bail!("column '{}' holds a blob, not text", column),
+    })
+}
+
+/// Double-quotes a table name for SQL, so any name (including one with spaces or quotes)
+/// is read as a name and never as SQL.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl Sqlite for Reader {
+    fn sqlite_action_iter(
+        path: &Path,
+        mapping: &ColumnMapping,
+    ) -> anyhow::Result<SqliteActionIterator> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown file")
+            .to_string();
+        let table = mapping
+            .sqlite_table
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SQLITE_TABLE.to_string());
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("failed to open sqlite database: {}", file_name))?;
+        let query = format!("SELECT * FROM {}", quote_identifier(&table));
+        let columns: Vec<String> = connection
+            .prepare(&query)
+            .with_context(|| {
+                format!(
+                    "failed to read table '{}' in sqlite database: {}. Set SQLITE_TABLE to the table or view holding the actions",
+                    table, file_name
+                )
+            })?
+            .column_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let total_rows: i64 = connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", quote_identifier(&table)),
+                [],
+                |row| row.get(0),
+            )
+            .with_context(|| {
+                format!(
+                    "failed to count rows of table '{}' in sqlite database: {}",
+                    table, file_name
+                )
+            })?;
+
+        let (tx, rows) = sync_channel(STREAM_BUFFER_ROWS);
+        let thread_columns = columns.clone();
+        // The thread stops as soon as the iterator is dropped
+        std::thread::spawn(move || {
+            let result = (|| -> rusqlite::Result<()> {
+                let mut statement = connection.prepare(&query)?;
+                let mut query_rows = statement.query([])?;
+                while let Some(row) = query_rows.next()? {
+                    let values = thread_columns
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, column)| {
+                            row.get_ref(idx)
+                                .map_err(anyhow::Error::new)
+                                .and_then(|value| value_text(value, column))
+                        })
+                        .collect();
+                    if tx.send(values).is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                let _ = tx.send(Err(anyhow::Error::new(e)));
+            }
+        });
+
+        let headers: Vec<String> = columns
+            .iter()
+            .map(|column| mapping.map_header(column))
+            .collect();
+        let has_outcome_column =
+            ColumnMapping::has_outcome_column(headers.iter().map(String::as_str));
+        let extra_note_indices = mapping.extra_note_indices(&headers, &file_name);
+        Ok(SqliteActionIterator {
+            rows,
+            file_name,
+            table,
+            header_record: headers
+                .iter()
+                .map(|header| ColumnMapping::field_key(header))
+                .collect(),
+            headers,
+            extra_note_indices,
+            row_num: 0,
+            total_rows: usize::try_from(total_rows).unwrap_or_default(),
+            mapping: mapping.clone(),
+            has_outcome_column,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn reads_rows_through_the_header_aliases() {
+        let path = TempPath::new("actions.db");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE \"staged actions\" (requestId REAL, actionDate TEXT, note TEXT, actionWho TEXT, CFactionId INTEGER);
+                 INSERT INTO \"staged actions\" VALUES (1001.0, '2024-01-15 09:30:00', 'first', 'tester', 456);
+                 INSERT INTO \"staged actions\" VALUES (NULL, NULL, 'second', 'tester', 457);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let mapping = ColumnMapping {
+            sqlite_table: Some("staged actions".to_string()),
+            ..ColumnMapping::default()
+        };
+        let iter = <Reader as Sqlite>::sqlite_action_iter(&path, &mapping).unwrap();
+        assert_eq!(iter.total_rows(), Some(2));
+        let rows: Vec<_> = iter.collect();
+        let missing = <Reader as Sqlite>::sqlite_action_iter(&path, &ColumnMapping::default())
+            .err()
+            .unwrap();
+
+        let first = rows[0].as_ref().unwrap();
+        assert_eq!(first.ticket_id, 1001);
+        assert_eq!(first.action_id(), "456");
+        assert!(first.actiondate.is_some());
+        let second = rows[1].as_ref().unwrap_err().to_string();
+        assert!(
+            second.contains("row 2 of table 'staged actions'"),
+            "{}",
+            second
+        );
+        assert!(format!("{:#}", missing).contains("SQLITE_TABLE"));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::inbound::file::InputKind;
+use anyhow::Context;
+use futures::StreamExt;
+use futures::channel::mpsc::{UnboundedReceiver, unbounded};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long a new file's size must hold still before it counts as fully written.
+const SETTLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Something that happened while watching the input directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A supported file was created or written to.
+    File(PathBuf),
+    /// The operator pressed Enter: log the run's totals so far.
+    Summary,
+    /// The operator typed `q`: stop watching and finish the run.
+    Stop,
+}
+
+/// Watches the input directory for new input files (`--watch`), and stdin for operator
+/// commands.
+///
+/// Dropping the watcher stops both. If stdin is closed (e.g. `< /dev/null`), only file
+/// events arrive and the run ends when it is killed or `MAX_RUN_SECS` passes.
+pub struct InputWatcher {
+    _watcher: RecommendedWatcher,
+    events: UnboundedReceiver<WatchEvent>,
+}
+
+impl InputWatcher {
+    pub fn start(dir: &Path) -> anyhow::Result<Self> {
+        let (tx, events) = unbounded();
+        let file_tx = tx.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in event.paths.into_iter().filter(|p| is_input_file(p)) {
+                        let _ = file_tx.unbounded_send(WatchEvent::File(path));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Input directory watcher error: {}", e),
+            })
+            .context("Failed to create the input directory watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch input directory: {}", dir.display()))?;
+
+        // Blocks on stdin, so it gets its own thread rather than a runtime worker
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                let event = match line.trim().to_lowercase().as_str() {
+                    "q" | "quit" => WatchEvent::Stop,
+                    _ => WatchEvent::Summary,
+                };
+                if tx.unbounded_send(event).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// The next event; `None` once the watcher has shut down.
+    pub async fn next(&mut self) -> Option<WatchEvent> {
+        self.events.next().await
+    }
+}
+
+/// Whether a path is a file the importer reads, leaving out hidden files and the `~$`
+/// lock files Office creates next to open workbooks.
+pub fn is_input_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+        return false;
+    };
+    if name.starts_with('.') || name.starts_with("~$") {
+        return false;
+    }
+    InputKind::of(path).is_some()
+}
+
+/// Waits until a file stops growing, so a file still being copied in isn't read half
+/// written. Returns `false` if the file is gone.
+pub async fn wait_until_written(path: &Path) -> bool {
+    let mut last_len = None;
+    loop {
+        let len = match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => return false,
+        };
+        if last_len == Some(len) {
+            return true;
+        }
+        last_len = Some(len);
+        tokio::time::sleep(SETTLE_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_input_files_are_picked_up() {
+        assert!(is_input_file(Path::new("input/actions.CSV")));
+        assert!(is_input_file(Path::new("input/actions.xlsx")));
+        assert!(!is_input_file(Path::new("input/~$actions.xlsx")));
+        assert!(!is_input_file(Path::new("input/.actions.csv.swp")));
+        assert!(!is_input_file(Path::new("input/notes.txt")));
+    }
+}
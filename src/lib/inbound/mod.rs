@@ -1,2 +1,3 @@
 pub mod client;
 pub mod file;
+pub mod report_cache;
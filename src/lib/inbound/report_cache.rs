@@ -0,0 +1,238 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// Directory under `OUTPUT_DIR` the cached reports are kept in.
+const CACHE_DIR_NAME: &str = "report_cache";
+
+/// One report's IDs as fetched, with when they were fetched.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedReport {
+    /// The report URL plus anything else that changes its result, e.g. the chunk range.
+    pub key: String,
+    pub fetched_at: DateTime<Utc>,
+    /// IDs the report listed, counting repeats, for the fetch stats.
+    pub ids_listed: usize,
+    pub ids: Vec<String>,
+}
+
+impl CachedReport {
+    pub fn age(&self) -> Duration {
+        (Utc::now() - self.fetched_at).to_std().unwrap_or_default()
+    }
+
+    /// Reads a cached report file, or every one in a cache directory, whatever its age.
+    pub fn read_all(path: &Path) -> anyhow::Result<Vec<CachedReport>> {
+        let paths = if path.is_dir() {
+            let mut paths = Vec::new();
+            for entry in std::fs::read_dir(path).with_context(|| {
+                format!("Failed to read report cache directory: {}", path.display())
+            })? {
+                let entry_path = entry?.path();
+                if entry_path.extension().is_some_and(|ext| ext == "json") {
+                    paths.push(entry_path);
+                }
+            }
+            paths.sort();
+            paths
+        } else {
+            vec![path.to_path_buf()]
+        };
+        paths
+            .iter()
+            .map(|path| {
+                let text = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read report cache file: {}", path.display())
+                })?;
+                serde_json::from_str(&text).with_context(|| {
+                    format!("Failed to parse report cache file: {}", path.display())
+                })
+            })
+            .collect()
+    }
+}
+
+/// How a fresh report fetch differs from a saved one (`--compare-reports`).
+#[derive(Debug, Default, PartialEq)]
+pub struct ReportDiff {
+    /// IDs listed now but not in the saved set, sorted.
+    pub added: Vec<String>,
+    /// IDs in the saved set but no longer listed, sorted.
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl ReportDiff {
+    pub fn new(saved: &HashSet<String>, current: &HashSet<String>) -> Self {
+        let mut added: Vec<String> = current.difference(saved).cloned().collect();
+        let mut removed: Vec<String> = saved.difference(current).cloned().collect();
+        added.sort();
+        removed.sort();
+        Self {
+            added,
+            removed,
+            unchanged: saved.intersection(current).count(),
+        }
+    }
+
+    /// Writes one `change,action_id` row per added or removed ID.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create report diff file: {}", path.display()))?;
+        writer.write_record(["change", "action_id"])?;
+        for (change, ids) in [("added", &self.added), ("removed", &self.removed)] {
+            for id in ids {
+                writer.write_record([change, id.as_str()])?;
+            }
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write report diff file: {}", path.display()))
+    }
+}
+
+/// Per-report cache of existing-ID sets (`REPORT_CACHE_SECS`), so a run that stops partway
+/// through fetching several reports can be re-run without fetching the finished ones again.
+///
+/// A cached report doesn't include actions created after it was fetched, so the cache is
+/// cleared as soon as a run moves on to posting and only ever spans fetch-only attempts.
+#[derive(Debug, Clone)]
+pub struct ReportCache {
+    dir: PathBuf,
+    max_age: Duration,
+}
+
+impl ReportCache {
+    pub fn new(output_dir: &Path, max_age: Duration) -> Self {
+        Self {
+            dir: output_dir.join(CACHE_DIR_NAME),
+            max_age,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cached report for `key` if one was stored within the max age. An unreadable
+    /// entry is logged and treated as missing.
+    pub fn load(&self, key: &str) -> Option<CachedReport> {
+        let path = self.path_for(key);
+        let text = std::fs::read_to_string(&path).ok()?;
+        let cached: CachedReport = match serde_json::from_str(&text) {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!(
+                    "Ignoring unreadable report cache file {}: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        (cached.key == key && cached.age() <= self.max_age).then_some(cached)
+    }
+
+    /// Saves a report's IDs, replacing any earlier entry for `key`.
+    pub fn store(&self, key: &str, ids: &HashSet<String>, ids_listed: usize) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "Failed to create report cache directory: {}",
+                self.dir.display()
+            )
+        })?;
+        let cached = CachedReport {
+            key: key.to_string(),
+            fetched_at: Utc::now(),
+            ids_listed,
+            ids: ids.iter().cloned().collect(),
+        };
+        let path = self.path_for(key);
+        // Written aside and renamed so a crash mid-write can't leave a truncated entry
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&cached)?)
+            .and_then(|()| std::fs::rename(&tmp_path, &path))
+            .with_context(|| format!("Failed to write report cache file: {}", path.display()))
+    }
+
+    /// Deletes every cached report, returning how many there were.
+    pub fn clear(&self) -> anyhow::Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to read report cache directory: {}",
+                        self.dir.display()
+                    )
+                });
+            }
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                std::fs::remove_file(&path).with_context(|| {
+                    format!("Failed to remove report cache file: {}", path.display())
+                })?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// File names are a hash of the key, since report URLs don't make valid file names.
+    fn path_for(&self, key: &str) -> PathBuf {
+        // FNV-1a, which unlike `DefaultHasher` is stable across Rust releases
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        self.dir.join(format!("{:016x}.json", hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn stores_loads_and_expires_reports() {
+        let output_dir = TempPath::new("report_cache");
+        let cache = ReportCache::new(&output_dir, Duration::from_secs(60));
+        let ids = HashSet::from(["1".to_string(), "2".to_string()]);
+        cache.store("https://halo/report/a", &ids, 3).unwrap();
+
+        let cached = cache.load("https://halo/report/a").unwrap();
+        assert_eq!(cached.ids_listed, 3);
+        assert_eq!(cached.ids.into_iter().collect::<HashSet<_>>(), ids);
+        assert!(cache.load("https://halo/report/b").is_none());
+
+        let expired = ReportCache::new(&output_dir, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expired.load("https://halo/report/a").is_none());
+
+        let saved: HashSet<String> = CachedReport::read_all(cache.dir())
+            .unwrap()
+            .into_iter()
+            .flat_map(|report| report.ids)
+            .collect();
+        let current = HashSet::from(["2".to_string(), "3".to_string()]);
+        assert_eq!(
+            ReportDiff::new(&saved, &current),
+            ReportDiff {
+                added: vec!["3".to_string()],
+                removed: vec!["1".to_string()],
+                unchanged: 1,
+            }
+        );
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.load("https://halo/report/a").is_none());
+    }
+}
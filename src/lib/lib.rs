@@ -2,3 +2,5 @@ pub mod config;
 pub mod domain;
 pub mod inbound;
 pub mod outbound;
+#[cfg(test)]
+mod test_support;
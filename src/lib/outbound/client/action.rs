@@ -1,30 +1,167 @@
 use crate::{
-    config::Config, domain::models::action_object::ActionObject, outbound::client::auth::AuthClient,
+    config::Config,
+    domain::{
+        importer::{
+            error::{ImportError, PartialBatch},
+            sample::RowSampler,
+        },
+        models::action_object::{ActionObject, ActionPayload},
+    },
+    outbound::client::{
+        auth::AuthClient,
+        http::{self, REQUEST_ID_HEADER, RetryStats, new_request_id},
+    },
 };
 use anyhow::Context;
-use reqwest::Client as ReqwestClient;
+use reqwest::{Client as ReqwestClient, Url};
+use serde::Deserialize;
 use std::sync::Arc;
-use tracing::{error, warn};
+use tracing::{Level, debug, error, trace, warn};
+
+/// Seed of the `TRACE_SAMPLE_RATE` sampler. Fixed, so a rerun traces the same action IDs,
+/// and unlike any `SAMPLE_SEED` in practice, so the rows `SAMPLE_RATE` keeps aren't also
+/// the ones traced.
+const TRACE_SAMPLE_SEED: u64 = 0x7ace_5a3b_1e0f_d00d;
 
 #[derive(Debug, Clone)]
 pub struct ActionClient {
     config: Config,
     http_client: ReqwestClient,
     auth_client: Arc<AuthClient>,
+    retry_stats: Arc<RetryStats>,
+    /// Picks the POSTs traced in full for `TRACE_SAMPLE_RATE`.
+    trace_sampler: Option<RowSampler>,
 }
 
 impl ActionClient {
-    pub fn new(config: Config, auth_client: Arc<AuthClient>) -> Self {
+    pub fn new(
+        config: Config,
+        http_client: ReqwestClient,
+        auth_client: Arc<AuthClient>,
+        retry_stats: Arc<RetryStats>,
+    ) -> Self {
+        let trace_sampler = config
+            .trace_sample_rate
+            .map(|rate| RowSampler::new(rate, TRACE_SAMPLE_SEED));
         Self {
             config,
-            http_client: ReqwestClient::new(),
+            http_client,
             auth_client,
+            retry_stats,
+            trace_sampler,
+        }
+    }
+
+    /// Splits actions by the endpoint their outcome maps to, keeping first-seen order.
+    pub fn group_by_endpoint(
+        &self,
+        action_objects: Vec<ActionObject>,
+    ) -> Vec<(Url, Vec<ActionObject>)> {
+        let mut groups: Vec<(Url, Vec<ActionObject>)> = Vec::new();
+        for action in action_objects {
+            let endpoint = self.config.endpoint_for(&action.outcome);
+            match groups.iter_mut().find(|(url, _)| url == endpoint) {
+                Some((_, group)) => group.push(action),
+                None => groups.push((endpoint.clone(), vec![action])),
+            }
+        }
+        groups
+    }
+
+    /// Reads one action back from Halo, custom fields included.
+    pub async fn get_action(
+        &self,
+        ticket_id: u32,
+        action_id: u32,
+    ) -> anyhow::Result<serde_json::Value> {
+        let mut url = self.config.actions_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| anyhow::anyhow!("actions URL cannot have a path"))?
+            .pop_if_empty()
+            .push(&action_id.to_string());
+        url.query_pairs_mut()
+            .append_pair("ticket_id", &ticket_id.to_string())
+            .append_pair("includedetails", "true");
+        let auth_token = self
+            .auth_client
+            .get_valid_token()
+            .await
+            .context("Failed to get valid authentication token")?;
+        let request_id = new_request_id();
+        let request = self
+            .http_client
+            .get(url.clone())
+            .header("Authorization", &auth_token)
+            .header(REQUEST_ID_HEADER, &request_id);
+        let response = http::send(request, self.config.http_trace)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to send GET request for action ID: {} (request ID: {})",
+                    action_id, request_id
+                )
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "failed to get error response".to_string());
+            anyhow::bail!(
+                "GET {} failed: status {}, request ID: {}, error: {}",
+                url,
+                status,
+                request_id,
+                body
+            );
         }
+        response
+            .json()
+            .await
+            .with_context(|| format!("failed to parse action {} from {}", action_id, url))
     }
 
+    /// Whether a POST carrying these actions is logged in full at TRACE.
+    fn is_traced(&self, action_ids: &[String]) -> bool {
+        tracing::enabled!(Level::TRACE)
+            && self
+                .trace_sampler
+                .is_some_and(|sampler| action_ids.iter().any(|id| sampler.keeps(id)))
+    }
+
+    /// The action's entry in a POST body, shaped by the payload settings.
+    fn payload<'a>(&'a self, action: &'a ActionObject) -> ActionPayload<'a> {
+        action
+            .payload(
+                self.config.action_id_custom_field_id,
+                &self.config.omitted_payload_fields,
+            )
+            .with_source_field(self.config.source_file_custom_field_id)
+            .with_leading_zeros(self.config.leading_zeros)
+    }
+
+    /// Size in bytes of the action's entry in a POST body.
+    pub fn payload_bytes(&self, action: &ActionObject) -> usize {
+        serde_json::to_vec(&self.payload(action))
+            .map(|bytes| bytes.len())
+            .unwrap_or(0)
+    }
+
+    /// Posts actions to their mapped endpoints, one request per endpoint.
     pub async fn post_action_objects(
         &self,
         action_objects: Vec<ActionObject>,
+    ) -> anyhow::Result<()> {
+        for (endpoint, group) in self.group_by_endpoint(action_objects) {
+            self.post_action_objects_to(&endpoint, group).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn post_action_objects_to(
+        &self,
+        endpoint: &Url,
+        action_objects: Vec<ActionObject>,
     ) -> anyhow::Result<()> {
         if action_objects.is_empty() {
             return Ok(());
@@ -34,51 +171,69 @@ impl ActionClient {
             .iter()
             .map(|a| a.action_id().to_string())
             .collect();
-        let mut endpoint = self.config.base_resource_url.clone();
-        endpoint.set_path("api/actions");
         let endpoint_str = endpoint.to_string();
+        let payload: Vec<_> = action_objects.iter().map(|a| self.payload(a)).collect();
+        let traced = self.is_traced(&action_ids);
         let mut auth_token = self
             .auth_client
             .get_valid_token()
             .await
             .context("Failed to get valid authentication token")?;
-        
+
         // Outer loop for 504 timeout retries (infinite until success)
         loop {
             // Inner loop for 401 auth retries (max 2 attempts)
             for attempt in 0..2 {
+                let request_id = new_request_id();
+                debug!(
+                    "POST {} for action IDs {:?} (request ID: {})",
+                    endpoint_str, action_ids, request_id
+                );
+                if traced {
+                    trace!(
+                        "Traced POST {} (request ID: {}) payload: {}",
+                        endpoint_str,
+                        request_id,
+                        serde_json::to_string(&payload).unwrap_or_default()
+                    );
+                }
                 let request = self
                     .http_client
                     .post(endpoint.clone())
                     .header("Authorization", &auth_token)
-                    .header("Content-Type", "application/json; charset=utf-8")
-                    .json(&action_objects);
+                    .header("Content-Type", &self.config.request_content_type)
+                    .header(REQUEST_ID_HEADER, &request_id)
+                    .json(&payload);
 
-                let response = match request.send().await.with_context(|| {
+                let response = match http::send(request, self.config.http_trace)
+                    .await
+                    .with_context(|| {
                     format!(
-                        "failed to send POST request for action IDs: {:?} to endpoint: {}",
-                        action_ids, endpoint_str
+                        "failed to send POST request for action IDs: {:?} to endpoint: {} (request ID: {})",
+                        action_ids, endpoint_str, request_id
                     )
                 }) {
                     Ok(resp) => resp,
                     Err(e) => {
                         error!(
-                            "Failed to send POST request for action IDs {:?}: {}",
-                            action_ids, e
+                            "Failed to send POST request for action IDs {:?} (request ID: {}): {}",
+                            action_ids, request_id, e
                         );
                         return Err(e);
                     }
                 };
 
                 let status = response.status();
-                
-                // Handle 504 Gateway Timeout - wait 5 minutes and retry from outer loop
+
+                // Handle 504 Gateway Timeout - wait 1 minute and retry from outer loop
                 if status == reqwest::StatusCode::GATEWAY_TIMEOUT {
                     warn!(
-                        "Received 504 Gateway Timeout for action IDs {:?}, waiting 1 minute before retrying",
-                        action_ids
+                        "Received 504 Gateway Timeout for action IDs {:?} (request ID: {}), waiting 1 minute before retrying",
+                        action_ids, request_id
                     );
-                    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                    let wait = std::time::Duration::from_secs(60);
+                    tokio::time::sleep(wait).await;
+                    self.retry_stats.record_gateway_timeout(wait);
                     auth_token = self
                         .auth_client
                         .get_valid_token()
@@ -86,14 +241,18 @@ impl ActionClient {
                         .context("Failed to refresh authentication token after 504")?;
                     break; // Break inner loop to retry from outer loop
                 }
-                
+
                 if status == reqwest::StatusCode::UNAUTHORIZED && attempt == 0 {
-                    warn!("Received 401 Unauthorized for batch, refreshing token and retrying");
+                    warn!(
+                        "Received 401 Unauthorized for batch (request ID: {}), refreshing token and retrying",
+                        request_id
+                    );
                     auth_token = self
                         .auth_client
-                        .get_valid_token()
+                        .refresh_token()
                         .await
                         .context("Failed to refresh authentication token after 401")?;
+                    self.retry_stats.record_auth_refresh();
                     continue;
                 }
 
@@ -109,34 +268,192 @@ impl ActionClient {
                         })
                         .unwrap_or_else(|_| "failed to get error response".to_string());
                     error!(
-                        "Action object POST failed for batch: status {}, error: {}",
-                        status, error_text
+                        "Action object POST failed for batch: status {}, request ID: {}, error: {}",
+                        status, request_id, error_text
                     );
-                    anyhow::bail!(
-                        "Action object POST failed for batch: status {}, error: {}",
-                        status,
-                        error_text
-                    )
+                    return Err(ImportError::Http {
+                        status: status.as_u16(),
+                        request_id,
+                        body: error_text,
+                    }
+                    .into());
                 }
 
+                if status == reqwest::StatusCode::MULTI_STATUS {
+                    let body = response.text().await.unwrap_or_default();
+                    if traced {
+                        trace!(
+                            "Traced response {} (request ID: {}): {}",
+                            status, request_id, body
+                        );
+                    }
+                    return match read_multi_status(&body, action_ids.len(), &request_id) {
+                        Some(partial) if partial.rejected.is_empty() => Ok(()),
+                        Some(partial) => {
+                            warn!(
+                                "{} for action IDs {:?} (request ID: {})",
+                                partial, action_ids, request_id
+                            );
+                            Err(partial.into())
+                        }
+                        None => {
+                            warn!(
+                                "207 response for action IDs {:?} (request ID: {}) has no result per action, counting the batch as imported: {}",
+                                action_ids, request_id, body
+                            );
+                            Ok(())
+                        }
+                    };
+                }
+
+                if traced {
+                    let body = response.text().await.unwrap_or_default();
+                    trace!(
+                        "Traced response {} (request ID: {}): {}",
+                        status, request_id, body
+                    );
+                }
                 // Success - return from function
                 return Ok(());
             }
             // If we broke out of inner loop due to 504, continue outer loop (retry)
         }
     }
+
+    /// Posts one action with no retries and returns the server's response as-is, for
+    /// reproducing a single failing row. The exact payload is logged at debug.
+    pub async fn post_single(&self, action: &ActionObject) -> anyhow::Result<SingleResponse> {
+        let endpoint = self.config.endpoint_for(&action.outcome);
+        let payload = vec![self.payload(action)];
+        let request_id = new_request_id();
+        debug!(
+            "POST {} (request ID: {}) payload: {}",
+            endpoint,
+            request_id,
+            serde_json::to_string_pretty(&payload)?
+        );
+        let auth_token = self
+            .auth_client
+            .get_valid_token()
+            .await
+            .context("Failed to get valid authentication token")?;
+        let request = self
+            .http_client
+            .post(endpoint.clone())
+            .header("Authorization", &auth_token)
+            .header("Content-Type", &self.config.request_content_type)
+            .header(REQUEST_ID_HEADER, &request_id)
+            .json(&payload);
+        let response = http::send(request, self.config.http_trace)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to send POST request for action ID: {} to endpoint: {} (request ID: {})",
+                    action.action_id(),
+                    endpoint,
+                    request_id
+                )
+            })?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .context("failed to read response body")?;
+        debug!("Response {} (request ID: {}): {}", status, request_id, body);
+        Ok(SingleResponse {
+            endpoint: endpoint.clone(),
+            request_id,
+            status,
+            body,
+        })
+    }
+}
+
+/// One action's entry in a 207 Multi-Status body.
+#[derive(Debug, Deserialize)]
+struct ActionStatus {
+    status: u16,
+    #[serde(default, alias = "message")]
+    error: Option<serde_json::Value>,
+}
+
+/// Reads a 207 body: a JSON array with one `{"status": ..., "error": ...}` entry per posted
+/// action, in the order they were posted. `None` when the body isn't that shape, since
+/// then there's no telling which actions were rejected.
+fn read_multi_status(body: &str, posted: usize, request_id: &str) -> Option<PartialBatch> {
+    let entries: Vec<ActionStatus> = serde_json::from_str(body).ok()?;
+    if entries.len() != posted {
+        return None;
+    }
+    let rejected = entries
+        .into_iter()
+        .enumerate()
+        .filter(|(_, entry)| !(200..300).contains(&entry.status))
+        .map(|(idx, entry)| {
+            let body = match entry.error {
+                Some(serde_json::Value::String(error)) => error,
+                Some(error) => error.to_string(),
+                None => String::new(),
+            };
+            let error = ImportError::Http {
+                status: entry.status,
+                request_id: request_id.to_string(),
+                body,
+            };
+            (idx, error)
+        })
+        .collect();
+    Some(PartialBatch { rejected, posted })
+}
+
+/// Server response to [`ActionClient::post_single`].
+#[derive(Debug, Clone)]
+pub struct SingleResponse {
+    pub endpoint: Url,
+    pub request_id: String,
+    pub status: u16,
+    pub body: String,
+}
+
+impl SingleResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{domain::models::action_object::ActionId, outbound::client::auth::AuthClient};
+    use crate::{
+        domain::models::action_object::ActionId,
+        outbound::client::{auth::AuthClient, http::build_http_client},
+    };
     use std::sync::Arc;
+
+    #[test]
+    fn trace_sample_is_independent_of_the_row_sample() {
+        let row_sampler = RowSampler::new(0.1, 0);
+        let trace_sampler = RowSampler::new(0.05, TRACE_SAMPLE_SEED);
+        let posted: Vec<String> = (0..100_000)
+            .map(|id| id.to_string())
+            .filter(|id| row_sampler.keeps(id))
+            .collect();
+        let traced = posted.iter().filter(|id| trace_sampler.keeps(id)).count();
+        let share = traced as f64 / posted.len() as f64;
+        assert!((0.04..0.06).contains(&share), "traced {:.3}", share);
+    }
+
     #[tokio::test]
     async fn post_action_object() {
         let config = Config::from_env().unwrap();
-        let auth_client = Arc::new(AuthClient::new(config.clone()));
-        let action_client = ActionClient::new(config, auth_client);
+        let http_client = build_http_client(&config).unwrap();
+        let auth_client = Arc::new(AuthClient::new(config.clone(), http_client.clone()));
+        let action_client = ActionClient::new(
+            config,
+            http_client,
+            auth_client,
+            Arc::new(RetryStats::default()),
+        );
         let action_object = ActionObject::new(
             2997,
             None,
@@ -148,4 +465,38 @@ mod tests {
         let response = action_client.post_action_objects(vec![action_object]).await;
         assert!(response.is_ok());
     }
+
+    #[test]
+    fn multi_status_body_lists_the_rejected_actions() {
+        let body = r#"[
+            {"status": 201},
+            {"status": 404, "error": "Ticket not found"},
+            {"status": 201},
+            {"status": 400, "message": {"note": "too long"}}
+        ]"#;
+        let partial = read_multi_status(body, 4, "abc").unwrap();
+        let rejected: Vec<(usize, String)> = partial
+            .rejected
+            .iter()
+            .map(|(idx, error)| (*idx, error.to_string()))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (
+                    1,
+                    "Action object POST failed for batch: status 404, request ID: abc, error: Ticket not found"
+                        .to_string()
+                ),
+                (
+                    3,
+                    r#"Action object POST failed for batch: status 400, request ID: abc, error: {"note":"too long"}"#
+                        .to_string()
+                ),
+            ]
+        );
+        // A body that doesn't list every action can't say which were rejected
+        assert!(read_multi_status(body, 5, "abc").is_none());
+        assert!(read_multi_status("created", 4, "abc").is_none());
+    }
 }
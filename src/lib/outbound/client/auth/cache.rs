@@ -0,0 +1,130 @@
+use crate::outbound::client::auth::token::AuthToken;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// A token as stored on disk, with what it was issued for.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedToken {
+    /// Token URL and client ID, so a token is never reused against another tenant.
+    key: String,
+    #[serde(flatten)]
+    token: AuthToken,
+}
+
+/// Token kept between runs (`TOKEN_CACHE_PATH`), so frequent scheduled runs don't each ask
+/// the token endpoint for a new one.
+///
+/// The file holds a live credential: it is written readable by its owner only, and its
+/// contents are never logged.
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+    key: String,
+}
+
+impl TokenCache {
+    pub fn new(path: &Path, token_url: &str, client_id: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            key: format!("{} {}", token_url, client_id),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The cached token if it was issued for this token URL and client and hasn't expired.
+    /// An unreadable file is logged and treated as missing.
+    pub fn load(&self) -> Option<AuthToken> {
+        let text = std::fs::read_to_string(&self.path).ok()?;
+        let cached: CachedToken = match serde_json::from_str(&text) {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!(
+                    "Ignoring unreadable token cache file {}: {}",
+                    self.path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        (cached.key == self.key && !cached.token.is_expired()).then_some(cached.token)
+    }
+
+    /// Saves a token, replacing the cached one.
+    pub fn store(&self, token: &AuthToken) -> anyhow::Result<()> {
+        let cached = CachedToken {
+            key: self.key.clone(),
+            token: token.clone(),
+        };
+        // Written aside and renamed so a crash mid-write can't leave a truncated file
+        let tmp_path = self.path.with_extension("tmp");
+        write_private(&tmp_path, &serde_json::to_vec(&cached)?)
+            .and_then(|()| std::fs::rename(&tmp_path, &self.path))
+            .with_context(|| format!("Failed to write token cache file: {}", self.path.display()))
+    }
+}
+
+/// Writes a file only its owner can read. Other platforms get their default permissions.
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies when the file is created, so tighten a leftover one too
+        if let Ok(metadata) = std::fs::metadata(path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(0o600);
+            std::fs::set_permissions(path, permissions)?;
+        }
+    }
+    options.open(path)?.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExpiresInUnit;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn reuses_only_a_live_token_for_the_same_client() {
+        let path = TempPath::new("token.json");
+        let cache = TokenCache::new(&path, "https://halo.example/auth/token", "client");
+        let token = AuthToken::new(
+            "secret".to_string(),
+            "Bearer".to_string(),
+            3600,
+            ExpiresInUnit::Seconds,
+        );
+        cache.store(&token).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let loaded = cache.load().map(|token| token.header_value());
+        let other_client =
+            TokenCache::new(&path, "https://halo.example/auth/token", "other").load();
+        let expired = AuthToken::new(
+            "secret".to_string(),
+            "Bearer".to_string(),
+            0,
+            ExpiresInUnit::Seconds,
+        );
+        cache.store(&expired).unwrap();
+        let after_expiry = cache.load();
+
+        assert_eq!(loaded.as_deref(), Some("Bearer secret"));
+        assert!(other_client.is_none());
+        assert!(after_expiry.is_none());
+    }
+}
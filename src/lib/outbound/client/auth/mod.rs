@@ -1,12 +1,19 @@
+pub mod cache;
 pub mod token;
 
-use crate::{config::Config, outbound::client::auth::token::AuthToken};
+use crate::{
+    config::{Config, Grant},
+    outbound::client::{
+        auth::{cache::TokenCache, token::AuthToken},
+        http::{self, REQUEST_ID_HEADER, new_request_id},
+    },
+};
 use anyhow::Context;
 use reqwest::Client as ReqwestClient;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::error;
+use tracing::{error, info, warn};
 
 #[derive(Debug, Deserialize)]
 struct TokenResponse {
@@ -21,12 +28,25 @@ struct ErrorResponse {
     error_description: String,
 }
 
-#[derive(Debug, Serialize)]
-struct TokenRequest {
-    client_id: String,
-    client_secret: String,
-    grant_type: String,
-    scope: String,
+/// Form fields of a token request for the configured grant.
+fn token_form(config: &Config) -> Vec<(&str, &str)> {
+    let mut form = vec![
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+        ("grant_type", config.grant.grant_type()),
+        ("scope", "all"),
+    ];
+    if let Grant::Password { username, password } = &config.grant {
+        form.push(("username", username));
+        form.push(("password", password));
+    }
+    form.extend(
+        config
+            .token_extra_fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+    form
 }
 
 #[derive(Debug)]
@@ -34,14 +54,32 @@ pub struct AuthClient {
     config: Config,
     http_client: ReqwestClient,
     current_token: Arc<Mutex<Option<AuthToken>>>,
+    token_cache: Option<TokenCache>,
 }
 
 impl AuthClient {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config, http_client: ReqwestClient) -> Self {
+        // Password grant tokens belong to the user, so each user gets their own cache entry
+        let identity = match &config.grant {
+            Grant::Password { username, .. } => format!("{} {}", config.client_id, username),
+            _ => config.client_id.clone(),
+        };
+        let token_cache = config
+            .token_cache_path
+            .as_ref()
+            .map(|path| TokenCache::new(path, config.token_url.as_str(), &identity));
+        // A token cached by an earlier run is only picked up once, at startup
+        let cached_token = token_cache.as_ref().and_then(TokenCache::load);
+        if let Some(cache) = &token_cache
+            && cached_token.is_some()
+        {
+            info!("Reusing cached token from {}", cache.path().display());
+        }
         Self {
             config,
-            http_client: ReqwestClient::new(),
-            current_token: Arc::new(Mutex::new(None)),
+            http_client,
+            current_token: Arc::new(Mutex::new(cached_token)),
+            token_cache,
         }
     }
 
@@ -54,31 +92,48 @@ impl AuthClient {
             return Ok(token.header_value());
         }
 
-        let new_token = self.fetch_new_token().await?;
+        let new_token = self.fetch_and_cache_token().await?;
         *token_guard = Some(new_token);
 
         Ok(token_guard.as_ref().unwrap().header_value())
     }
 
-    async fn fetch_new_token(&self) -> anyhow::Result<AuthToken> {
-        let token_request = TokenRequest {
-            client_id: self.config.client_id.clone(),
-            client_secret: self.config.client_secret.clone(),
-            grant_type: "client_credentials".to_string(),
-            scope: "all".to_string(),
-        };
+    /// Replaces the current token with a new one even if it hasn't expired, for when the
+    /// server rejected it (e.g. a cached token that was revoked).
+    pub async fn refresh_token(&self) -> anyhow::Result<String> {
+        let mut token_guard = self.current_token.lock().await;
+        let new_token = self.fetch_and_cache_token().await?;
+        *token_guard = Some(new_token);
 
-        let response = self
+        Ok(token_guard.as_ref().unwrap().header_value())
+    }
+
+    /// Fetches a token and saves it to `TOKEN_CACHE_PATH`; a failed save only loses the
+    /// reuse, so it is logged rather than failing the run.
+    async fn fetch_and_cache_token(&self) -> anyhow::Result<AuthToken> {
+        let token = self.fetch_new_token().await?;
+        if let Some(cache) = &self.token_cache
+            && let Err(e) = cache.store(&token)
+        {
+            warn!("{:#}", e);
+        }
+        Ok(token)
+    }
+
+    async fn fetch_new_token(&self) -> anyhow::Result<AuthToken> {
+        let request_id = new_request_id();
+        let request = self
             .http_client
             .post(self.config.token_url.as_str())
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&token_request)
-            .send()
+            .header(REQUEST_ID_HEADER, &request_id)
+            .form(&token_form(&self.config));
+        let response = http::send(request, self.config.http_trace)
             .await
             .with_context(|| {
                 format!(
-                    "failed to send authentication request to: {}",
-                    self.config.token_url
+                    "failed to send authentication request to: {} (request ID: {})",
+                    self.config.token_url, request_id
                 )
             })?;
 
@@ -86,8 +141,8 @@ impl AuthClient {
 
         if status == reqwest::StatusCode::UNAUTHORIZED {
             error!(
-                "Authentication failed: invalid credentials (status: {})",
-                status
+                "Authentication failed: invalid credentials (status: {}, request ID: {})",
+                status, request_id
             );
             anyhow::bail!("Authentication failed: invalid credentials");
         }
@@ -135,6 +190,7 @@ impl AuthClient {
             token_response.access_token,
             token_response.token_type,
             token_response.expires_in,
+            self.config.expires_in_unit,
         ))
     }
 }
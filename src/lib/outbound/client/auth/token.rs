@@ -1,7 +1,9 @@
+use crate::config::ExpiresInUnit;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AuthToken {
     access_token: String,
     token_type: String,
@@ -10,8 +12,13 @@ pub struct AuthToken {
 }
 
 impl AuthToken {
-    pub fn new(access_token: String, token_type: String, expires_in: i64) -> Self {
-        let expires_at = Utc::now() + Duration::seconds(expires_in);
+    pub fn new(
+        access_token: String,
+        token_type: String,
+        expires_in: i64,
+        unit: ExpiresInUnit,
+    ) -> Self {
+        let expires_at = expiry(Utc::now(), expires_in, unit);
         Self {
             access_token,
             token_type,
@@ -28,3 +35,41 @@ impl AuthToken {
         format!("{} {}", self.token_type, self.access_token)
     }
 }
+
+// Written by hand so the token itself never ends up in a log line
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthToken")
+            .field("access_token", &"<redacted>")
+            .field("token_type", &self.token_type)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// When a token issued at `now` expires, reading `expires_in` in the given unit.
+fn expiry(now: DateTime<Utc>, expires_in: i64, unit: ExpiresInUnit) -> DateTime<Utc> {
+    match unit {
+        ExpiresInUnit::Seconds => now + Duration::seconds(expires_in),
+        ExpiresInUnit::Milliseconds => now + Duration::milliseconds(expires_in),
+        // An out-of-range timestamp is treated as already expired so it gets refreshed
+        ExpiresInUnit::Epoch => DateTime::from_timestamp(expires_in, 0).unwrap_or(now),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_honours_unit() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let in_an_hour = now + Duration::hours(1);
+        assert_eq!(expiry(now, 3600, ExpiresInUnit::Seconds), in_an_hour);
+        assert_eq!(
+            expiry(now, 3_600_000, ExpiresInUnit::Milliseconds),
+            in_an_hour
+        );
+        assert_eq!(expiry(now, 1_700_003_600, ExpiresInUnit::Epoch), in_an_hour);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::config::Config;
+use anyhow::Context;
+use reqwest::{Certificate, Client as ReqwestClient, Identity, RequestBuilder, Response};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Identifies importer traffic in Halo's API logs, e.g. `halo_action_importer/0.1.0`.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Builds the reqwest client shared by the auth, report, action, and webhook clients.
+///
+/// Build it once per run and clone it: clones share one connection pool, so keep-alive
+/// connections are reused across every outbound call.
+pub fn build_http_client(config: &Config) -> anyhow::Result<ReqwestClient> {
+    let mut builder = ReqwestClient::builder().user_agent(USER_AGENT);
+    if let Some(max_idle) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout_secs) = config.pool_idle_timeout_secs {
+        builder = builder.pool_idle_timeout(Duration::from_secs(idle_timeout_secs));
+    }
+    if let Some(identity) = load_identity(config)? {
+        builder = builder.identity(identity);
+    }
+    if let Some(path) = &config.ca_bundle_path {
+        let pem = read_file(path, "CA bundle")?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("invalid PEM in CA bundle: {}", path.display()))?;
+        if certificates.is_empty() {
+            anyhow::bail!("no certificates found in CA bundle: {}", path.display());
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build().context("failed to build HTTP client")
+}
+
+fn read_file(path: &Path, what: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}: {}", what, path.display()))
+}
+
+/// Loads the mutual-TLS client identity, if `CLIENT_CERT_PATH` is set.
+///
+/// `.p12`/`.pfx` files are read as PKCS#12 with `CLIENT_CERT_PASSWORD` (empty when
+/// unset); anything else is a PEM certificate chain paired with the PKCS#8 PEM key at
+/// `CLIENT_KEY_PATH`.
+fn load_identity(config: &Config) -> anyhow::Result<Option<Identity>> {
+    let Some(cert_path) = &config.client_cert_path else {
+        return Ok(None);
+    };
+    let is_pkcs12 = cert_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("p12") || ext.eq_ignore_ascii_case("pfx"));
+    let cert = read_file(cert_path, "client certificate")?;
+    let identity = if is_pkcs12 {
+        let password = config.client_cert_password.as_deref().unwrap_or_default();
+        Identity::from_pkcs12_der(&cert, password).with_context(|| {
+            format!(
+                "failed to load PKCS#12 client certificate: {} (check CLIENT_CERT_PASSWORD)",
+                cert_path.display()
+            )
+        })?
+    } else {
+        let key_path = config.client_key_path.as_ref().with_context(|| {
+            format!(
+                "CLIENT_KEY_PATH is required for PEM client certificate: {}",
+                cert_path.display()
+            )
+        })?;
+        let key = read_file(key_path, "client key")?;
+        Identity::from_pkcs8_pem(&cert, &key).with_context(|| {
+            format!(
+                "failed to load client certificate {} with key {} (the key must be unencrypted PKCS#8 PEM)",
+                cert_path.display(),
+                key_path.display()
+            )
+        })?
+    };
+    Ok(Some(identity))
+}
+
+/// Generates a fresh ID for the `X-Request-Id` header of a single request.
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Sends a request. With `HTTP_TRACE` on, its method, URL, request ID, status, and latency
+/// are logged at debug, the same way for every client. Other headers and bodies are left
+/// out, so tokens and secrets never reach the log.
+pub async fn send(request: RequestBuilder, http_trace: bool) -> reqwest::Result<Response> {
+    if !http_trace {
+        return request.send().await;
+    }
+    let (client, request) = request.build_split();
+    let request = request?;
+    let method = request.method().clone();
+    let url = request.url().clone();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("none")
+        .to_string();
+    let start = Instant::now();
+    let result = client.execute(request).await;
+    let millis = start.elapsed().as_millis();
+    match &result {
+        Ok(response) => tracing::debug!(
+            "HTTP {} {} (request ID: {}) -> {} in {}ms",
+            method,
+            url,
+            request_id,
+            response.status(),
+            millis
+        ),
+        Err(e) => tracing::debug!(
+            "HTTP {} {} (request ID: {}) failed after {}ms: {}",
+            method,
+            url,
+            request_id,
+            millis,
+            e
+        ),
+    }
+    result
+}
+
+/// Retry activity shared by the report and action clients for the run summary.
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    auth_refreshes: AtomicUsize,
+    gateway_timeouts: AtomicUsize,
+    gateway_wait_millis: AtomicU64,
+}
+
+/// Point-in-time copy of [`RetryStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RetryCounts {
+    pub auth_refreshes: usize,
+    pub gateway_timeouts: usize,
+    pub gateway_wait_secs: f64,
+}
+
+impl RetryCounts {
+    /// Every 401 refresh and 504 wait leads to exactly one retried request.
+    pub fn total_retries(&self) -> usize {
+        self.auth_refreshes + self.gateway_timeouts
+    }
+}
+
+impl RetryStats {
+    pub fn record_auth_refresh(&self) {
+        self.auth_refreshes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_gateway_timeout(&self, wait: Duration) {
+        self.gateway_timeouts.fetch_add(1, Ordering::Relaxed);
+        self.gateway_wait_millis
+            .fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> RetryCounts {
+        RetryCounts {
+            auth_refreshes: self.auth_refreshes.load(Ordering::Relaxed),
+            gateway_timeouts: self.gateway_timeouts.load(Ordering::Relaxed),
+            gateway_wait_secs: self.gateway_wait_millis.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod action;
 pub mod auth;
+pub mod http;
+pub mod webhook;
@@ -0,0 +1,102 @@
+use crate::config::{Config, WebhookFormat};
+use crate::outbound::client::http;
+use anyhow::Context;
+use reqwest::{Client as ReqwestClient, Url};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A slow or unreachable webhook shouldn't hold up the end of the run.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run outcome sent to `NOTIFY_WEBHOOK_URL`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunNotification {
+    pub run_id: String,
+    /// `completed`, `completed_with_failures`, `stopped_early`, or `aborted`.
+    pub status: &'static str,
+    pub only_parse: bool,
+    pub processed: usize,
+    pub imported: usize,
+    pub skipped: usize,
+    pub sampled_out: usize,
+    pub skipped_empty_note: usize,
+    pub failed: usize,
+    pub failures_by_category: BTreeMap<&'static str, usize>,
+    pub unreadable_files: usize,
+    pub runtime_secs: f64,
+    /// Why the run stopped early or aborted.
+    pub reason: Option<String>,
+}
+
+impl RunNotification {
+    fn slack_text(&self) -> String {
+        let mode = if self.only_parse {
+            "Parse-only run"
+        } else {
+            "Import run"
+        };
+        let mut text = format!(
+            "{} {} {}: {} processed, {} imported, {} skipped, {} failed in {:.1}s",
+            mode,
+            self.run_id,
+            self.status.replace('_', " "),
+            self.processed,
+            self.imported,
+            self.skipped,
+            self.failed,
+            self.runtime_secs
+        );
+        if self.unreadable_files > 0 {
+            text.push_str(&format!(", {} unreadable file(s)", self.unreadable_files));
+        }
+        if let Some(reason) = &self.reason {
+            text.push_str(&format!("\nReason: {}", reason));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookClient {
+    url: Url,
+    format: WebhookFormat,
+    http_client: ReqwestClient,
+    /// Whether the request is logged (`HTTP_TRACE`).
+    http_trace: bool,
+}
+
+impl WebhookClient {
+    /// A client for `NOTIFY_WEBHOOK_URL`, or `None` when it isn't set.
+    pub fn from_config(config: &Config, http_client: ReqwestClient) -> Option<Self> {
+        let url = config.notify_webhook_url.clone()?;
+        Some(Self {
+            url,
+            format: config.notify_webhook_format,
+            http_client,
+            http_trace: config.http_trace,
+        })
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub async fn send(&self, notification: &RunNotification) -> anyhow::Result<()> {
+        let body = match self.format {
+            WebhookFormat::Json => serde_json::to_value(notification)?,
+            WebhookFormat::Slack => serde_json::json!({ "text": notification.slack_text() }),
+        };
+        let request = self
+            .http_client
+            .post(self.url.clone())
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&body);
+        http::send(request, self.http_trace)
+            .await
+            .context("failed to send webhook request")?
+            .error_for_status()
+            .context("webhook returned an error status")?;
+        Ok(())
+    }
+}
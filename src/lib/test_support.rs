@@ -0,0 +1,59 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A path in the temp directory for a test, removed with everything under it when dropped,
+/// so a failing assertion doesn't leave files behind.
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    /// A path ending in `name` that doesn't exist yet.
+    pub fn new(name: &str) -> Self {
+        // Numbered as well, so tests running at the same time never share a path
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Self(std::env::temp_dir().join(format!(
+            "halo_action_importer_test_{}_{}_{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+            name
+        )))
+    }
+
+    /// A file ending in `name` holding `contents`.
+    pub fn file(name: &str, contents: impl AsRef<[u8]>) -> Self {
+        let path = Self::new(name);
+        std::fs::write(&path.0, contents).unwrap();
+        path
+    }
+
+    /// An empty directory ending in `name`.
+    pub fn dir(name: &str) -> Self {
+        let path = Self::new(name);
+        std::fs::create_dir_all(&path.0).unwrap();
+        path
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            std::fs::remove_dir_all(&self.0).ok();
+        } else {
+            std::fs::remove_file(&self.0).ok();
+        }
+    }
+}